@@ -0,0 +1,164 @@
+//! Conversion of encoded states to JSON.
+
+use primitive_types::U256;
+use scale_info::{MetaType, PortableRegistry, Registry, TypeInfo};
+use scale_value::{Composite, Primitive, Value, ValueDef};
+use serde_json::{Map, Value as Json};
+
+/// Types converted to hex strings instead of byte arrays.
+const HASHES: [&str; 4] = ["ActorId", "CodeId", "MessageId", "ReservationId"];
+
+/// Decodes `encoded` as `T` using its [`TypeInfo`] & converts it to JSON.
+///
+/// Structs become objects, tuples & sequences become arrays, unit variants
+/// become strings, & other variants become objects with a single key.
+/// Hashes & [`U256`] numbers become strings, as well as integers that don't
+/// fit in `u64`/`i64`.
+pub fn to_json<T: TypeInfo + 'static>(encoded: &[u8]) -> Result<Json, String> {
+    let mut registry = Registry::new();
+    let id = registry.register_type(&MetaType::new::<T>()).id;
+    let registry = PortableRegistry::from(registry);
+    let value = scale_value::scale::decode_as_type(&mut &*encoded, id, &registry)
+        .map_err(|error| error.to_string())?;
+
+    Ok(convert(&registry, value))
+}
+
+fn convert(registry: &PortableRegistry, value: Value<u32>) -> Json {
+    let name = registry
+        .resolve(value.context)
+        .and_then(|ty| ty.path.segments.last())
+        .map(String::as_str);
+
+    match name {
+        Some(name) if HASHES.contains(&name) => {
+            let bytes: Vec<_> = integers(&value)
+                .into_iter()
+                .map(|byte| byte as u8)
+                .collect();
+
+            return Json::String(format!("0x{}", hex::encode(bytes)));
+        }
+        Some("U256") => {
+            let mut limbs = [0; 4];
+
+            for (limb, integer) in limbs.iter_mut().zip(integers(&value)) {
+                *limb = integer as u64;
+            }
+
+            return Json::String(U256(limbs).to_string());
+        }
+        _ => {}
+    }
+
+    match value.value {
+        ValueDef::Composite(composite) => convert_composite(registry, composite),
+        ValueDef::Variant(variant) => {
+            if variant.values.is_empty() {
+                Json::String(variant.name)
+            } else {
+                let values = match variant.values {
+                    Composite::Unnamed(mut values) if values.len() == 1 => {
+                        convert(registry, values.remove(0))
+                    }
+                    values => convert_composite(registry, values),
+                };
+
+                Json::Object(Map::from_iter([(variant.name, values)]))
+            }
+        }
+        ValueDef::Primitive(primitive) => match primitive {
+            Primitive::Bool(boolean) => Json::Bool(boolean),
+            Primitive::Char(char) => Json::String(char.into()),
+            Primitive::String(string) => Json::String(string),
+            Primitive::U128(integer) => u64::try_from(integer)
+                .map(Into::into)
+                .unwrap_or_else(|_| Json::String(integer.to_string())),
+            Primitive::I128(integer) => i64::try_from(integer)
+                .map(Into::into)
+                .unwrap_or_else(|_| Json::String(integer.to_string())),
+            Primitive::U256(bytes) | Primitive::I256(bytes) => {
+                Json::String(format!("0x{}", hex::encode(bytes)))
+            }
+        },
+        ValueDef::BitSequence(bits) => Json::Array(bits.iter().map(Json::Bool).collect()),
+    }
+}
+
+fn convert_composite(registry: &PortableRegistry, composite: Composite<u32>) -> Json {
+    match composite {
+        Composite::Named(fields) => Json::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name, convert(registry, value)))
+                .collect(),
+        ),
+        Composite::Unnamed(values) => Json::Array(
+            values
+                .into_iter()
+                .map(|value| convert(registry, value))
+                .collect(),
+        ),
+    }
+}
+
+/// Flattens integers of nested composites, e.g. `[u8; 32]` of [`ActorId`](gstd::ActorId).
+fn integers(value: &Value<u32>) -> Vec<u128> {
+    match &value.value {
+        ValueDef::Composite(composite) => composite.values().flat_map(integers).collect(),
+        ValueDef::Primitive(Primitive::U128(integer)) => vec![*integer],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_json;
+    use gstd::ActorId;
+    use parity_scale_codec::Encode;
+    use primitive_types::U256;
+    use scale_info::TypeInfo;
+    use serde_json::json;
+
+    #[derive(Encode, TypeInfo)]
+    struct State {
+        actor: ActorId,
+        amount: U256,
+        reserve: (u128, u128),
+        kind: Option<Kind>,
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum Kind {
+        Unit,
+        Tuple(u8, bool),
+    }
+
+    #[test]
+    fn conversion() {
+        let state = State {
+            actor: [1; 32].into(),
+            amount: U256::MAX,
+            reserve: (u128::MAX, 1),
+            kind: Some(Kind::Unit),
+        };
+
+        assert_eq!(
+            to_json::<State>(&state.encode()),
+            Ok(json!({
+                "actor": format!("0x{}", "01".repeat(32)),
+                "amount": U256::MAX.to_string(),
+                "reserve": [u128::MAX.to_string(), 1],
+                "kind": { "Some": "Unit" },
+            }))
+        );
+        assert_eq!(
+            to_json::<Option<Kind>>(&Some(Kind::Tuple(5, true)).encode()),
+            Ok(json!({ "Some": { "Tuple": [5, true] } }))
+        );
+        assert_eq!(
+            to_json::<Option<Kind>>(&None::<Kind>.encode()),
+            Ok(json!("None"))
+        );
+    }
+}
@@ -0,0 +1,602 @@
+//! A command-line tool for operating the DEX.
+//!
+//! Commands are sent to a node, or, with `--dry-run` (the `dry-run` feature,
+//! enabled by default), run in a fresh `gtest` system, so they can be checked
+//! before touching the node. The `run` command executes a script of commands
+//! (one per line) in the same session, which is the way to chain commands in
+//! the dry-run mode.
+
+use async_trait::async_trait;
+use clap::{Parser, Subcommand, ValueEnum};
+#[cfg(feature = "dry-run")]
+use dex_client::GtestBackend;
+use dex_client::{Backend, Client, GearApiBackend};
+use dex_factory_io::{AdminAction, Initialize, Proposal};
+use dex_pair_io::{Amount, FeeTier, DEFAULT_FEE_TIER};
+use gclient::{GearApi, WSAddress};
+use gstd::ActorId;
+#[cfg(feature = "dry-run")]
+use gtest::System;
+use std::{collections::HashMap, fs, time::Duration};
+
+mod json;
+
+type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+const FACTORY_WASM: &str = "target/wasm32-unknown-unknown/release/dex_factory.opt.wasm";
+const PAIR_WASM: &str = "target/wasm32-unknown-unknown/release/dex_pair.opt.wasm";
+/// The user that sends messages in the dry-run mode.
+#[cfg(feature = "dry-run")]
+const DRY_RUN_USER: u64 = 42;
+/// A margin (in ms) added to the default ETA of queued admin actions to let
+/// the proposal reach the Factory contract in time.
+const ETA_MARGIN: u64 = 60 * 1000;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Runs commands in a fresh gtest system instead of sending them to a
+    /// node.
+    #[cfg(feature = "dry-run")]
+    #[arg(long)]
+    dry_run: bool,
+    /// The WebSocket address of a node.
+    #[arg(long, default_value = "ws://127.0.0.1")]
+    node: String,
+    /// The port of a node.
+    #[arg(long, default_value_t = 9944)]
+    port: u16,
+    /// The secret URI of the account that signs messages.
+    #[arg(long, default_value = "//Alice")]
+    suri: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// A line of a script.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Uploads the Pair contract's code & deploys the Factory contract.
+    ///
+    /// Sets `$factory` in scripts.
+    Deploy {
+        /// A path to the optimized Factory contract.
+        #[arg(long, default_value = FACTORY_WASM)]
+        factory: String,
+        /// A path to the optimized Pair contract.
+        #[arg(long, default_value = PAIR_WASM)]
+        pair: String,
+        /// A receiver of the protocol fee. Defaults to the zero address
+        /// (disabled).
+        #[arg(long, value_parser = parse_actor)]
+        fee_to: Option<ActorId>,
+        /// Admins of the Factory contract. Defaults to the sender.
+        #[arg(long, value_parser = parse_actor)]
+        admin: Vec<ActorId>,
+        /// A number of admins' confirmations required to approve a proposal.
+        #[arg(long, default_value_t = 1)]
+        threshold: u32,
+        /// A delay (in ms) between queuing an admin action & its execution.
+        #[arg(long, default_value_t = 0)]
+        timelock_delay: u64,
+        /// Initially enabled fee tiers.
+        #[arg(long, default_values_t = [DEFAULT_FEE_TIER])]
+        fee_tier: Vec<FeeTier>,
+    },
+    /// Creates a Pair contract.
+    ///
+    /// Sets `$pair` in scripts.
+    CreatePair {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        #[arg(value_parser = parse_actor)]
+        token_a: ActorId,
+        #[arg(value_parser = parse_actor)]
+        token_b: ActorId,
+        #[arg(long, default_value_t = DEFAULT_FEE_TIER)]
+        fee_tier: FeeTier,
+    },
+    /// Adds liquidity to a Pair contract.
+    AddLiquidity {
+        #[arg(value_parser = parse_actor)]
+        pair: ActorId,
+        amount_a: u128,
+        amount_b: u128,
+        #[arg(long, default_value_t = 0)]
+        amount_a_min: u128,
+        #[arg(long, default_value_t = 0)]
+        amount_b_min: u128,
+        #[command(flatten)]
+        options: TxOptions,
+    },
+    /// Removes liquidity from a Pair contract.
+    RemoveLiquidity {
+        #[arg(value_parser = parse_actor)]
+        pair: ActorId,
+        #[arg(value_parser = parse_amount)]
+        liquidity: Amount,
+        #[arg(long, default_value_t = 0)]
+        amount_a_min: u128,
+        #[arg(long, default_value_t = 0)]
+        amount_b_min: u128,
+        #[command(flatten)]
+        options: TxOptions,
+    },
+    /// Swaps an exact amount of input tokens.
+    Swap {
+        #[arg(value_parser = parse_actor)]
+        pair: ActorId,
+        #[arg(value_enum)]
+        kind: SwapKind,
+        amount_in: u128,
+        #[arg(long, default_value_t = 0)]
+        amount_out_min: u128,
+        #[command(flatten)]
+        options: TxOptions,
+    },
+    /// Proposes to queue setting a receiver of the protocol fee.
+    SetFeeTo {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        #[arg(value_parser = parse_actor)]
+        fee_to: ActorId,
+        #[command(flatten)]
+        eta: Eta,
+    },
+    /// Proposes to queue adding an admin.
+    ///
+    /// Admins replace the former `fee_to_setter`.
+    AddAdmin {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        #[arg(value_parser = parse_actor)]
+        admin: ActorId,
+        #[command(flatten)]
+        eta: Eta,
+    },
+    /// Proposes to queue removing an admin.
+    RemoveAdmin {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        #[arg(value_parser = parse_actor)]
+        admin: ActorId,
+        #[command(flatten)]
+        eta: Eta,
+    },
+    /// Confirms a proposal.
+    Confirm {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        id: u64,
+    },
+    /// Executes a queued admin action after its ETA.
+    Execute {
+        #[arg(value_parser = parse_actor)]
+        factory: ActorId,
+        id: u64,
+    },
+    /// Prints the decoded state of a contract as JSON.
+    State {
+        #[arg(value_enum)]
+        contract: Contract,
+        #[arg(value_parser = parse_actor)]
+        program: ActorId,
+    },
+    /// Waits for a number of seconds (spends blocks in the dry-run mode).
+    Wait { seconds: u32 },
+    /// Runs commands from a script, one per line.
+    ///
+    /// Empty lines & lines starting with `#` are skipped, & `$name` arguments
+    /// are replaced with variables set by previous commands.
+    Run { script: String },
+}
+
+#[derive(clap::Args)]
+struct TxOptions {
+    /// A recipient of tokens. Defaults to the sender.
+    #[arg(long, value_parser = parse_actor)]
+    to: Option<ActorId>,
+    /// A timestamp (in ms) after which the transaction fails.
+    #[arg(long, default_value_t = u64::MAX)]
+    deadline: u64,
+}
+
+#[derive(clap::Args)]
+struct Eta {
+    /// A timestamp (in ms) after which the action can be executed. Defaults
+    /// to the last block's timestamp + the timelock delay + 1 minute.
+    #[arg(long)]
+    eta: Option<u64>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SwapKind {
+    AForB,
+    BForA,
+}
+
+impl From<SwapKind> for dex_pair_io::SwapKind {
+    fn from(kind: SwapKind) -> Self {
+        match kind {
+            SwapKind::AForB => Self::AForB,
+            SwapKind::BForA => Self::BForA,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Contract {
+    Pair,
+    Factory,
+}
+
+/// Parses a hex address or, for users of the dry-run mode, a number.
+fn parse_actor(actor: &str) -> Result<ActorId, String> {
+    if let Ok(user) = actor.parse::<u64>() {
+        return Ok(user.into());
+    }
+
+    let bytes: [u8; 32] = hex::decode(actor.trim_start_matches("0x"))
+        .map_err(|error| error.to_string())?
+        .try_into()
+        .map_err(|_| "an address must have 32 bytes".to_owned())?;
+
+    Ok(bytes.into())
+}
+
+fn parse_amount(amount: &str) -> Result<Amount, String> {
+    Amount::from_dec_str(amount).map_err(|error| error.to_string())
+}
+
+fn to_hex(actor: ActorId) -> String {
+    let bytes: [u8; 32] = actor.into();
+
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Waits in a backend's own time.
+#[async_trait(?Send)]
+trait Wait {
+    async fn wait(&mut self, seconds: u32);
+}
+
+#[async_trait(?Send)]
+impl Wait for GearApiBackend {
+    async fn wait(&mut self, seconds: u32) {
+        tokio::time::sleep(Duration::from_secs(seconds.into())).await;
+    }
+}
+
+#[cfg(feature = "dry-run")]
+#[async_trait(?Send)]
+impl Wait for GtestBackend<'_> {
+    async fn wait(&mut self, seconds: u32) {
+        // A block of gtest lasts 1 second.
+        self.system().spend_blocks(seconds);
+    }
+}
+
+struct Session<B> {
+    client: Client<B>,
+    variables: HashMap<String, String>,
+}
+
+impl<B: Backend + Wait> Session<B> {
+    fn new(backend: B) -> Self {
+        Self {
+            client: Client::new(backend),
+            variables: HashMap::new(),
+        }
+    }
+
+    async fn run(&mut self, command: Command) -> Result<()> {
+        let Command::Run { script } = command else {
+            return self.execute(command).await;
+        };
+
+        for (number, line) in fs::read_to_string(&script)?.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            println!("> {line}");
+
+            let arguments = line
+                .split_whitespace()
+                .map(|argument| match argument.strip_prefix('$') {
+                    Some(name) => self
+                        .variables
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| format!("line {}: `${name}` isn't set", number + 1)),
+                    None => Ok(argument.to_owned()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match Line::try_parse_from(arguments)?.command {
+                Command::Run { .. } => {
+                    return Err(format!("line {}: scripts can't run scripts", number + 1).into())
+                }
+                command => self.execute(command).await?,
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&mut self, command: Command) -> Result<()> {
+        let sender = self.client.backend().actor_id();
+
+        match command {
+            Command::Deploy {
+                factory,
+                pair,
+                fee_to,
+                admin,
+                threshold,
+                timelock_delay,
+                fee_tier,
+            } => {
+                let factory = self
+                    .client
+                    .deploy_factory(
+                        &factory,
+                        &pair,
+                        Initialize {
+                            fee_to: fee_to.unwrap_or_default(),
+                            admins: if admin.is_empty() {
+                                vec![sender]
+                            } else {
+                                admin
+                            },
+                            threshold,
+                            fee_tiers: fee_tier,
+                            timelock_delay,
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+
+                self.set("factory", factory);
+            }
+            Command::CreatePair {
+                factory,
+                token_a,
+                token_b,
+                fee_tier,
+            } => {
+                let pair = self
+                    .client
+                    .create_pair(factory, (token_a, token_b), fee_tier)
+                    .await?;
+
+                self.set("pair", pair);
+            }
+            Command::AddLiquidity {
+                pair,
+                amount_a,
+                amount_b,
+                amount_a_min,
+                amount_b_min,
+                options: TxOptions { to, deadline },
+            } => {
+                let ((amount_a, amount_b), liquidity) = self
+                    .client
+                    .add_liquidity(
+                        pair,
+                        (amount_a, amount_b),
+                        (amount_a_min, amount_b_min),
+                        to.unwrap_or(sender),
+                        deadline,
+                    )
+                    .await?;
+
+                println!("Added {amount_a} A & {amount_b} B tokens for {liquidity} LP tokens.");
+            }
+            Command::RemoveLiquidity {
+                pair,
+                liquidity,
+                amount_a_min,
+                amount_b_min,
+                options: TxOptions { to, deadline },
+            } => {
+                let (amount_a, amount_b) = self
+                    .client
+                    .remove_liquidity(
+                        pair,
+                        liquidity,
+                        (amount_a_min, amount_b_min),
+                        to.unwrap_or(sender),
+                        deadline,
+                    )
+                    .await?;
+
+                println!("Removed {amount_a} A & {amount_b} B tokens.");
+            }
+            Command::Swap {
+                pair,
+                kind,
+                amount_in,
+                amount_out_min,
+                options: TxOptions { to, deadline },
+            } => {
+                let amount_out = self
+                    .client
+                    .swap_exact_in(
+                        pair,
+                        kind.into(),
+                        amount_in,
+                        amount_out_min,
+                        to.unwrap_or(sender),
+                        deadline,
+                    )
+                    .await?;
+
+                println!("Swapped {amount_in} for {amount_out} tokens.");
+            }
+            Command::SetFeeTo {
+                factory,
+                fee_to,
+                eta,
+            } => self.queue(factory, AdminAction::FeeTo(fee_to), eta).await?,
+            Command::AddAdmin {
+                factory,
+                admin,
+                eta,
+            } => {
+                self.queue(factory, AdminAction::AddAdmin(admin), eta)
+                    .await?
+            }
+            Command::RemoveAdmin {
+                factory,
+                admin,
+                eta,
+            } => {
+                self.queue(factory, AdminAction::RemoveAdmin(admin), eta)
+                    .await?
+            }
+            Command::Confirm { factory, id } => {
+                let event = self
+                    .client
+                    .send_to_factory(factory, dex_factory_io::InnerAction::Confirm(id))
+                    .await?;
+
+                println!("{event:?}");
+            }
+            Command::Execute { factory, id } => {
+                let event = self
+                    .client
+                    .send_to_factory(factory, dex_factory_io::InnerAction::Execute(id))
+                    .await?;
+
+                println!("{event:?}");
+            }
+            Command::State { contract, program } => {
+                let state = self.client.backend().read_state(program).await?;
+                let state = match contract {
+                    Contract::Pair => json::to_json::<dex_pair_io::State>(&state),
+                    Contract::Factory => json::to_json::<dex_factory_io::State>(&state),
+                }?;
+
+                println!("{}", serde_json::to_string_pretty(&state)?);
+            }
+            Command::Wait { seconds } => self.client.backend_mut().wait(seconds).await,
+            Command::Run { .. } => unreachable!("scripts are run by `Session::run()`"),
+        }
+
+        Ok(())
+    }
+
+    async fn queue(&mut self, factory: ActorId, action: AdminAction, eta: Eta) -> Result<()> {
+        let eta = match eta.eta {
+            Some(eta) => eta,
+            None => {
+                let dex_factory_io::State { timelock_delay, .. } =
+                    self.client.read_state(factory).await?;
+
+                self.client.backend().block_timestamp().await? + timelock_delay + ETA_MARGIN
+            }
+        };
+        let event = self
+            .client
+            .send_to_factory(
+                factory,
+                dex_factory_io::InnerAction::Propose(Proposal::Queue { action, eta }),
+            )
+            .await?;
+
+        println!("{event:?}");
+
+        Ok(())
+    }
+
+    fn set(&mut self, name: &str, actor: ActorId) {
+        let actor = to_hex(actor);
+
+        println!("${name} = {actor}");
+        self.variables.insert(name.into(), actor);
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let Cli {
+        #[cfg(feature = "dry-run")]
+        dry_run,
+        node,
+        port,
+        suri,
+        command,
+    } = Cli::parse();
+
+    #[cfg(feature = "dry-run")]
+    if dry_run {
+        let system = System::new();
+
+        return Session::new(GtestBackend::new(&system, DRY_RUN_USER))
+            .run(command)
+            .await;
+    }
+
+    let api = GearApi::init_with(WSAddress::new(node, port), suri).await?;
+
+    Session::new(GearApiBackend::new(api).await?)
+        .run(command)
+        .await
+}
+
+#[cfg(all(test, feature = "dry-run"))]
+mod tests {
+    use super::{parse_actor, Command, Session, DRY_RUN_USER};
+    use dex_client::GtestBackend;
+    use gtest::System;
+    use std::{env, fs};
+
+    const FACTORY: &str = "../target/wasm32-unknown-unknown/debug/dex_factory.opt.wasm";
+    const PAIR: &str = "../target/wasm32-unknown-unknown/debug/dex_pair.opt.wasm";
+    const FEE_TO: u64 = 7;
+
+    #[tokio::test]
+    async fn dry_run() {
+        let system = System::new();
+        let mut session = Session::new(GtestBackend::new(&system, DRY_RUN_USER));
+        let script = env::temp_dir().join(format!("dex-cli-{}.txt", std::process::id()));
+
+        fs::write(
+            &script,
+            format!(
+                "# Queues setting `fee_to` & executes it after the default ETA.
+                deploy --factory {FACTORY} --pair {PAIR} --timelock-delay 1000
+
+                set-fee-to $factory {FEE_TO}
+                wait 62
+                execute $factory 0"
+            ),
+        )
+        .unwrap();
+
+        let result = session
+            .run(Command::Run {
+                script: script.to_string_lossy().into_owned(),
+            })
+            .await;
+
+        fs::remove_file(script).unwrap();
+        result.unwrap();
+
+        let factory = parse_actor(&session.variables["factory"]).unwrap();
+        let dex_factory_io::State { fee_to, .. } =
+            session.client.read_state(factory).await.unwrap();
+
+        assert_eq!(fee_to, FEE_TO.into());
+    }
+}
@@ -0,0 +1,272 @@
+use super::Error;
+use async_trait::async_trait;
+use gstd::{ActorId, CodeId};
+
+/// Delivers messages to programs & reads their states for
+/// [`Client`](super::Client).
+///
+/// Implement it to run the client against something other than a node, e.g.
+/// a mock.
+#[async_trait(?Send)]
+pub trait Backend {
+    /// Sends `payload` to `destination` & returns the payload of its reply.
+    ///
+    /// If the program fails to process the message, returns
+    /// [`Error::ErrorReply`].
+    async fn send(&mut self, destination: ActorId, payload: Vec<u8>) -> Result<Vec<u8>, Error>;
+
+    /// Reads the full state of `program`.
+    async fn read_state(&self, program: ActorId) -> Result<Vec<u8>, Error>;
+
+    /// Uploads the code at `path` & returns its [`CodeId`].
+    ///
+    /// Already uploaded code isn't an error.
+    async fn upload_code_by_path(&mut self, path: &str) -> Result<CodeId, Error>;
+
+    /// Creates a program from the code at `path`, initializes it with
+    /// `payload`, & returns its [`ActorId`] with the payload of its reply.
+    async fn upload_program_by_path(
+        &mut self,
+        path: &str,
+        payload: Vec<u8>,
+    ) -> Result<(ActorId, Vec<u8>), Error>;
+
+    /// Returns the timestamp of the last block.
+    async fn block_timestamp(&self) -> Result<u64, Error>;
+
+    /// Returns the [`ActorId`] of the sender of messages.
+    fn actor_id(&self) -> ActorId;
+}
+
+#[cfg(feature = "gclient")]
+pub use gear_api::GearApiBackend;
+
+#[cfg(feature = "gtest")]
+pub use test_system::GtestBackend;
+
+#[cfg(feature = "gclient")]
+mod gear_api {
+    use super::{Backend, Error};
+    use async_trait::async_trait;
+    use gclient::{
+        errors::{Gear, ModuleError},
+        Error as GclientError, EventListener, GearApi,
+    };
+    use gstd::{ActorId, CodeId};
+
+    impl From<gclient::Error> for Error {
+        fn from(error: gclient::Error) -> Self {
+            Self::Backend(error.to_string())
+        }
+    }
+
+    /// Sends messages to a node through [`GearApi`].
+    ///
+    /// The gas limit of every message is estimated with
+    /// [`GearApi::calculate_handle_gas()`] & increased by a margin since
+    /// actions of the contracts await replies from other programs.
+    pub struct GearApiBackend {
+        api: GearApi,
+        listener: EventListener,
+        gas_margin: u64,
+    }
+
+    impl GearApiBackend {
+        /// The default margin (in percent) of estimated gas limits.
+        pub const DEFAULT_GAS_MARGIN: u64 = 100;
+
+        pub async fn new(api: GearApi) -> gclient::Result<Self> {
+            let listener = api.subscribe().await?;
+
+            Ok(Self {
+                api,
+                listener,
+                gas_margin: Self::DEFAULT_GAS_MARGIN,
+            })
+        }
+
+        /// Sets the margin (in percent) added to estimated gas limits.
+        pub fn with_gas_margin(mut self, gas_margin: u64) -> Self {
+            self.gas_margin = gas_margin;
+
+            self
+        }
+
+        pub fn api(&self) -> &GearApi {
+            &self.api
+        }
+
+        fn with_margin(&self, gas_limit: u64) -> u64 {
+            gas_limit.saturating_add(gas_limit.saturating_mul(self.gas_margin) / 100)
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Backend for GearApiBackend {
+        async fn send(&mut self, destination: ActorId, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+            let destination: [u8; 32] = destination.into();
+            let destination = destination.into();
+            let gas_limit = self.with_margin(
+                self.api
+                    .calculate_handle_gas(None, destination, payload.clone(), 0, true)
+                    .await?
+                    .min_limit,
+            );
+
+            let (message_id, _) = self
+                .api
+                .send_message_bytes(destination, payload, gas_limit, 0)
+                .await?;
+            let (_, reply, _) = self.listener.reply_bytes_on(message_id).await?;
+
+            reply.map_err(Error::ErrorReply)
+        }
+
+        async fn read_state(&self, program: ActorId) -> Result<Vec<u8>, Error> {
+            let program: [u8; 32] = program.into();
+
+            Ok(self.api.read_state_bytes(program.into()).await?)
+        }
+
+        async fn upload_code_by_path(&mut self, path: &str) -> Result<CodeId, Error> {
+            let code_id: [u8; 32] = match self.api.upload_code_by_path(path).await {
+                Ok((code_id, _)) => code_id.into(),
+                Err(GclientError::Module(ModuleError::Gear(Gear::CodeAlreadyExists))) => {
+                    sp_core_hashing::blake2_256(&gclient::code_from_os(path)?)
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            Ok(code_id.into())
+        }
+
+        async fn upload_program_by_path(
+            &mut self,
+            path: &str,
+            payload: Vec<u8>,
+        ) -> Result<(ActorId, Vec<u8>), Error> {
+            let code = gclient::code_from_os(path)?;
+            let gas_limit = self.with_margin(
+                self.api
+                    .calculate_upload_gas(None, code.clone(), payload.clone(), 0, true)
+                    .await?
+                    .min_limit,
+            );
+
+            let (message_id, program_id, _) = self
+                .api
+                .upload_program_bytes(
+                    code,
+                    gclient::now_micros().to_le_bytes(),
+                    payload,
+                    gas_limit,
+                    0,
+                )
+                .await?;
+            let (_, reply, _) = self.listener.reply_bytes_on(message_id).await?;
+            let program_id: [u8; 32] = program_id.into();
+
+            Ok((program_id.into(), reply.map_err(Error::ErrorReply)?))
+        }
+
+        async fn block_timestamp(&self) -> Result<u64, Error> {
+            Ok(self.api.last_block_timestamp().await?)
+        }
+
+        fn actor_id(&self) -> ActorId {
+            let account_id: &[u8; 32] = self.api.account_id().as_ref();
+
+            (*account_id).into()
+        }
+    }
+}
+
+#[cfg(feature = "gtest")]
+mod test_system {
+    use super::{Backend, Error};
+    use async_trait::async_trait;
+    use gstd::{ActorId, CodeId};
+    use gtest::{Program, RunResult, System};
+
+    /// Runs messages in [`System`] on behalf of a user.
+    pub struct GtestBackend<'a> {
+        system: &'a System,
+        user: u64,
+    }
+
+    impl<'a> GtestBackend<'a> {
+        pub fn new(system: &'a System, user: u64) -> Self {
+            Self { system, user }
+        }
+
+        pub fn system(&self) -> &'a System {
+            self.system
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Backend for GtestBackend<'_> {
+        async fn send(&mut self, destination: ActorId, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+            let destination: [u8; 32] = destination.into();
+
+            reply(
+                self.system
+                    .get_program(destination)
+                    .send_bytes(self.user, payload),
+            )
+        }
+
+        async fn read_state(&self, program: ActorId) -> Result<Vec<u8>, Error> {
+            let program: [u8; 32] = program.into();
+
+            self.system
+                .get_program(program)
+                .read_state_bytes()
+                .map_err(|error| Error::Backend(format!("{error:?}")))
+        }
+
+        async fn upload_code_by_path(&mut self, path: &str) -> Result<CodeId, Error> {
+            let code_id: [u8; 32] = self.system.submit_code(path).into();
+
+            Ok(code_id.into())
+        }
+
+        async fn upload_program_by_path(
+            &mut self,
+            path: &str,
+            payload: Vec<u8>,
+        ) -> Result<(ActorId, Vec<u8>), Error> {
+            let program = Program::from_file(self.system, path);
+            let program_id: [u8; 32] = program.id().into();
+
+            Ok((
+                program_id.into(),
+                reply(program.send_bytes(self.user, payload))?,
+            ))
+        }
+
+        async fn block_timestamp(&self) -> Result<u64, Error> {
+            Ok(self.system.block_timestamp())
+        }
+
+        fn actor_id(&self) -> ActorId {
+            self.user.into()
+        }
+    }
+
+    fn reply(result: RunResult) -> Result<Vec<u8>, Error> {
+        let reply = result
+            .log()
+            .first()
+            .map(|log| log.payload().to_vec())
+            .ok_or(Error::NoReply)?;
+
+        if result.main_failed() {
+            Err(Error::ErrorReply(
+                String::from_utf8_lossy(&reply).into_owned(),
+            ))
+        } else {
+            Ok(reply)
+        }
+    }
+}
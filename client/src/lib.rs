@@ -0,0 +1,290 @@
+//! A typed client of the Factory & Pair contracts.
+//!
+//! [`Client`] encodes actions, sends them through a [`Backend`], & decodes
+//! their `Result<Event, Error>` replies. [`GearApiBackend`] talks to a node,
+//! & [`GtestBackend`] runs programs in [`gtest`](::gtest) for tests.
+
+use dex_pair_io::{hidden::calculate_out_amount, Amount, FeeTier, SwapKind};
+use gstd::ActorId;
+use parity_scale_codec::{Decode, Encode, Error as CodecError};
+use std::fmt::{self, Display, Formatter};
+
+mod backend;
+
+pub use backend::*;
+
+/// Error variants of failed [`Client`] methods.
+#[derive(Debug)]
+pub enum Error {
+    /// A backend failed to send a message or read a state.
+    Backend(String),
+    /// A program failed to process a message & replied with an error message
+    /// (e.g. it panicked or ran out of gas).
+    ErrorReply(String),
+    /// A program didn't reply to a message.
+    NoReply,
+    /// A reply or a state couldn't be decoded.
+    Codec(CodecError),
+    /// A Pair contract replied with an error.
+    Pair(dex_pair_io::Error),
+    /// The Factory contract replied with an error.
+    Factory(dex_factory_io::Error),
+    /// A program replied with an event that doesn't match a sent action.
+    UnexpectedEvent,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Backend(error) => write!(f, "backend error: {error}"),
+            Self::ErrorReply(error) => write!(f, "program failed: {error}"),
+            Self::NoReply => f.write_str("program didn't reply"),
+            Self::Codec(error) => write!(f, "failed to decode a payload: {error}"),
+            Self::Pair(error) => write!(f, "Pair contract error: {error:?}"),
+            Self::Factory(error) => write!(f, "Factory contract error: {error:?}"),
+            Self::UnexpectedEvent => f.write_str("received an unexpected event"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<CodecError> for Error {
+    fn from(error: CodecError) -> Self {
+        Self::Codec(error)
+    }
+}
+
+/// A client of the Factory & Pair contracts.
+pub struct Client<B> {
+    backend: B,
+}
+
+impl<B: Backend> Client<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Uploads the Pair contract's code from `pair`, deploys the Factory
+    /// contract from `factory` with `initialize`, & returns the Factory
+    /// contract's [`ActorId`].
+    ///
+    /// [`Initialize::pair`](dex_factory_io::Initialize::pair) is replaced with
+    /// the [`CodeId`](gstd::CodeId) of the uploaded code.
+    pub async fn deploy_factory(
+        &mut self,
+        factory: &str,
+        pair: &str,
+        initialize: dex_factory_io::Initialize,
+    ) -> Result<ActorId, Error> {
+        let pair = self.backend.upload_code_by_path(pair).await?;
+        let (factory, reply) = self
+            .backend
+            .upload_program_by_path(
+                factory,
+                dex_factory_io::Initialize { pair, ..initialize }.encode(),
+            )
+            .await?;
+
+        decode::<Result<(), _>>(reply)?.map_err(Error::Factory)?;
+
+        Ok(factory)
+    }
+
+    /// Creates a Pair contract with
+    /// [`InnerAction::CreatePair`](dex_factory_io::InnerAction::CreatePair)
+    /// & returns its [`ActorId`].
+    pub async fn create_pair(
+        &mut self,
+        factory: ActorId,
+        (token_a, token_b): (ActorId, ActorId),
+        fee_tier: FeeTier,
+    ) -> Result<ActorId, Error> {
+        let event = self
+            .send_to_factory(
+                factory,
+                dex_factory_io::InnerAction::CreatePair(token_a, token_b, fee_tier),
+            )
+            .await?;
+
+        if let dex_factory_io::Event::PairCreated { pair_actor, .. } = event {
+            Ok(pair_actor)
+        } else {
+            Err(Error::UnexpectedEvent)
+        }
+    }
+
+    /// Adds liquidity with
+    /// [`InnerAction::AddLiquidity`](dex_pair_io::InnerAction::AddLiquidity)
+    /// & returns added amounts of the A & B tokens with an amount of minted
+    /// liquidity tokens.
+    pub async fn add_liquidity(
+        &mut self,
+        pair: ActorId,
+        (amount_a_desired, amount_b_desired): (u128, u128),
+        (amount_a_min, amount_b_min): (u128, u128),
+        to: ActorId,
+        deadline: u64,
+    ) -> Result<((u128, u128), Amount), Error> {
+        let event = self
+            .send_to_pair(
+                pair,
+                dex_pair_io::InnerAction::AddLiquidity {
+                    amount_a_desired,
+                    amount_b_desired,
+                    amount_a_min,
+                    amount_b_min,
+                    to,
+                    deadline,
+                },
+            )
+            .await?;
+
+        if let dex_pair_io::Event::AddedLiquidity {
+            amount_a,
+            amount_b,
+            liquidity,
+            ..
+        } = event
+        {
+            Ok(((amount_a, amount_b), liquidity))
+        } else {
+            Err(Error::UnexpectedEvent)
+        }
+    }
+
+    /// Removes liquidity with
+    /// [`InnerAction::RemoveLiquidity`](dex_pair_io::InnerAction::RemoveLiquidity)
+    /// & returns returned amounts of the A & B tokens.
+    pub async fn remove_liquidity(
+        &mut self,
+        pair: ActorId,
+        liquidity: Amount,
+        (amount_a_min, amount_b_min): (u128, u128),
+        to: ActorId,
+        deadline: u64,
+    ) -> Result<(u128, u128), Error> {
+        let event = self
+            .send_to_pair(
+                pair,
+                dex_pair_io::InnerAction::RemoveLiquidity {
+                    liquidity,
+                    amount_a_min,
+                    amount_b_min,
+                    to,
+                    deadline,
+                },
+            )
+            .await?;
+
+        if let dex_pair_io::Event::RemovedLiquidity {
+            amount_a, amount_b, ..
+        } = event
+        {
+            Ok((amount_a, amount_b))
+        } else {
+            Err(Error::UnexpectedEvent)
+        }
+    }
+
+    /// Swaps an exact amount of input tokens with
+    /// [`InnerAction::SwapExactTokensForTokens`](dex_pair_io::InnerAction::SwapExactTokensForTokens)
+    /// & returns an amount of output tokens.
+    pub async fn swap_exact_in(
+        &mut self,
+        pair: ActorId,
+        swap_kind: SwapKind,
+        amount_in: u128,
+        amount_out_min: u128,
+        to: ActorId,
+        deadline: u64,
+    ) -> Result<u128, Error> {
+        let event = self
+            .send_to_pair(
+                pair,
+                dex_pair_io::InnerAction::SwapExactTokensForTokens {
+                    swap_kind,
+                    amount_in,
+                    amount_out_min,
+                    to,
+                    deadline,
+                },
+            )
+            .await?;
+
+        if let dex_pair_io::Event::Swap { out_amount, .. } = event {
+            Ok(out_amount)
+        } else {
+            Err(Error::UnexpectedEvent)
+        }
+    }
+
+    /// Reads the full state of a program, e.g.
+    /// [`dex_pair_io::State`] or [`dex_factory_io::State`].
+    pub async fn read_state<T: Decode>(&self, program: ActorId) -> Result<T, Error> {
+        decode(self.backend.read_state(program).await?)
+    }
+
+    /// Returns an amount of output tokens that a swap of `amount_in` would
+    /// receive with the current reserve of a Pair contract.
+    ///
+    /// The quote is calculated from the state, so it doesn't send messages.
+    pub async fn quote(
+        &self,
+        pair: ActorId,
+        swap_kind: SwapKind,
+        amount_in: u128,
+    ) -> Result<u128, Error> {
+        let dex_pair_io::State {
+            reserve, fee_tier, ..
+        } = self.read_state(pair).await?;
+        let reserve = match swap_kind {
+            SwapKind::AForB => reserve,
+            SwapKind::BForA => (reserve.1, reserve.0),
+        };
+
+        calculate_out_amount(amount_in, reserve, fee_tier).map_err(Error::Pair)
+    }
+
+    /// Sends any action to a Pair contract & returns its event.
+    pub async fn send_to_pair(
+        &mut self,
+        pair: ActorId,
+        action: dex_pair_io::InnerAction,
+    ) -> Result<dex_pair_io::Event, Error> {
+        self.send::<_, Result<_, _>>(pair, dex_pair_io::Action::new(action))
+            .await?
+            .map_err(Error::Pair)
+    }
+
+    /// Sends any action to the Factory contract & returns its event.
+    pub async fn send_to_factory(
+        &mut self,
+        factory: ActorId,
+        action: dex_factory_io::InnerAction,
+    ) -> Result<dex_factory_io::Event, Error> {
+        self.send::<_, Result<_, _>>(factory, dex_factory_io::Action::new(action))
+            .await?
+            .map_err(Error::Factory)
+    }
+
+    async fn send<A: Encode, R: Decode>(
+        &mut self,
+        destination: ActorId,
+        action: A,
+    ) -> Result<R, Error> {
+        decode(self.backend.send(destination, action.encode()).await?)
+    }
+}
+
+fn decode<T: Decode>(payload: Vec<u8>) -> Result<T, Error> {
+    Ok(T::decode(&mut payload.as_slice())?)
+}
@@ -0,0 +1,162 @@
+use dex_client::{Client, Error, GtestBackend};
+use dex_pair_io::{SwapKind, DEFAULT_FEE_TIER, MINIMUM_LIQUIDITY};
+use ft_main_io::{FTokenAction, FTokenEvent, InitFToken, LogicAction};
+use gstd::{prelude::*, ActorId};
+use gtest::{Log, Program, System};
+
+const USER: u64 = 5;
+const LIQUIDITY: u128 = 100000;
+const SWAP_AMOUNT: u128 = 1000;
+const DEADLINE: u64 = u64::MAX;
+const FT_MAIN: &str = "../target/wasm32-unknown-unknown/debug/ft_main.opt.wasm";
+const FT_STORAGE: &str = "../target/wasm32-unknown-unknown/debug/ft_storage.opt.wasm";
+const FT_LOGIC: &str = "../target/wasm32-unknown-unknown/debug/ft_logic.opt.wasm";
+const PAIR: &str = "../target/wasm32-unknown-unknown/debug/dex_pair.opt.wasm";
+
+fn actor_id(program: &Program) -> ActorId {
+    let bytes: [u8; 32] = program.id().into();
+
+    bytes.into()
+}
+
+fn initialize_token(system: &System) -> Program {
+    let program = Program::from_file(system, FT_MAIN);
+    let storage_code_id: [u8; 32] = system.submit_code(FT_STORAGE).into();
+    let logic_code_id: [u8; 32] = system.submit_code(FT_LOGIC).into();
+
+    assert!(!program
+        .send(
+            USER,
+            InitFToken {
+                storage_code_hash: storage_code_id.into(),
+                ft_logic_code_hash: logic_code_id.into(),
+            },
+        )
+        .main_failed());
+
+    program
+}
+
+fn mint_and_approve(token: &Program, spender: ActorId, amount: u128) {
+    for (transaction_id, payload) in [
+        LogicAction::Mint {
+            recipient: USER.into(),
+            amount,
+        },
+        LogicAction::Approve {
+            approved_account: spender,
+            amount,
+        },
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        assert!(token
+            .send(
+                USER,
+                FTokenAction::Message {
+                    transaction_id: transaction_id as u64,
+                    payload,
+                },
+            )
+            .contains(&Log::builder().payload(FTokenEvent::Ok)));
+    }
+}
+
+#[tokio::test]
+async fn client() {
+    let system = System::new();
+
+    system.init_logger();
+
+    let token_b = initialize_token(&system);
+    let token_a = initialize_token(&system);
+    let token_pair = (actor_id(&token_a), actor_id(&token_b));
+
+    let factory = Program::from_opt_and_meta_code_with_id(
+        &system,
+        3,
+        dex_factory::WASM_BINARY_OPT.into(),
+        None,
+    );
+    let pair_code_id: [u8; 32] = system.submit_code(PAIR).into();
+
+    assert!(!factory
+        .send(
+            USER,
+            dex_factory_io::Initialize {
+                fee_to: ActorId::zero(),
+                admins: vec![USER.into()],
+                threshold: 1,
+                pair: pair_code_id.into(),
+                fee_tiers: vec![DEFAULT_FEE_TIER],
+                timelock_delay: 0,
+            },
+        )
+        .main_failed());
+
+    let factory = actor_id(&factory);
+    let mut client = Client::new(GtestBackend::new(&system, USER));
+
+    // Errors are decoded into `Error`
+
+    assert!(matches!(
+        client
+            .create_pair(factory, (token_pair.0, token_pair.0), DEFAULT_FEE_TIER)
+            .await,
+        Err(Error::Factory(dex_factory_io::Error::IdenticalTokens))
+    ));
+
+    let pair = client
+        .create_pair(factory, token_pair, DEFAULT_FEE_TIER)
+        .await
+        .unwrap();
+
+    mint_and_approve(&token_a, pair, LIQUIDITY + SWAP_AMOUNT);
+    mint_and_approve(&token_b, pair, LIQUIDITY);
+
+    assert_eq!(
+        client
+            .add_liquidity(pair, (LIQUIDITY, LIQUIDITY), (0, 0), USER.into(), DEADLINE)
+            .await
+            .unwrap(),
+        (
+            (LIQUIDITY, LIQUIDITY),
+            (LIQUIDITY - u128::from(MINIMUM_LIQUIDITY)).into()
+        )
+    );
+
+    // The quote matches the swap
+
+    let quote = client
+        .quote(pair, SwapKind::AForB, SWAP_AMOUNT)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client
+            .swap_exact_in(
+                pair,
+                SwapKind::AForB,
+                SWAP_AMOUNT,
+                quote,
+                USER.into(),
+                DEADLINE
+            )
+            .await
+            .unwrap(),
+        quote
+    );
+
+    let state: dex_pair_io::State = client.read_state(pair).await.unwrap();
+
+    assert_eq!(state.token, token_pair);
+    assert_eq!(state.reserve, (LIQUIDITY + SWAP_AMOUNT, LIQUIDITY - quote));
+
+    assert!(matches!(
+        client
+            .swap_exact_in(pair, SwapKind::AForB, SWAP_AMOUNT, 0, USER.into(), DEADLINE)
+            .await,
+        Err(Error::Pair(dex_pair_io::Error::TransferFailed))
+    ));
+}
@@ -1,8 +1,23 @@
 #![no_std]
 
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use gear_lib::tx_manager;
 use gmeta::{InOut, Metadata};
 use gstd::{errors::Error as GstdError, prelude::*, ActorId, CodeId};
 
+pub use dex_pair_io::{CircuitBreaker, FeeTier, TokenStandard, TxConfig};
+pub use gear_lib::tx_manager::TransactionManagerError;
+
+/// The maximum timelock delay (30 days in ms).
+pub const MAX_TIMELOCK_DELAY: u64 = 30 * 24 * 60 * 60 * 1000;
+/// A period (14 days in ms) after an operation's ETA during which the
+/// operation can be executed. After this period, the operation becomes stale
+/// and can only be canceled.
+pub const GRACE_PERIOD: u64 = 14 * 24 * 60 * 60 * 1000;
+/// The maximum number of Pair contracts returned from
+/// [`InnerAction::GetPairs`].
+pub const MAX_PAIRS_LIMIT: u32 = 100;
+
 pub struct ContractMetadata;
 
 impl Metadata for ContractMetadata {
@@ -21,74 +36,311 @@ impl Metadata for ContractMetadata {
 pub struct State {
     pub pair: CodeId,
     pub fee_to: ActorId,
-    pub fee_to_setter: ActorId,
-    pub pairs: Vec<((ActorId, ActorId), ActorId)>,
+    pub admins: Vec<ActorId>,
+    pub threshold: u32,
+    pub fee_tiers: Vec<FeeTier>,
+    /// Pair contracts keyed by (`token_a`, `token_b`, `fee_tier`).
+    pub pairs: Vec<((ActorId, ActorId, FeeTier), PairRecord)>,
+    pub timelock_delay: u64,
+    /// Queued [`AdminAction`]s with their identifiers.
+    pub queue: Vec<(u64, QueuedOperation)>,
+    /// Pending [`Proposal`]s with their identifiers.
+    pub proposals: Vec<(u64, PendingProposal)>,
+    /// The config of cached transactions of Pair contracts.
+    pub pair_tx_config: TxConfig,
+    /// The limits of swaps of Pair contracts.
+    pub pair_circuit_breaker: CircuitBreaker,
+    /// [`TokenStandard`]s of tokens. Tokens that aren't listed are
+    /// [`TokenStandard::ShardedFt`].
+    pub token_standards: Vec<(ActorId, TokenStandard)>,
+    pub cached_actions: Vec<(ActorId, CachedAction)>,
 }
 
 impl State {
-    pub fn pair(&self, mut pair: (ActorId, ActorId)) -> ActorId {
-        if pair.1 > pair.0 {
-            pair = (pair.1, pair.0);
-        }
+    pub fn token_standard(&self, token: ActorId) -> TokenStandard {
+        self.token_standards
+            .iter()
+            .find_map(|(key, standard)| (*key == token).then_some(*standard))
+            .unwrap_or_default()
+    }
+
+    pub fn pair(&self, pair: (ActorId, ActorId), fee_tier: FeeTier) -> ActorId {
+        let (token_a, token_b) = sort_pair(pair);
 
         self.pairs
             .iter()
-            .find_map(|(existing_pair, actor)| (*existing_pair == pair).then_some(*actor))
+            .find_map(|(key, record)| {
+                (*key == (token_a, token_b, fee_tier)).then_some(record.pair_actor)
+            })
             .unwrap_or_default()
     }
+
+    /// Returns all Pair contracts for a given token pair with their
+    /// [`FeeTier`]s.
+    pub fn pairs_for(&self, pair: (ActorId, ActorId)) -> Vec<(FeeTier, ActorId)> {
+        let pair = sort_pair(pair);
+        let mut pairs: Vec<_> = self
+            .pairs
+            .iter()
+            .filter_map(|((token_a, token_b, fee_tier), record)| {
+                ((*token_a, *token_b) == pair).then_some((*fee_tier, record.pair_actor))
+            })
+            .collect();
+
+        pairs.sort_unstable();
+
+        pairs
+    }
+
+    /// Returns Pair contracts created or registered by `creator`.
+    pub fn pairs_by_creator(
+        &self,
+        creator: ActorId,
+    ) -> Vec<((ActorId, ActorId, FeeTier), PairRecord)> {
+        self.filter_pairs(|record| record.creator == creator)
+    }
+
+    /// Returns Pair contracts created or registered within a given time range
+    /// (in ms, inclusive).
+    pub fn pairs_created_between(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Vec<((ActorId, ActorId, FeeTier), PairRecord)> {
+        self.filter_pairs(|record| (from..=to).contains(&record.created_at))
+    }
+
+    fn filter_pairs(
+        &self,
+        filter: impl Fn(&PairRecord) -> bool,
+    ) -> Vec<((ActorId, ActorId, FeeTier), PairRecord)> {
+        let mut pairs: Vec<_> = self
+            .pairs
+            .iter()
+            .filter(|(_, record)| filter(record))
+            .copied()
+            .collect();
+
+        pairs.sort_unstable_by_key(|(_, record)| (record.created_at_block, record.pair_actor));
+
+        pairs
+    }
 }
 
-/// Initializes the contract.
+/// A registry entry of a Pair contract.
 #[derive(
-    Default, Encode, Decode, TypeInfo, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash,
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
 )]
+pub struct PairRecord {
+    pub pair_actor: ActorId,
+    /// An actor that sent [`InnerAction::CreatePair`], or, for a Pair
+    /// contract added with [`AdminAction::RegisterPair`], an actor that
+    /// executed this action.
+    pub creator: ActorId,
+    /// The height of a block in which the Pair contract was added to the
+    /// registry.
+    pub created_at_block: u32,
+    /// The timestamp (in ms) of a block in which the Pair contract was added
+    /// to the registry.
+    pub created_at: u64,
+    /// [`CodeId`] from which the Pair contract was created.
+    ///
+    /// [`None`] for a Pair contract added with [`AdminAction::RegisterPair`]
+    /// since its code can't be verified.
+    pub code_id: Option<CodeId>,
+}
+
+/// Sorts a token pair in the order used for the Factory contract's keys.
+pub fn sort_pair(pair: (ActorId, ActorId)) -> (ActorId, ActorId) {
+    if pair.1 > pair.0 {
+        (pair.1, pair.0)
+    } else {
+        pair
+    }
+}
+
+/// Returns the salt with which the Factory contract creates a Pair contract.
+///
+/// The salt doesn't depend on the order of tokens in `pair`.
+pub fn pair_salt(factory: ActorId, pair: (ActorId, ActorId), fee_tier: FeeTier) -> Vec<u8> {
+    (factory, sort_pair(pair), fee_tier).encode()
+}
+
+/// Computes [`ActorId`] of a Pair contract created by the Factory contract
+/// without querying the latter.
+///
+/// - `factory` - [`ActorId`] of the Factory contract.
+/// - `pair_code` - the Pair contract's [`CodeId`] set in
+/// [`Initialize::pair`].
+pub fn pair_actor_id(
+    factory: ActorId,
+    pair_code: CodeId,
+    pair: (ActorId, ActorId),
+    fee_tier: FeeTier,
+) -> ActorId {
+    // Mirrors how the runtime generates an identifier of a created program.
+    let mut hasher = Blake2b::<U32>::new();
+
+    hasher.update(b"program");
+    hasher.update(pair_code.as_ref());
+    hasher.update(pair_salt(factory, pair, fee_tier));
+
+    ActorId::new(hasher.finalize().into())
+}
+
+/// Initializes the contract.
+#[derive(Default, Encode, Decode, TypeInfo, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub struct Initialize {
     /// The actor that'll receive the 0.05% commission per trade.
     ///
     /// If it'll equal to [`ActorId::zero()`], the commission will be disabled.
     pub fee_to: ActorId,
-    /// The actor that'll have the right to set `fee_to` & `fee_to_setter`.
-    pub fee_to_setter: ActorId,
+    /// The admin set whose members propose & confirm [`Proposal`]s.
+    ///
+    /// Mustn't contain duplicates & [`ActorId::zero()`].
+    pub admins: Vec<ActorId>,
+    /// A number of distinct admins' confirmations required to approve a
+    /// [`Proposal`].
+    ///
+    /// Must be > 0 & <= the number of `admins`.
+    pub threshold: u32,
     /// The identifier of the Pair contract.
     pub pair: CodeId,
+    /// Initially enabled [`FeeTier`]s with which Pair contracts can be
+    /// created.
+    ///
+    /// Mustn't contain duplicates, & each tier must be less than
+    /// [`FEE_DENOMINATOR`](dex_pair_io::FEE_DENOMINATOR).
+    pub fee_tiers: Vec<FeeTier>,
+    /// A minimum delay (in ms) between queuing an [`AdminAction`] and its
+    /// execution.
+    ///
+    /// Mustn't be more than [`MAX_TIMELOCK_DELAY`].
+    pub timelock_delay: u64,
 }
 
-/// Sends the contract info about what it should do.
 #[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
-pub enum Action {
+pub enum InnerAction {
     /// Creates a Pair contract instance from a pair of
     /// (SFT)[https://github.com/gear-dapps/sharded-fungible-token]
-    /// [`ActorId`]s.
+    /// [`ActorId`]s with a given swap fee.
+    ///
+    /// Several Pair contracts can exist for the same SFT [`ActorId`]s, one
+    /// per [`FeeTier`].
     ///
     /// # Requirements:
     /// - [`ActorId`]s mustn't be identical.
     /// - [`ActorId`]s mustn't equal to [`ActorId::zero()`].
-    /// - Pair with given [`ActorId`]s mustn't already exist.
+    /// - [`FeeTier`] must be enabled.
+    /// - Pair with given [`ActorId`]s & [`FeeTier`] mustn't already exist.
+    ///
+    /// If the creation was interrupted (e.g. by running out of gas), it can be
+    /// resumed by sending the same action with
+    /// [`ActionKind::Retry`](tx_manager::ActionKind::Retry) from the same
+    /// actor. A Pair contract that was already created by the interrupted
+    /// attempt is then adopted instead of creating a duplicate.
     ///
     /// On success, replies with [`Event::PairCreated`].
-    CreatePair(ActorId, ActorId),
+    CreatePair(ActorId, ActorId, FeeTier),
 
-    /// Sets [`ActorId`] of the fee receiver (`fee_to`).
+    /// Creates a Pair contract like [`InnerAction::CreatePair`] & adds the
+    /// initial liquidity to it from [`msg::source`](gstd::msg::source)'s
+    /// fungible tokens in one flow.
     ///
-    /// Setting the fee receiver to [`ActorId::zero()`] disables the 0.05%
-    /// commission.
+    /// Until the initial liquidity is added, the Pair contract doesn't let
+    /// anyone else set its initial price (see
+    /// [`Initialize::seeder`](dex_pair_io::Initialize::seeder)).
+    ///
+    /// Before sending this action, [`msg::source`](gstd::msg::source) must
+    /// approve the Pair contract to transfer given amounts in both SFT
+    /// contracts. The Pair contract's [`ActorId`] can be computed with
+    /// [`pair_actor_id()`].
+    ///
+    /// If this action is interrupted, it can be resumed with
+    /// [`ActionKind::Retry`](tx_manager::ActionKind::Retry). If adding the
+    /// liquidity fails, the Pair contract stays created, but stops awaiting
+    /// the initial liquidity from [`msg::source`](gstd::msg::source) (see
+    /// [`InnerAction::ClearSeeder`](dex_pair_io::InnerAction::ClearSeeder)),
+    /// so anyone can add it.
     ///
     /// # Requirements:
-    /// - [`msg::source`](gstd::msg::source) must have the right to set the fee
-    /// receiver (must be equal to `fee_to_setter`).
+    /// - The same as for [`InnerAction::CreatePair`].
+    /// - The same as for
+    /// [`InnerAction::AddLiquidity`](dex_pair_io::InnerAction::AddLiquidity).
     ///
-    /// On success, replies with [`Event::FeeToSet`].
-    FeeTo(ActorId),
+    /// On success, replies with [`Event::PairCreatedWithLiquidity`].
+    CreatePairWithLiquidity {
+        token_a: ActorId,
+        token_b: ActorId,
+        fee_tier: FeeTier,
+        /// An amount of `token_a` to add as liquidity.
+        amount_a: u128,
+        /// An amount of `token_b` to add as liquidity.
+        amount_b: u128,
+        /// Timestamp (in ms) after which adding the liquidity will revert.
+        deadline: u64,
+    },
+
+    /// Proposes [`Proposal`] & confirms it on behalf of
+    /// [`msg::source`](gstd::msg::source).
+    ///
+    /// # Requirements:
+    /// - [`msg::source`](gstd::msg::source) must be one of the admins.
+    ///
+    /// On success, replies with [`Event::Confirmed`], or, if the proposal is
+    /// approved right away (the threshold equals 1), with an event of the
+    /// approved proposal (see [`Proposal`]).
+    Propose(Proposal),
+
+    /// Confirms a pending [`Proposal`] on behalf of
+    /// [`msg::source`](gstd::msg::source).
+    ///
+    /// When a proposal gets the threshold number of confirmations, it's
+    /// approved & performed. An approved proposal is removed even if it fails
+    /// to be performed.
+    ///
+    /// # Requirements:
+    /// - [`msg::source`](gstd::msg::source) must be one of the admins.
+    /// - [`msg::source`](gstd::msg::source) mustn't already confirm the
+    /// proposal unless the proposal has the threshold number of confirmations
+    /// (e.g. after [`AdminAction::Threshold`] lowered the threshold), in
+    /// which case the proposal is approved.
+    ///
+    /// On success, replies with [`Event::Confirmed`], or, if the proposal is
+    /// approved, with an event of the approved proposal (see [`Proposal`]).
+    Confirm(
+        /// The identifier of the proposal.
+        u64,
+    ),
 
-    /// Sets [`ActorId`] that'll have the right to set `fee_to` &
-    /// `fee_to_setter`.
+    /// Revokes [`msg::source`](gstd::msg::source)'s confirmation of a pending
+    /// [`Proposal`].
+    ///
+    /// A proposal without confirmations is removed.
     ///
     /// # Requirements:
-    /// - [`msg::source`](gstd::msg::source) must be equal to current
-    /// `fee_to_setter`.
+    /// - [`msg::source`](gstd::msg::source) must confirm the proposal.
     ///
-    /// On success, replies with [`Event::FeeToSetterSet`].
-    FeeToSetter(ActorId),
+    /// On success, replies with [`Event::Revoked`].
+    Revoke(
+        /// The identifier of the proposal.
+        u64,
+    ),
+
+    /// Executes a queued [`AdminAction`].
+    ///
+    /// Anyone can execute an action.
+    ///
+    /// # Requirements:
+    /// - The current block timestamp must be >= the action's `eta` & <=
+    /// `eta` + [`GRACE_PERIOD`].
+    ///
+    /// On success, replies with an event of the executed action (see
+    /// [`AdminAction`]).
+    Execute(
+        /// The identifier of the queued action.
+        u64,
+    ),
 
     /// Gets [`ActorId`] of the current fee receiver.
     ///
@@ -96,32 +348,357 @@ pub enum Action {
     ///
     /// On success, replies with [`Event::FeeToSet`].
     GetFeeTo,
+
+    /// Gets [`ActorId`] of a Pair contract by its tokens & [`FeeTier`].
+    ///
+    /// The order of tokens doesn't matter.
+    ///
+    /// On success, replies with [`Event::Pair`].
+    GetPair(ActorId, ActorId, FeeTier),
+
+    /// Gets a number of Pair contracts inside the contract.
+    ///
+    /// On success, replies with [`Event::PairCount`].
+    GetPairCount,
+
+    /// Gets a page of Pair contracts in the order they were added to the
+    /// contract, so the `pair_number` of a Pair contract equals its index + 1.
+    ///
+    /// `limit` is capped by [`MAX_PAIRS_LIMIT`].
+    ///
+    /// On success, replies with [`Event::Pairs`].
+    GetPairs { offset: u32, limit: u32 },
 }
 
-/// A result of successfully processed [`Action`].
+/// Sends the contract info about what it should do.
+pub type Action = tx_manager::Action<InnerAction>;
+
+/// An admin set's decision.
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum Proposal {
+    /// Queues [`AdminAction`] for the execution after `eta`.
+    ///
+    /// # Requirements:
+    /// - `eta` must be >= the current block timestamp + the timelock delay.
+    ///
+    /// On approval, replies with [`Event::Queued`].
+    Queue {
+        action: AdminAction,
+        /// Timestamp (in ms) after which the action can be executed.
+        eta: u64,
+    },
+
+    /// Cancels a queued [`AdminAction`].
+    ///
+    /// On approval, replies with [`Event::Canceled`].
+    Cancel(
+        /// The identifier of the queued action.
+        u64,
+    ),
+}
+
+/// [`Proposal`] waiting for confirmations.
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
+pub struct PendingProposal {
+    pub proposal: Proposal,
+    /// Admins that confirmed the proposal.
+    pub confirmations: Vec<ActorId>,
+}
+
+/// An action changing the contract's parameters.
+///
+/// Can only be performed through the timelock (see [`Proposal::Queue`]).
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum AdminAction {
+    /// Sets [`ActorId`] of the fee receiver (`fee_to`).
+    ///
+    /// Setting the fee receiver to [`ActorId::zero()`] disables the 0.05%
+    /// commission.
+    ///
+    /// Pair contracts cache `fee_to`, so the new value is also broadcast to
+    /// all of them with
+    /// [`InnerAction::UpdateFeeTo`](dex_pair_io::InnerAction::UpdateFeeTo).
+    /// The broadcast is best-effort: the action doesn't fail if some of the
+    /// messages can't be sent, & these Pair contracts are listed in the
+    /// reply instead.
+    ///
+    /// On execution, replies with [`Event::FeeToChanged`].
+    FeeTo(ActorId),
+
+    /// Adds [`ActorId`] to the admin set.
+    ///
+    /// # Requirements:
+    /// - [`ActorId`] mustn't equal to [`ActorId::zero()`].
+    /// - [`ActorId`] mustn't already be an admin.
+    ///
+    /// On execution, replies with [`Event::AdminAdded`].
+    AddAdmin(ActorId),
+
+    /// Removes [`ActorId`] from the admin set & its confirmations from pending
+    /// [`Proposal`]s.
+    ///
+    /// # Requirements:
+    /// - [`ActorId`] must be an admin.
+    /// - The remaining number of admins mustn't be less than the threshold.
+    ///
+    /// On execution, replies with [`Event::AdminRemoved`].
+    RemoveAdmin(ActorId),
+
+    /// Sets the number of confirmations required to approve a [`Proposal`].
+    ///
+    /// # Requirements:
+    /// - The threshold must be > 0 & <= the number of admins.
+    ///
+    /// On execution, replies with [`Event::ThresholdSet`].
+    Threshold(u32),
+
+    /// Sets the timelock delay (in ms).
+    ///
+    /// # Requirements:
+    /// - The delay mustn't be more than [`MAX_TIMELOCK_DELAY`].
+    ///
+    /// On execution, replies with [`Event::TimelockDelaySet`].
+    TimelockDelay(u64),
+
+    /// Enables [`FeeTier`] for new Pair contracts.
+    ///
+    /// # Requirements:
+    /// - [`FeeTier`] must be less than
+    /// [`FEE_DENOMINATOR`](dex_pair_io::FEE_DENOMINATOR).
+    /// - [`FeeTier`] mustn't already be enabled.
+    ///
+    /// On execution, replies with [`Event::FeeTierEnabled`].
+    EnableFeeTier(FeeTier),
+
+    /// Disables [`FeeTier`] for new Pair contracts.
+    ///
+    /// Already created Pair contracts with this tier are left as is.
+    ///
+    /// # Requirements:
+    /// - [`FeeTier`] must be enabled.
+    ///
+    /// On execution, replies with [`Event::FeeTierDisabled`].
+    DisableFeeTier(FeeTier),
+
+    /// Sets the config of cached transactions of Pair contracts.
+    ///
+    /// The new config is passed to new Pair contracts on their creation &
+    /// broadcast to all existing ones with
+    /// [`InnerAction::UpdateTxConfig`](dex_pair_io::InnerAction::UpdateTxConfig).
+    ///
+    /// # Requirements:
+    /// - [`TxConfig`] must be valid (see its fields).
+    ///
+    /// On execution, replies with [`Event::PairTxConfigSet`].
+    PairTxConfig(TxConfig),
+
+    /// Sets the limits of swaps of Pair contracts.
+    ///
+    /// The new limits are passed to new Pair contracts on their creation &
+    /// broadcast to all existing ones with
+    /// [`InnerAction::UpdateCircuitBreaker`](dex_pair_io::InnerAction::UpdateCircuitBreaker).
+    ///
+    /// # Requirements:
+    /// - [`CircuitBreaker`] must be valid (see its fields).
+    ///
+    /// On execution, replies with [`Event::PairCircuitBreakerSet`].
+    PairCircuitBreaker(CircuitBreaker),
+
+    /// Sets [`TokenStandard`] of a token.
+    ///
+    /// The standard is passed to new Pair contracts with the token on their
+    /// creation. Existing ones keep the standard they were created with.
+    ///
+    /// # Requirements:
+    /// - `token` mustn't equal to [`ActorId::zero()`].
+    ///
+    /// On execution, replies with [`Event::TokenStandardSet`].
+    TokenStandard {
+        token: ActorId,
+        standard: TokenStandard,
+    },
+
+    /// Adds an externally deployed Pair contract to the contract's pairs as
+    /// if it was created with [`InnerAction::CreatePair`].
+    ///
+    /// Before adding, the Pair contract is verified with
+    /// [`InnerAction::GetInfo`](dex_pair_io::InnerAction::GetInfo), & then
+    /// its cached `fee_to`, [`TxConfig`], & [`CircuitBreaker`] are updated.
+    ///
+    /// # Requirements:
+    /// - `pair_actor` mustn't equal to [`ActorId::zero()`].
+    /// - `token_pair` mustn't contain identical [`ActorId`]s.
+    /// - `fee_tier` must be less than
+    /// [`FEE_DENOMINATOR`](dex_pair_io::FEE_DENOMINATOR), but it doesn't have
+    /// to be enabled.
+    /// - Pair with given [`ActorId`]s & [`FeeTier`] mustn't already exist.
+    /// - The Pair contract's `factory` must be this contract, its `token` must
+    /// equal `token_pair` sorted with [`sort_pair()`], & its `fee_tier` must
+    /// match a given one.
+    ///
+    /// On execution, replies with [`Event::PairRegistered`].
+    RegisterPair {
+        pair_actor: ActorId,
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    },
+}
+
+/// [`AdminAction`] waiting for its execution.
 #[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub struct QueuedOperation {
+    pub action: AdminAction,
+    /// Timestamp (in ms) after which the action can be executed.
+    pub eta: u64,
+}
+
+/// A result of successfully processed [`Action`].
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
 pub enum Event {
-    /// Should be returned from [`Action::CreatePair`].
+    /// Should be returned from [`InnerAction::CreatePair`].
     PairCreated {
         /// A pair of SFT [`ActorId`]s.
         token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+        /// [`ActorId`] of a created Pair contract.
+        pair_actor: ActorId,
+        /// A number of Pair contracts (including a created one) inside the
+        /// Factory contract.
+        pair_number: u32,
+    },
+
+    /// Should be returned from [`InnerAction::CreatePairWithLiquidity`].
+    PairCreatedWithLiquidity {
+        /// A pair of SFT [`ActorId`]s.
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
         /// [`ActorId`] of a created Pair contract.
         pair_actor: ActorId,
         /// A number of Pair contracts (including a created one) inside the
         /// Factory contract.
         pair_number: u32,
+        /// An amount of liquidity tokens minted to the creator.
+        liquidity: dex_pair_io::Amount,
+    },
+
+    /// Should be returned from [`AdminAction::RegisterPair`].
+    PairRegistered {
+        /// A pair of SFT [`ActorId`]s.
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+        /// [`ActorId`] of a registered Pair contract.
+        pair_actor: ActorId,
+        /// A number of Pair contracts (including a registered one) inside the
+        /// Factory contract.
+        pair_number: u32,
     },
 
-    /// Should be returned from [`Action::FeeToSetter`].
-    FeeToSetterSet(
-        /// New `fee_to_setter`.
+    /// Should be returned from [`AdminAction::AddAdmin`].
+    AdminAdded(ActorId),
+
+    /// Should be returned from [`AdminAction::RemoveAdmin`].
+    AdminRemoved(ActorId),
+
+    /// Should be returned from [`AdminAction::Threshold`].
+    ThresholdSet(u32),
+
+    /// Should be returned from [`InnerAction::GetFeeTo`].
+    FeeToSet(
+        /// Current `fee_to`.
         ActorId,
     ),
 
-    /// Should be returned from [`Action::FeeTo`].
-    FeeToSet(
+    /// Should be returned from [`AdminAction::FeeTo`].
+    FeeToChanged {
         /// New `fee_to`.
-        ActorId,
+        fee_to: ActorId,
+        /// Pair contracts that weren't notified about the change. They keep
+        /// the previous `fee_to` until
+        /// [`InnerAction::RefreshFeeTo`](dex_pair_io::InnerAction::RefreshFeeTo).
+        unnotified_pairs: Vec<ActorId>,
+    },
+
+    /// Should be returned from [`AdminAction::TimelockDelay`].
+    TimelockDelaySet(
+        /// New timelock delay.
+        u64,
+    ),
+
+    /// Should be returned from [`AdminAction::EnableFeeTier`].
+    FeeTierEnabled(FeeTier),
+
+    /// Should be returned from [`AdminAction::DisableFeeTier`].
+    FeeTierDisabled(FeeTier),
+
+    /// Should be returned from [`AdminAction::PairTxConfig`].
+    PairTxConfigSet {
+        tx_config: TxConfig,
+        /// Pair contracts that weren't notified about the change. They keep
+        /// the previous config until it's set again.
+        unnotified_pairs: Vec<ActorId>,
+    },
+
+    /// Should be returned from [`AdminAction::PairCircuitBreaker`].
+    PairCircuitBreakerSet {
+        circuit_breaker: CircuitBreaker,
+        /// Pair contracts that weren't notified about the change. They keep
+        /// the previous limits until they're set again.
+        unnotified_pairs: Vec<ActorId>,
+    },
+
+    /// Should be returned from [`AdminAction::TokenStandard`].
+    TokenStandardSet {
+        token: ActorId,
+        standard: TokenStandard,
+    },
+
+    /// Should be returned from [`InnerAction::Propose`]/[`InnerAction::Confirm`] that
+    /// didn't approve a proposal.
+    Confirmed {
+        /// The identifier of the proposal.
+        id: u64,
+        /// A number of the proposal's confirmations.
+        confirmations: u32,
+    },
+
+    /// Should be returned from [`InnerAction::Revoke`].
+    Revoked {
+        /// The identifier of the proposal.
+        id: u64,
+        /// A number of the proposal's confirmations.
+        confirmations: u32,
+    },
+
+    /// Should be returned from an approved [`Proposal::Queue`].
+    Queued {
+        /// The identifier of the queued action.
+        id: u64,
+        action: AdminAction,
+        eta: u64,
+    },
+
+    /// Should be returned from an approved [`Proposal::Cancel`].
+    Canceled(
+        /// The identifier of the canceled action.
+        u64,
+    ),
+
+    /// Should be returned from [`InnerAction::GetPair`].
+    Pair {
+        /// A pair of SFT [`ActorId`]s.
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+        /// [`ActorId`] of a Pair contract or [`None`] if it doesn't exist.
+        pair_actor: Option<ActorId>,
+    },
+
+    /// Should be returned from [`InnerAction::GetPairCount`].
+    PairCount(u32),
+
+    /// Should be returned from [`InnerAction::GetPairs`].
+    Pairs(
+        /// Pair contracts with their (`token_a`, `token_b`, `fee_tier`).
+        Vec<((ActorId, ActorId, FeeTier), ActorId)>,
     ),
 }
 
@@ -130,15 +707,59 @@ pub enum Event {
 pub enum Error {
     /// See [`ContractError`].
     GstdError(String),
-    /// [`msg::source()`](gstd::msg::source) doesn't equal to `fee_to_setter`.
+    /// [`msg::source()`](gstd::msg::source) isn't an admin.
     AccessRestricted,
     /// [`ActorId::zero()`] was found where it's forbidden.
     ZeroActorId,
     /// SFT [`ActorId`]s in a given pair to create the Pair contract are equal.
     IdenticalTokens,
-    /// A pair contract with given SFT [`ActorId`]s already exist.
+    /// A pair contract with given SFT [`ActorId`]s & [`FeeTier`] already
+    /// exist.
     PairExist,
     PairCreationFailed(dex_pair_io::Error),
+    /// A created Pair contract failed to add the initial liquidity.
+    SeedingFailed(dex_pair_io::Error),
+    /// A given timelock delay is more than [`MAX_TIMELOCK_DELAY`].
+    InvalidTimelockDelay,
+    /// A given `eta` is less than the current block timestamp + the timelock
+    /// delay.
+    EtaTooEarly,
+    /// A queued action with a given identifier doesn't exist.
+    OperationNotFound,
+    /// The `eta` of a queued action hasn't come yet.
+    OperationNotReady,
+    /// The [`GRACE_PERIOD`] of a queued action has passed.
+    OperationStale,
+    /// A given threshold is 0 or more than the number of admins.
+    InvalidThreshold,
+    /// A given [`ActorId`] is already an admin.
+    AdminExists,
+    /// A given [`ActorId`] isn't an admin.
+    AdminNotFound,
+    /// A pending proposal with a given identifier doesn't exist.
+    ProposalNotFound,
+    /// [`msg::source()`](gstd::msg::source) has already confirmed a proposal.
+    AlreadyConfirmed,
+    /// [`msg::source()`](gstd::msg::source) hasn't confirmed a proposal.
+    NotConfirmed,
+    /// A given [`FeeTier`] isn't less than
+    /// [`FEE_DENOMINATOR`](dex_pair_io::FEE_DENOMINATOR).
+    InvalidFeeTier,
+    /// A given [`FeeTier`] isn't enabled.
+    FeeTierNotEnabled,
+    /// A given [`FeeTier`] is already enabled.
+    FeeTierEnabled,
+    /// A program to register or a Pair contract created by an interrupted
+    /// action isn't a Pair contract of this contract, or its parameters
+    /// don't match given ones.
+    PairVerificationFailed,
+    /// A given [`TxConfig`] is invalid.
+    InvalidPairTxConfig,
+    /// A given [`CircuitBreaker`] is invalid.
+    InvalidPairCircuitBreaker,
+    /// A Pair contract replied with an unexpected payload.
+    UnexpectedPairReply,
+    TxCacheError(TransactionManagerError),
 }
 
 impl From<GstdError> for Error {
@@ -152,3 +773,38 @@ impl From<dex_pair_io::Error> for Error {
         Self::PairCreationFailed(error)
     }
 }
+
+impl From<TransactionManagerError> for Error {
+    fn from(error: TransactionManagerError) -> Self {
+        Self::TxCacheError(error)
+    }
+}
+
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum CachedAction {
+    CreatePair {
+        /// (`token_a`, `token_b`, `fee_tier`) of a pair being created.
+        key: (ActorId, ActorId, FeeTier),
+        /// Whether a Pair contract creation was requested.
+        is_created: bool,
+    },
+    CreatePairWithLiquidity {
+        /// (`token_a`, `token_b`, `fee_tier`) of a pair being created.
+        key: (ActorId, ActorId, FeeTier),
+        /// Amounts of the initial liquidity in the order of tokens in `key`.
+        amount: (u128, u128),
+        progress: SeedingProgress,
+    },
+    Other,
+}
+
+/// A progress of [`InnerAction::CreatePairWithLiquidity`].
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct SeedingProgress {
+    /// Whether a Pair contract creation was requested.
+    pub is_created: bool,
+    /// Whether adding the initial liquidity was requested.
+    pub is_seeding: bool,
+}
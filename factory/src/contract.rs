@@ -1,48 +1,361 @@
 use dex_factory_io::*;
-use gstd::{
-    errors::Result, exec, msg, prelude::*, prog::ProgramGenerator, ActorId, CodeId, HashMap,
-    MessageId,
-};
+use gear_lib::tx_manager::{ActionKind, TransactionManager};
+use gstd::{errors::Result, exec, msg, prelude::*, prog, ActorId, CodeId, HashMap, MessageId};
+use multisig::{Confirmation, Multisig};
+use timelock::Timelock;
+
+mod multisig;
+mod timelock;
 
 struct Contract {
     pair: CodeId,
     fee_to: ActorId,
-    fee_to_setter: ActorId,
-    pairs: HashMap<(ActorId, ActorId), ActorId>,
+    fee_tiers: Vec<FeeTier>,
+    pair_tx_config: TxConfig,
+    pair_circuit_breaker: CircuitBreaker,
+    /// Standards of tokens except [`TokenStandard::ShardedFt`] ones.
+    token_standards: HashMap<ActorId, TokenStandard>,
+    pairs: HashMap<(ActorId, ActorId, FeeTier), PairRecord>,
+    /// Keys of `pairs` in the order they were added.
+    all_pairs: Vec<(ActorId, ActorId, FeeTier)>,
+    multisig: Multisig,
+    timelock: Timelock,
 }
 
-static mut STATE: Option<Contract> = None;
+static mut STATE: Option<(Contract, TransactionManager<CachedAction>)> = None;
 
 impl Contract {
-    fn check_fee_to_setter(&self) -> Result<(), Error> {
-        if self.fee_to_setter == msg::source() {
+    fn check_new_fee_tier(&self, fee_tier: FeeTier) -> Result<(), Error> {
+        if fee_tier >= dex_pair_io::FEE_DENOMINATOR {
+            Err(Error::InvalidFeeTier)
+        } else if self.fee_tiers.contains(&fee_tier) {
+            Err(Error::FeeTierEnabled)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_enabled_fee_tier(&self, fee_tier: FeeTier) -> Result<(), Error> {
+        if self.fee_tiers.contains(&fee_tier) {
             Ok(())
         } else {
-            Err(Error::AccessRestricted)
+            Err(Error::FeeTierNotEnabled)
         }
     }
 
-    fn set_fee_to_setter(&mut self, actor: ActorId) -> Result<Event, Error> {
-        self.check_fee_to_setter()?;
+    fn enable_fee_tier(&mut self, fee_tier: FeeTier) -> Result<Event, Error> {
+        self.check_new_fee_tier(fee_tier)?;
+        self.fee_tiers.push(fee_tier);
 
-        if actor.is_zero() {
-            return Err(Error::ZeroActorId);
+        Ok(Event::FeeTierEnabled(fee_tier))
+    }
+
+    fn disable_fee_tier(&mut self, fee_tier: FeeTier) -> Result<Event, Error> {
+        self.check_enabled_fee_tier(fee_tier)?;
+        self.fee_tiers.retain(|tier| *tier != fee_tier);
+
+        Ok(Event::FeeTierDisabled(fee_tier))
+    }
+
+    fn check_admin_action(&self, action: AdminAction) -> Result<(), Error> {
+        match action {
+            AdminAction::FeeTo(_) => Ok(()),
+            AdminAction::AddAdmin(actor) => self.multisig.check_new_admin(actor),
+            AdminAction::RemoveAdmin(actor) => self.multisig.check_removed_admin(actor),
+            AdminAction::Threshold(threshold) => self.multisig.check_threshold(threshold),
+            AdminAction::TimelockDelay(delay) => {
+                if delay > MAX_TIMELOCK_DELAY {
+                    Err(Error::InvalidTimelockDelay)
+                } else {
+                    Ok(())
+                }
+            }
+            AdminAction::EnableFeeTier(fee_tier) => self.check_new_fee_tier(fee_tier),
+            AdminAction::DisableFeeTier(fee_tier) => self.check_enabled_fee_tier(fee_tier),
+            AdminAction::PairTxConfig(tx_config) => check_pair_tx_config(tx_config),
+            AdminAction::PairCircuitBreaker(circuit_breaker) => {
+                check_pair_circuit_breaker(circuit_breaker)
+            }
+            AdminAction::TokenStandard { token, .. } => check_token(token),
+            AdminAction::RegisterPair {
+                pair_actor,
+                token_pair,
+                fee_tier,
+            } => self
+                .check_registered_pair(pair_actor, token_pair, fee_tier)
+                .map(|_| ()),
+        }
+    }
+
+    fn propose(&mut self, proposal: Proposal) -> Result<Event, Error> {
+        if let Proposal::Queue { action, .. } = proposal {
+            self.check_admin_action(action)?;
         }
 
-        self.fee_to_setter = actor;
+        let msg_source = msg::source();
+        let (id, confirmation) = self.multisig.propose(msg_source, proposal)?;
 
-        Ok(Event::FeeToSetterSet(actor))
+        self.process_confirmation(id, confirmation)
     }
 
-    fn set_fee_to(&mut self, actor: ActorId) -> Result<Event, Error> {
-        self.check_fee_to_setter()?;
+    fn confirm(&mut self, id: u64) -> Result<Event, Error> {
+        let msg_source = msg::source();
+        let confirmation = self.multisig.confirm(msg_source, id)?;
+
+        self.process_confirmation(id, confirmation)
+    }
+
+    fn process_confirmation(
+        &mut self,
+        id: u64,
+        confirmation: Confirmation,
+    ) -> Result<Event, Error> {
+        let proposal = match confirmation {
+            Confirmation::Pending(confirmations) => {
+                return Ok(Event::Confirmed { id, confirmations })
+            }
+            Confirmation::Approved(proposal) => proposal,
+        };
 
+        let result = match proposal {
+            Proposal::Queue { action, eta } => self.timelock.queue(action, eta),
+            Proposal::Cancel(operation_id) => self.timelock.cancel(operation_id),
+        };
+
+        // A failed proposal (e.g. with `Error::EtaTooEarly`) can't succeed
+        // later, so it's removed as well & must be proposed again.
+        self.multisig.remove(id);
+
+        result
+    }
+
+    async fn execute(&mut self, id: u64) -> Result<Event, Error> {
+        let event = match self.timelock.ready_action(id)? {
+            AdminAction::FeeTo(actor) => self.set_fee_to(actor),
+            AdminAction::AddAdmin(actor) => self.multisig.add_admin(actor),
+            AdminAction::RemoveAdmin(actor) => self.multisig.remove_admin(actor),
+            AdminAction::Threshold(threshold) => self.multisig.set_threshold(threshold),
+            AdminAction::TimelockDelay(delay) => self.timelock.set_delay(delay),
+            AdminAction::EnableFeeTier(fee_tier) => self.enable_fee_tier(fee_tier),
+            AdminAction::DisableFeeTier(fee_tier) => self.disable_fee_tier(fee_tier),
+            AdminAction::PairTxConfig(tx_config) => self.set_pair_tx_config(tx_config),
+            AdminAction::PairCircuitBreaker(circuit_breaker) => {
+                self.set_pair_circuit_breaker(circuit_breaker)
+            }
+            AdminAction::TokenStandard { token, standard } => {
+                self.set_token_standard(token, standard)
+            }
+            AdminAction::RegisterPair {
+                pair_actor,
+                token_pair,
+                fee_tier,
+            } => self.register_pair(pair_actor, token_pair, fee_tier).await,
+        }?;
+
+        self.timelock.remove(id);
+
+        Ok(event)
+    }
+
+    fn set_fee_to(&mut self, actor: ActorId) -> Result<Event, Error> {
         self.fee_to = actor;
 
-        Ok(Event::FeeToSet(actor))
+        // Pairs cache `fee_to`, so they must be notified about its change.
+        // The rest of pairs can refresh it themselves.
+        let unnotified_pairs = self.broadcast(dex_pair_io::InnerAction::UpdateFeeTo(actor));
+
+        Ok(Event::FeeToChanged {
+            fee_to: actor,
+            unnotified_pairs,
+        })
+    }
+
+    fn set_pair_tx_config(&mut self, tx_config: TxConfig) -> Result<Event, Error> {
+        check_pair_tx_config(tx_config)?;
+
+        self.pair_tx_config = tx_config;
+
+        let unnotified_pairs = self.broadcast(dex_pair_io::InnerAction::UpdateTxConfig(tx_config));
+
+        Ok(Event::PairTxConfigSet {
+            tx_config,
+            unnotified_pairs,
+        })
+    }
+
+    fn set_pair_circuit_breaker(
+        &mut self,
+        circuit_breaker: CircuitBreaker,
+    ) -> Result<Event, Error> {
+        check_pair_circuit_breaker(circuit_breaker)?;
+
+        self.pair_circuit_breaker = circuit_breaker;
+
+        let unnotified_pairs = self.broadcast(dex_pair_io::InnerAction::UpdateCircuitBreaker(
+            circuit_breaker,
+        ));
+
+        Ok(Event::PairCircuitBreakerSet {
+            circuit_breaker,
+            unnotified_pairs,
+        })
+    }
+
+    /// Sends `action` to all pairs & returns the ones it wasn't sent to.
+    ///
+    /// Sending can fail midway (e.g. after exceeding the limit of outgoing
+    /// messages), but the factory state is already changed, so the rest of
+    /// pairs are reported instead of failing the whole action.
+    fn broadcast(&self, action: dex_pair_io::InnerAction) -> Vec<ActorId> {
+        self.pairs
+            .values()
+            .filter_map(|PairRecord { pair_actor, .. }| {
+                msg::send(*pair_actor, dex_pair_io::Action::new(action), 0)
+                    .is_err()
+                    .then_some(*pair_actor)
+            })
+            .collect()
+    }
+
+    fn set_token_standard(
+        &mut self,
+        token: ActorId,
+        standard: TokenStandard,
+    ) -> Result<Event, Error> {
+        check_token(token)?;
+
+        if standard == TokenStandard::default() {
+            self.token_standards.remove(&token);
+        } else {
+            self.token_standards.insert(token, standard);
+        }
+
+        Ok(Event::TokenStandardSet { token, standard })
+    }
+
+    fn token_standard(&self, token: ActorId) -> TokenStandard {
+        self.token_standards
+            .get(&token)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the key of a Pair contract to register.
+    fn check_registered_pair(
+        &self,
+        pair_actor: ActorId,
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    ) -> Result<(ActorId, ActorId, FeeTier), Error> {
+        if pair_actor.is_zero() || token_pair.0.is_zero() || token_pair.1.is_zero() {
+            return Err(Error::ZeroActorId);
+        }
+
+        if token_pair.0 == token_pair.1 {
+            return Err(Error::IdenticalTokens);
+        }
+
+        if fee_tier >= dex_pair_io::FEE_DENOMINATOR {
+            return Err(Error::InvalidFeeTier);
+        }
+
+        let token_pair = sort_pair(token_pair);
+        let key = (token_pair.0, token_pair.1, fee_tier);
+
+        if self.pairs.contains_key(&key) {
+            Err(Error::PairExist)
+        } else {
+            Ok(key)
+        }
+    }
+
+    async fn register_pair(
+        &mut self,
+        pair_actor: ActorId,
+        token_pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    ) -> Result<Event, Error> {
+        let key = self.check_registered_pair(pair_actor, token_pair, fee_tier)?;
+
+        // The pair's A & B tokens must be in the order of the key, otherwise
+        // its reserve & amounts would be swapped for users of the factory.
+        if !Self::is_own_pair(pair_actor, key).await {
+            return Err(Error::PairVerificationFailed);
+        }
+
+        // The same pair could've been created or registered while waiting for
+        // the reply.
+        self.check_registered_pair(pair_actor, token_pair, fee_tier)?;
+
+        let pair_number = self.insert_pair(key, pair_actor, msg::source(), None);
+
+        self.update_pair_config(pair_actor)?;
+
+        Ok(Event::PairRegistered {
+            token_pair: (key.0, key.1),
+            fee_tier,
+            pair_actor,
+            pair_number,
+        })
+    }
+
+    /// Sends the current `fee_to`, [`TxConfig`], & [`CircuitBreaker`] to a
+    /// Pair contract that could miss their broadcasts.
+    fn update_pair_config(&self, pair_actor: ActorId) -> Result<(), Error> {
+        msg::send(
+            pair_actor,
+            dex_pair_io::Action::new(dex_pair_io::InnerAction::UpdateFeeTo(self.fee_to)),
+            0,
+        )?;
+        msg::send(
+            pair_actor,
+            dex_pair_io::Action::new(dex_pair_io::InnerAction::UpdateTxConfig(
+                self.pair_tx_config,
+            )),
+            0,
+        )?;
+        msg::send(
+            pair_actor,
+            dex_pair_io::Action::new(dex_pair_io::InnerAction::UpdateCircuitBreaker(
+                self.pair_circuit_breaker,
+            )),
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    /// Checks that `pair_actor` is an initialized Pair contract of this
+    /// factory with a given key.
+    async fn is_own_pair(
+        pair_actor: ActorId,
+        (token_a, token_b, fee_tier): (ActorId, ActorId, FeeTier),
+    ) -> bool {
+        let reply = send_to_pair(
+            pair_actor,
+            dex_pair_io::Action::new(dex_pair_io::InnerAction::GetInfo),
+        )
+        .await;
+
+        matches!(
+            reply,
+            Ok(Ok(dex_pair_io::Event::Info {
+                factory,
+                token,
+                fee_tier: pair_fee_tier,
+            })) if factory == exec::program_id()
+                && token == (token_a, token_b)
+                && pair_fee_tier == fee_tier
+        )
     }
 
-    async fn create_pair(&mut self, token_a: ActorId, token_b: ActorId) -> Result<Event, Error> {
+    /// Returns the key of a Pair contract to create.
+    fn check_pair_key(
+        &self,
+        token_a: ActorId,
+        token_b: ActorId,
+        fee_tier: FeeTier,
+    ) -> Result<(ActorId, ActorId, FeeTier), Error> {
         if token_a == token_b {
             return Err(Error::IdenticalTokens);
         }
@@ -51,39 +364,258 @@ impl Contract {
             return Err(Error::ZeroActorId);
         }
 
-        let token_pair = if token_b > token_a {
-            (token_b, token_a)
-        } else {
-            (token_a, token_b)
-        };
+        self.check_enabled_fee_tier(fee_tier)?;
 
-        if self.pairs.contains_key(&token_pair) {
-            return Err(Error::PairExist);
+        let token_pair = sort_pair((token_a, token_b));
+
+        Ok((token_pair.0, token_pair.1, fee_tier))
+    }
+
+    /// Creates a Pair contract, or adopts the one created by an interrupted
+    /// attempt.
+    async fn deploy_pair(
+        &self,
+        is_created: &mut bool,
+        key: (ActorId, ActorId, FeeTier),
+        seeder: Option<ActorId>,
+    ) -> Result<ActorId, Error> {
+        let (token_a, token_b, fee_tier) = key;
+        let token_pair = (token_a, token_b);
+        let factory = exec::program_id();
+        let pair_actor = pair_actor_id(factory, self.pair, token_pair, fee_tier);
+
+        // An interrupted attempt has already sent the creation message, & since
+        // the pair's address is deterministic, it can't be created again.
+        if *is_created {
+            if !Self::is_own_pair(pair_actor, key).await {
+                return Err(Error::PairVerificationFailed);
+            }
+
+            // The config could change after the interrupted attempt.
+            self.update_pair_config(pair_actor)?;
+
+            return Ok(pair_actor);
         }
 
-        let (pair_actor, result): (_, Result<(), dex_pair_io::Error>) =
-            ProgramGenerator::create_program_for_reply_as(
-                self.pair,
-                dex_pair_io::Initialize {
-                    pair: token_pair,
-                    factory: exec::program_id(),
-                }
-                .encode(),
-                0,
-                0,
-            )?
-            .await?;
+        *is_created = true;
+
+        // The salt is derived from the pair's key, so the pair's address can
+        // be computed off-chain with `pair_actor_id()`.
+        let (_, result): (_, Result<(), dex_pair_io::Error>) = prog::create_program_for_reply_as(
+            self.pair,
+            pair_salt(factory, token_pair, fee_tier),
+            dex_pair_io::Initialize {
+                pair: token_pair,
+                factory,
+                fee_tier,
+                fee_to: self.fee_to,
+                seeder,
+                tx_config: self.pair_tx_config,
+                circuit_breaker: self.pair_circuit_breaker,
+                token_standard: (self.token_standard(token_a), self.token_standard(token_b)),
+            }
+            .encode(),
+            0,
+            0,
+        )?
+        .await?;
 
         result?;
 
-        self.pairs.insert(token_pair, pair_actor);
+        Ok(pair_actor)
+    }
+
+    async fn create_pair(
+        &mut self,
+        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        msg_source: ActorId,
+        token_a: ActorId,
+        token_b: ActorId,
+        fee_tier: FeeTier,
+    ) -> Result<Event, Error> {
+        let key = self.check_pair_key(token_a, token_b, fee_tier)?;
+
+        if self.pairs.contains_key(&key) {
+            return Err(Error::PairExist);
+        }
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::CreatePair {
+                key,
+                is_created: false,
+            }),
+        )?;
+        let is_created = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::CreatePair {
+                key: cached_key,
+                is_created,
+            } = tx_data
+            {
+                (*cached_key == key).then_some(is_created)
+            } else {
+                None
+            }
+        })?;
+
+        let pair_actor = self.deploy_pair(is_created, key, None).await?;
+        let pair_number = self.insert_pair(key, pair_actor, msg_source, Some(self.pair));
 
         Ok(Event::PairCreated {
-            token_pair,
+            token_pair: (key.0, key.1),
+            fee_tier,
+            pair_actor,
+            pair_number,
+        })
+    }
+
+    async fn create_pair_with_liquidity(
+        &mut self,
+        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        msg_source: ActorId,
+        (token_a, token_b, fee_tier): (ActorId, ActorId, FeeTier),
+        amount: (u128, u128),
+        deadline: u64,
+    ) -> Result<Event, Error> {
+        let key = self.check_pair_key(token_a, token_b, fee_tier)?;
+        // Amounts must follow the order of tokens in the key.
+        let amount = if (key.0, key.1) == (token_a, token_b) {
+            amount
+        } else {
+            (amount.1, amount.0)
+        };
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::CreatePairWithLiquidity {
+                key,
+                amount,
+                progress: SeedingProgress::default(),
+            }),
+        )?;
+        let progress = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::CreatePairWithLiquidity {
+                key: cached_key,
+                amount: cached_amount,
+                progress,
+            } = tx_data
+            {
+                (*cached_key == key && *cached_amount == amount).then_some(progress)
+            } else {
+                None
+            }
+        })?;
+
+        let pair_actor = match self.pairs.get(&key) {
+            // The pair was created & registered by an interrupted attempt.
+            Some(record) if progress.is_created && record.creator == msg_source => {
+                record.pair_actor
+            }
+            Some(_) => return Err(Error::PairExist),
+            None => {
+                let pair_actor = self
+                    .deploy_pair(&mut progress.is_created, key, Some(msg_source))
+                    .await?;
+
+                self.insert_pair(key, pair_actor, msg_source, Some(self.pair));
+
+                pair_actor
+            }
+        };
+
+        let mut action = dex_pair_io::Action::new(dex_pair_io::InnerAction::AddLiquidityFor {
+            provider: msg_source,
+            amount_a_desired: amount.0,
+            amount_b_desired: amount.1,
+            amount_a_min: amount.0,
+            amount_b_min: amount.1,
+            deadline,
+        });
+
+        // The pair caches the interrupted addition, so it must be resumed
+        // rather than started anew.
+        if progress.is_seeding {
+            action = action.to_retry();
+        }
+
+        progress.is_seeding = true;
+
+        let liquidity = match send_to_pair(pair_actor, action).await {
+            Ok(Ok(dex_pair_io::Event::AddedLiquidity { liquidity, .. })) => liquidity,
+            reply => {
+                // The failed flow can't be resumed, so the pair mustn't await
+                // the initial liquidity from the creator anymore. Otherwise,
+                // nobody else could ever add it.
+                msg::send(
+                    pair_actor,
+                    dex_pair_io::Action::new(dex_pair_io::InnerAction::ClearSeeder),
+                    0,
+                )?;
+
+                return Err(match reply {
+                    Ok(Ok(_)) => Error::UnexpectedPairReply,
+                    Ok(Err(error)) => Error::SeedingFailed(error),
+                    Err(error) => error.into(),
+                });
+            }
+        };
+
+        Ok(Event::PairCreatedWithLiquidity {
+            token_pair: (key.0, key.1),
+            fee_tier,
             pair_actor,
             pair_number: self.pairs.len().try_into().unwrap(),
+            liquidity,
         })
     }
+
+    /// Adds a Pair contract to the registry & returns the number of Pair
+    /// contracts.
+    fn insert_pair(
+        &mut self,
+        key: (ActorId, ActorId, FeeTier),
+        pair_actor: ActorId,
+        creator: ActorId,
+        code_id: Option<CodeId>,
+    ) -> u32 {
+        self.pairs.insert(
+            key,
+            PairRecord {
+                pair_actor,
+                creator,
+                created_at_block: exec::block_height(),
+                created_at: exec::block_timestamp(),
+                code_id,
+            },
+        );
+        self.all_pairs.push(key);
+
+        self.pairs.len().try_into().unwrap()
+    }
+
+    fn get_pair(&self, token_a: ActorId, token_b: ActorId, fee_tier: FeeTier) -> Event {
+        let token_pair = sort_pair((token_a, token_b));
+
+        Event::Pair {
+            token_pair,
+            fee_tier,
+            pair_actor: self
+                .pairs
+                .get(&(token_pair.0, token_pair.1, fee_tier))
+                .map(|record| record.pair_actor),
+        }
+    }
+
+    fn get_pairs(&self, offset: u32, limit: u32) -> Event {
+        Event::Pairs(
+            self.all_pairs
+                .iter()
+                .skip(offset as usize)
+                .take(limit.min(MAX_PAIRS_LIMIT) as usize)
+                .map(|key| (*key, self.pairs[key].pair_actor))
+                .collect(),
+        )
+    }
 }
 
 #[no_mangle]
@@ -101,19 +633,34 @@ extern "C" fn init() {
 fn process_init() -> Result<(), Error> {
     let Initialize {
         fee_to,
+        admins,
+        threshold,
         pair,
-        fee_to_setter,
+        fee_tiers,
+        timelock_delay,
     } = msg::load()?;
 
-    unsafe {
-        STATE = Some(Contract {
-            pair,
-            fee_to,
-            fee_to_setter,
-            pairs: HashMap::new(),
-        });
+    let multisig = Multisig::new(admins, threshold)?;
+    let timelock = Timelock::new(timelock_delay)?;
+    let mut contract = Contract {
+        pair,
+        fee_to,
+        fee_tiers: vec![],
+        pair_tx_config: TxConfig::default(),
+        pair_circuit_breaker: CircuitBreaker::default(),
+        token_standards: HashMap::new(),
+        pairs: HashMap::new(),
+        all_pairs: vec![],
+        multisig,
+        timelock,
     };
 
+    for fee_tier in fee_tiers {
+        contract.enable_fee_tier(fee_tier)?;
+    }
+
+    unsafe { STATE = Some((contract, TransactionManager::default())) };
+
     Ok(())
 }
 
@@ -123,39 +670,131 @@ async fn main() {
 }
 
 async fn process_handle() -> Result<Event, Error> {
-    let action: Action = msg::load()?;
-    let contract = state_mut();
+    let Action {
+        action,
+        kind: action_kind,
+    } = msg::load()?;
+    let (contract, tx_manager) = state_mut();
+    let msg_source = msg::source();
 
     match action {
-        Action::FeeToSetter(actor) => contract.set_fee_to_setter(actor),
-        Action::FeeTo(actor) => contract.set_fee_to(actor),
-        Action::CreatePair(token_a, token_b) => contract.create_pair(token_a, token_b).await,
-        Action::GetFeeTo => Ok(Event::FeeToSet(contract.fee_to)),
+        InnerAction::CreatePair(token_a, token_b, fee_tier) => {
+            contract
+                .create_pair(
+                    (tx_manager, action_kind),
+                    msg_source,
+                    token_a,
+                    token_b,
+                    fee_tier,
+                )
+                .await
+        }
+        InnerAction::CreatePairWithLiquidity {
+            token_a,
+            token_b,
+            fee_tier,
+            amount_a,
+            amount_b,
+            deadline,
+        } => {
+            contract
+                .create_pair_with_liquidity(
+                    (tx_manager, action_kind),
+                    msg_source,
+                    (token_a, token_b, fee_tier),
+                    (amount_a, amount_b),
+                    deadline,
+                )
+                .await
+        }
+        InnerAction::Propose(proposal) => contract.propose(proposal),
+        InnerAction::Confirm(id) => contract.confirm(id),
+        InnerAction::Revoke(id) => contract.multisig.revoke(msg_source, id),
+        InnerAction::Execute(id) => contract.execute(id).await,
+        InnerAction::GetFeeTo => Ok(Event::FeeToSet(contract.fee_to)),
+        InnerAction::GetPair(token_a, token_b, fee_tier) => {
+            Ok(contract.get_pair(token_a, token_b, fee_tier))
+        }
+        InnerAction::GetPairCount => Ok(Event::PairCount(contract.pairs.len().try_into().unwrap())),
+        InnerAction::GetPairs { offset, limit } => Ok(contract.get_pairs(offset, limit)),
     }
 }
 
-fn state_mut() -> &'static mut Contract {
+fn state_mut() -> &'static mut (Contract, TransactionManager<CachedAction>) {
     unsafe { STATE.as_mut().expect("state isn't initialized") }
 }
 
 #[no_mangle]
 extern "C" fn state() {
-    let Contract {
-        pair,
-        fee_to,
-        fee_to_setter: admin,
-        pairs,
-    } = state_mut();
+    let (
+        Contract {
+            pair,
+            fee_to,
+            fee_tiers,
+            pair_tx_config,
+            pair_circuit_breaker,
+            token_standards,
+            pairs,
+            all_pairs,
+            multisig,
+            timelock,
+        },
+        tx_manager,
+    ) = state_mut();
 
     reply(State {
         pair: *pair,
-        fee_to_setter: *admin,
         fee_to: *fee_to,
-        pairs: pairs.into_iter().map(|(k, v)| (*k, *v)).collect(),
+        admins: multisig.admins.clone(),
+        threshold: multisig.threshold,
+        fee_tiers: fee_tiers.clone(),
+        pairs: all_pairs.iter().map(|key| (*key, pairs[key])).collect(),
+        timelock_delay: timelock.delay,
+        queue: timelock.operations(),
+        proposals: multisig.pending_proposals(),
+        pair_tx_config: *pair_tx_config,
+        pair_circuit_breaker: *pair_circuit_breaker,
+        token_standards: {
+            let mut token_standards: Vec<_> =
+                token_standards.iter().map(|(k, v)| (*k, *v)).collect();
+
+            token_standards.sort_unstable();
+
+            token_standards
+        },
+        cached_actions: tx_manager
+            .cached_transactions()
+            .map(|(k, v)| (*k, *v))
+            .collect(),
     })
     .expect("failed to encode or reply from `state()`");
 }
 
+fn check_pair_tx_config(tx_config: TxConfig) -> Result<(), Error> {
+    tx_config.check().map_err(|_| Error::InvalidPairTxConfig)
+}
+
+fn check_token(token: ActorId) -> Result<(), Error> {
+    if token.is_zero() {
+        Err(Error::ZeroActorId)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_pair_circuit_breaker(circuit_breaker: CircuitBreaker) -> Result<(), Error> {
+    circuit_breaker
+        .check()
+        .map_err(|_| Error::InvalidPairCircuitBreaker)
+}
+
+async fn send_to_pair(
+    pair_actor: ActorId,
+    action: dex_pair_io::Action,
+) -> Result<Result<dex_pair_io::Event, dex_pair_io::Error>> {
+    msg::send_for_reply_as(pair_actor, action, 0, 0)?.await
+}
+
 fn reply(payload: impl Encode) -> Result<MessageId> {
     msg::reply(payload, 0)
 }
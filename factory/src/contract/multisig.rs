@@ -0,0 +1,189 @@
+use dex_factory_io::*;
+use gstd::{prelude::*, ActorId, HashMap};
+
+pub enum Confirmation {
+    Pending(u32),
+    Approved(Proposal),
+}
+
+#[derive(Default)]
+pub struct Multisig {
+    pub admins: Vec<ActorId>,
+    pub threshold: u32,
+    pub proposals: HashMap<u64, PendingProposal>,
+    nonce: u64,
+}
+
+impl Multisig {
+    pub fn new(admins: Vec<ActorId>, threshold: u32) -> Result<Self, Error> {
+        let mut multisig = Self::default();
+
+        for admin in admins {
+            multisig.check_new_admin(admin)?;
+            multisig.admins.push(admin);
+        }
+
+        multisig.check_threshold(threshold)?;
+        multisig.threshold = threshold;
+
+        Ok(multisig)
+    }
+
+    pub fn check_admin(&self, actor: ActorId) -> Result<(), Error> {
+        if self.admins.contains(&actor) {
+            Ok(())
+        } else {
+            Err(Error::AccessRestricted)
+        }
+    }
+
+    pub fn check_new_admin(&self, actor: ActorId) -> Result<(), Error> {
+        if actor.is_zero() {
+            Err(Error::ZeroActorId)
+        } else if self.admins.contains(&actor) {
+            Err(Error::AdminExists)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_removed_admin(&self, actor: ActorId) -> Result<(), Error> {
+        if !self.admins.contains(&actor) {
+            Err(Error::AdminNotFound)
+        } else if self.admins.len() <= self.threshold as usize {
+            Err(Error::InvalidThreshold)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_threshold(&self, threshold: u32) -> Result<(), Error> {
+        if threshold == 0 || threshold as usize > self.admins.len() {
+            Err(Error::InvalidThreshold)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn add_admin(&mut self, actor: ActorId) -> Result<Event, Error> {
+        self.check_new_admin(actor)?;
+        self.admins.push(actor);
+
+        Ok(Event::AdminAdded(actor))
+    }
+
+    pub fn remove_admin(&mut self, actor: ActorId) -> Result<Event, Error> {
+        self.check_removed_admin(actor)?;
+        self.admins.retain(|admin| *admin != actor);
+
+        for pending_proposal in self.proposals.values_mut() {
+            pending_proposal
+                .confirmations
+                .retain(|confirmation| *confirmation != actor);
+        }
+
+        self.proposals
+            .retain(|_, pending_proposal| !pending_proposal.confirmations.is_empty());
+
+        Ok(Event::AdminRemoved(actor))
+    }
+
+    pub fn set_threshold(&mut self, threshold: u32) -> Result<Event, Error> {
+        self.check_threshold(threshold)?;
+        self.threshold = threshold;
+
+        Ok(Event::ThresholdSet(threshold))
+    }
+
+    pub fn propose(
+        &mut self,
+        admin: ActorId,
+        proposal: Proposal,
+    ) -> Result<(u64, Confirmation), Error> {
+        self.check_admin(admin)?;
+
+        let id = self.nonce;
+
+        self.nonce = self.nonce.wrapping_add(1);
+        self.proposals.insert(
+            id,
+            PendingProposal {
+                proposal,
+                confirmations: vec![],
+            },
+        );
+
+        self.confirm(admin, id)
+            .map(|confirmation| (id, confirmation))
+    }
+
+    pub fn confirm(&mut self, admin: ActorId, id: u64) -> Result<Confirmation, Error> {
+        self.check_admin(admin)?;
+
+        let PendingProposal {
+            proposal,
+            confirmations,
+        } = self.proposals.get_mut(&id).ok_or(Error::ProposalNotFound)?;
+
+        if !confirmations.contains(&admin) {
+            confirmations.push(admin);
+        } else if (confirmations.len() as u32) < self.threshold {
+            return Err(Error::AlreadyConfirmed);
+        }
+        // Otherwise, the threshold was lowered after the proposal got its
+        // confirmations, so the approval is retried.
+
+        // Shouldn't overflow since the number of admins is limited by the
+        // storage.
+        let number = confirmations.len() as u32;
+
+        Ok(if number >= self.threshold {
+            Confirmation::Approved(*proposal)
+        } else {
+            Confirmation::Pending(number)
+        })
+    }
+
+    /// Revokes `admin`'s confirmation & removes the proposal if it doesn't
+    /// have confirmations anymore.
+    pub fn revoke(&mut self, admin: ActorId, id: u64) -> Result<Event, Error> {
+        let confirmations = &mut self
+            .proposals
+            .get_mut(&id)
+            .ok_or(Error::ProposalNotFound)?
+            .confirmations;
+        let index = confirmations
+            .iter()
+            .position(|confirmation| *confirmation == admin)
+            .ok_or(Error::NotConfirmed)?;
+
+        confirmations.swap_remove(index);
+
+        let number = confirmations.len() as u32;
+
+        if number == 0 {
+            self.proposals.remove(&id);
+        }
+
+        Ok(Event::Revoked {
+            id,
+            confirmations: number,
+        })
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.proposals.remove(&id);
+    }
+
+    pub fn pending_proposals(&self) -> Vec<(u64, PendingProposal)> {
+        let mut proposals: Vec<_> = self
+            .proposals
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+
+        proposals.sort_unstable_by_key(|(id, _)| *id);
+
+        proposals
+    }
+}
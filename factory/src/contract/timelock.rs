@@ -0,0 +1,86 @@
+use dex_factory_io::*;
+use gstd::{exec, prelude::*, HashMap};
+
+#[derive(Default)]
+pub struct Timelock {
+    pub delay: u64,
+    pub queue: HashMap<u64, QueuedOperation>,
+    nonce: u64,
+}
+
+impl Timelock {
+    pub fn new(delay: u64) -> Result<Self, Error> {
+        check_delay(delay)?;
+
+        Ok(Self {
+            delay,
+            ..Default::default()
+        })
+    }
+
+    pub fn set_delay(&mut self, delay: u64) -> Result<Event, Error> {
+        check_delay(delay)?;
+
+        self.delay = delay;
+
+        Ok(Event::TimelockDelaySet(delay))
+    }
+
+    pub fn queue(&mut self, action: AdminAction, eta: u64) -> Result<Event, Error> {
+        if eta < exec::block_timestamp().saturating_add(self.delay) {
+            return Err(Error::EtaTooEarly);
+        }
+
+        let id = self.nonce;
+
+        self.nonce = self.nonce.wrapping_add(1);
+        self.queue.insert(id, QueuedOperation { action, eta });
+
+        Ok(Event::Queued { id, action, eta })
+    }
+
+    pub fn cancel(&mut self, id: u64) -> Result<Event, Error> {
+        self.queue
+            .remove(&id)
+            .map(|_| Event::Canceled(id))
+            .ok_or(Error::OperationNotFound)
+    }
+
+    /// Returns the action of a queued operation that's ready for the
+    /// execution.
+    pub fn ready_action(&self, id: u64) -> Result<AdminAction, Error> {
+        let QueuedOperation { action, eta } =
+            self.queue.get(&id).ok_or(Error::OperationNotFound)?;
+        let block_ts = exec::block_timestamp();
+
+        if block_ts < *eta {
+            return Err(Error::OperationNotReady);
+        }
+
+        if block_ts > eta.saturating_add(GRACE_PERIOD) {
+            return Err(Error::OperationStale);
+        }
+
+        Ok(*action)
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.queue.remove(&id);
+    }
+
+    pub fn operations(&self) -> Vec<(u64, QueuedOperation)> {
+        let mut operations: Vec<_> = self.queue.iter().map(|(k, v)| (*k, *v)).collect();
+
+        operations.sort_unstable_by_key(|(id, _)| *id);
+
+        operations
+    }
+}
+
+fn check_delay(delay: u64) -> Result<(), Error> {
+    if delay > MAX_TIMELOCK_DELAY {
+        Err(Error::InvalidTimelockDelay)
+    } else {
+        Ok(())
+    }
+}
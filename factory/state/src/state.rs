@@ -1,6 +1,6 @@
 use dex_factory_io::*;
 use gmeta::{metawasm, Metadata};
-use gstd::{prelude::*, ActorId};
+use gstd::{prelude::*, ActorId, CodeId};
 
 #[metawasm]
 pub mod metafns {
@@ -10,19 +10,89 @@ pub mod metafns {
         state.fee_to
     }
 
-    pub fn fee_to_setter(state: State) -> ActorId {
-        state.fee_to_setter
+    pub fn admins(state: State) -> Vec<ActorId> {
+        state.admins
     }
 
-    pub fn pair(state: State, pair: (ActorId, ActorId)) -> ActorId {
-        state.pair(pair)
+    pub fn pair(state: State, pair: (ActorId, ActorId), fee_tier: FeeTier) -> ActorId {
+        state.pair(pair, fee_tier)
     }
 
     pub fn all_pairs_length(state: State) -> u32 {
         state.pairs.len().try_into().unwrap()
     }
 
-    pub fn all_pairs(state: State) -> Vec<((ActorId, ActorId), ActorId)> {
-        state.pairs
+    pub fn all_pairs(state: State) -> Vec<((ActorId, ActorId, FeeTier), ActorId)> {
+        state
+            .pairs
+            .into_iter()
+            .map(|(key, record)| (key, record.pair_actor))
+            .collect()
+    }
+
+    pub fn timelock_delay(state: State) -> u64 {
+        state.timelock_delay
+    }
+
+    pub fn queued_operations(state: State) -> Vec<(u64, QueuedOperation)> {
+        state.queue
+    }
+
+    pub fn queued_operation(state: State, id: u64) -> Option<QueuedOperation> {
+        state
+            .queue
+            .into_iter()
+            .find_map(|(queued_id, operation)| (queued_id == id).then_some(operation))
+    }
+
+    pub fn threshold(state: State) -> u32 {
+        state.threshold
+    }
+
+    pub fn proposals(state: State) -> Vec<(u64, PendingProposal)> {
+        state.proposals
+    }
+
+    pub fn fee_tiers(state: State) -> Vec<FeeTier> {
+        state.fee_tiers
+    }
+
+    pub fn pairs_for(state: State, pair: (ActorId, ActorId)) -> Vec<(FeeTier, ActorId)> {
+        state.pairs_for(pair)
+    }
+
+    pub fn pair_code(state: State) -> CodeId {
+        state.pair
+    }
+
+    pub fn is_action_cached(state: State, actor: ActorId, action: CachedAction) -> bool {
+        state.cached_actions.contains(&(actor, action))
+    }
+
+    pub fn pairs_by_creator(
+        state: State,
+        creator: ActorId,
+    ) -> Vec<((ActorId, ActorId, FeeTier), PairRecord)> {
+        state.pairs_by_creator(creator)
+    }
+
+    pub fn pairs_created_between(
+        state: State,
+        from: u64,
+        to: u64,
+    ) -> Vec<((ActorId, ActorId, FeeTier), PairRecord)> {
+        state.pairs_created_between(from, to)
+    }
+
+    pub fn pair_tx_config(state: State) -> TxConfig {
+        state.pair_tx_config
+    }
+
+    pub fn pair_circuit_breaker(state: State) -> CircuitBreaker {
+        state.pair_circuit_breaker
+    }
+
+    pub fn token_standard(state: State, token: ActorId) -> TokenStandard {
+        state.token_standard(token)
     }
 }
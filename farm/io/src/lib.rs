@@ -0,0 +1,324 @@
+#![no_std]
+
+use gear_lib::tx_manager;
+use gmeta::{InOut, Metadata};
+use gstd::{errors::Error as GstdError, prelude::*, ActorId};
+use primitive_types::U256;
+
+pub use dex_pair_io::Amount;
+pub use gear_lib::tx_manager::TransactionManagerError;
+
+/// The precision of [`Pool::acc_reward_per_share`].
+pub const ACC_REWARD_PRECISION: u128 = 10u128.pow(12);
+
+/// An index of a [`Pool`] in [`State::pools`].
+pub type PoolId = u32;
+
+pub struct ContractMetadata;
+
+impl Metadata for ContractMetadata {
+    type Init = InOut<Initialize, Result<(), Error>>;
+    type Handle = InOut<Action, Result<Event, Error>>;
+    type Reply = ();
+    type Others = ();
+    type Signal = ();
+    type State = State;
+}
+
+/// Initializes the contract.
+#[derive(
+    Default, Encode, Decode, TypeInfo, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash,
+)]
+pub struct Initialize {
+    /// An actor that manages pools & the reward rate.
+    pub admin: ActorId,
+    /// The Factory contract whose Pair contracts can be added as pools.
+    pub factory: ActorId,
+    /// A fungible token in which rewards are paid.
+    ///
+    /// The contract pays rewards from its own balance of this token, so it
+    /// must be funded by transferring tokens to the contract.
+    pub reward_token: ActorId,
+    /// An amount of reward tokens distributed among all pools every second.
+    pub reward_per_second: u128,
+}
+
+/// The contract state.
+///
+/// For more info about fields, see [`Initialize`].
+#[derive(Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
+pub struct State {
+    pub admin: ActorId,
+    pub factory: ActorId,
+    pub reward_token: ActorId,
+    pub reward_per_second: u128,
+    /// A sum of [`Pool::alloc_point`]s of all pools.
+    pub total_alloc_point: u64,
+    /// Pools in the order of their [`PoolId`]s.
+    pub pools: Vec<Pool>,
+    pub stakes: Vec<((PoolId, ActorId), Stake)>,
+    pub cached_actions: Vec<(ActorId, CachedAction)>,
+}
+
+impl State {
+    pub fn stake(&self, pool_id: PoolId, user: ActorId) -> Stake {
+        self.stakes
+            .iter()
+            .find_map(|(key, stake)| (*key == (pool_id, user)).then_some(*stake))
+            .unwrap_or_default()
+    }
+
+    /// Returns a reward that `user` will be able to claim from a pool at
+    /// `block_ts`.
+    pub fn pending_reward(&self, pool_id: PoolId, user: ActorId, block_ts: u64) -> U256 {
+        let Some(pool) = self.pools.get(pool_id as usize) else {
+            return U256::zero();
+        };
+        let acc_reward_per_share =
+            pool.acc_reward_per_share_at(block_ts, self.reward_per_second, self.total_alloc_point);
+        let stake = self.stake(pool_id, user);
+
+        stake.unclaimed + stake.accrued_reward(acc_reward_per_share)
+    }
+}
+
+/// A pool of staked liquidity tokens of a Pair contract.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct Pool {
+    /// The Pair contract whose liquidity tokens are staked in the pool.
+    pub pair: ActorId,
+    /// A share of [`State::reward_per_second`] that the pool gets relative
+    /// to [`State::total_alloc_point`].
+    pub alloc_point: u32,
+    /// Timestamp (in ms) up to which rewards were accumulated.
+    pub last_reward_ts: u64,
+    /// Rewards accumulated per 1 staked liquidity token multiplied by
+    /// [`ACC_REWARD_PRECISION`].
+    pub acc_reward_per_share: U256,
+    /// An amount of liquidity tokens staked in the pool.
+    pub total_staked: Amount,
+}
+
+impl Pool {
+    /// Returns [`Pool::acc_reward_per_share`] as if rewards were accumulated
+    /// up to `block_ts`.
+    ///
+    /// Rewards for a period when nothing was staked aren't distributed.
+    pub fn acc_reward_per_share_at(
+        &self,
+        block_ts: u64,
+        reward_per_second: u128,
+        total_alloc_point: u64,
+    ) -> U256 {
+        if block_ts <= self.last_reward_ts || self.total_staked.is_zero() || total_alloc_point == 0
+        {
+            return self.acc_reward_per_share;
+        }
+
+        let numerator = U256::from(block_ts - self.last_reward_ts)
+            * U256::from(reward_per_second)
+            * self.alloc_point
+            * U256::from(ACC_REWARD_PRECISION);
+        // Timestamps are in ms.
+        let denominator = U256::from(total_alloc_point) * 1000 * self.total_staked;
+
+        self.acc_reward_per_share + numerator / denominator
+    }
+}
+
+/// A user's stake in a [`Pool`].
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct Stake {
+    /// An amount of staked liquidity tokens.
+    pub amount: Amount,
+    /// A part of the reward for `amount` that doesn't belong to the user
+    /// because it was accumulated before the last stake update.
+    pub reward_debt: U256,
+    /// A reward accrued before the last stake update & not claimed yet.
+    pub unclaimed: U256,
+}
+
+impl Stake {
+    /// Returns a reward accrued since the last stake update.
+    pub fn accrued_reward(&self, acc_reward_per_share: U256) -> U256 {
+        (self.amount * acc_reward_per_share / ACC_REWARD_PRECISION).saturating_sub(self.reward_debt)
+    }
+}
+
+/// A part of [`Action`].
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum InnerAction {
+    /// Adds a pool for liquidity tokens of a given Pair contract.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`](gstd::msg::source) must be the admin.
+    /// - The Pair contract must belong to the linked Factory contract.
+    /// - The contract mustn't already have a pool for the Pair contract.
+    ///
+    /// On success, replies with [`Event::PoolAdded`].
+    AddPool { pair: ActorId, alloc_point: u32 },
+
+    /// Sets [`Pool::alloc_point`] of a pool.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// On success, replies with [`Event::PoolSet`].
+    SetPool { pool_id: PoolId, alloc_point: u32 },
+
+    /// Sets an amount of reward tokens distributed every second.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// On success, replies with [`Event::RewardPerSecondSet`].
+    SetRewardPerSecond(u128),
+
+    /// Stakes liquidity tokens of [`msg::source()`](gstd::msg::source) in a
+    /// pool.
+    ///
+    /// # Requirements
+    /// - `amount` mustn't equal to 0.
+    /// - [`msg::source()`](gstd::msg::source) must approve the contract to
+    /// transfer liquidity tokens with
+    /// [`InnerAction::Approve`](dex_pair_io::InnerAction::Approve) of the Pair
+    /// contract.
+    ///
+    /// On success, replies with [`Event::Deposited`].
+    Deposit { pool_id: PoolId, amount: Amount },
+
+    /// Unstakes liquidity tokens of [`msg::source()`](gstd::msg::source) from
+    /// a pool.
+    ///
+    /// The accrued reward stays in the contract until
+    /// [`InnerAction::Claim`].
+    ///
+    /// # Requirements
+    /// - `amount` mustn't equal to 0.
+    /// - [`msg::source()`](gstd::msg::source) must have the same or a greater
+    /// amount of staked liquidity tokens than a given one.
+    ///
+    /// On success, replies with [`Event::Withdrawn`].
+    Withdraw { pool_id: PoolId, amount: Amount },
+
+    /// Transfers the reward accrued in a pool to
+    /// [`msg::source()`](gstd::msg::source).
+    ///
+    /// # Requirements
+    /// - The accrued reward mustn't equal to 0.
+    ///
+    /// On success, replies with [`Event::Claimed`].
+    Claim(PoolId),
+}
+
+/// Sends the contract info about what it should do.
+pub type Action = tx_manager::Action<InnerAction>;
+
+/// A result of successfully processed [`Action`].
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum Event {
+    /// Should be returned from [`InnerAction::AddPool`].
+    PoolAdded {
+        pool_id: PoolId,
+        pair: ActorId,
+        alloc_point: u32,
+    },
+    /// Should be returned from [`InnerAction::SetPool`].
+    PoolSet { pool_id: PoolId, alloc_point: u32 },
+    /// Should be returned from [`InnerAction::SetRewardPerSecond`].
+    RewardPerSecondSet(u128),
+    /// Should be returned from [`InnerAction::Deposit`].
+    Deposited {
+        pool_id: PoolId,
+        user: ActorId,
+        amount: Amount,
+    },
+    /// Should be returned from [`InnerAction::Withdraw`].
+    Withdrawn {
+        pool_id: PoolId,
+        user: ActorId,
+        amount: Amount,
+    },
+    /// Should be returned from [`InnerAction::Claim`].
+    Claimed {
+        pool_id: PoolId,
+        user: ActorId,
+        reward: u128,
+    },
+}
+
+/// Error variants of failed [`Action`].
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
+pub enum Error {
+    /// See [`GstdError`].
+    GstdError(String),
+    /// [`msg::source()`](gstd::msg::source) doesn't have the right to perform
+    /// an action.
+    AccessRestricted,
+    /// [`ActorId::zero()`] was found where it's forbidden.
+    ZeroActorId,
+    /// A zero amount of liquidity tokens was provided.
+    InsufficientAmount,
+    /// The contract already has a pool for a given Pair contract.
+    PoolExists,
+    PoolNotFound,
+    /// A given Pair contract doesn't belong to the linked Factory contract.
+    PairVerificationFailed,
+    /// [`msg::source()`](gstd::msg::source) doesn't have enough staked
+    /// liquidity tokens.
+    InsufficientStake,
+    /// [`msg::source()`](gstd::msg::source) doesn't have an accrued reward.
+    NothingToClaim,
+    /// The Pair contract failed to transfer liquidity tokens.
+    LpTransferFailed(dex_pair_io::Error),
+    /// The reward token contract failed to complete a transfer action.
+    ///
+    /// Most often, the reason is that the contract doesn't have enough reward
+    /// tokens.
+    TransferFailed,
+    /// The reward token contract or a Pair contract replied with an
+    /// unexpected payload.
+    UnexpectedTokenReply,
+    /// An overflow occurred during calculations.
+    Overflow,
+    TxCacheError(TransactionManagerError),
+}
+
+impl From<GstdError> for Error {
+    fn from(error: GstdError) -> Self {
+        Self::GstdError(error.to_string())
+    }
+}
+
+impl From<TransactionManagerError> for Error {
+    fn from(error: TransactionManagerError) -> Self {
+        Self::TxCacheError(error)
+    }
+}
+
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub enum CachedAction {
+    Deposit {
+        pool_id: PoolId,
+        amount: Amount,
+        /// Whether liquidity tokens have been transferred to the contract, so
+        /// they aren't pulled again.
+        is_transferred: bool,
+    },
+    Withdraw {
+        pool_id: PoolId,
+        amount: Amount,
+        /// Whether the stake has been decreased, so it isn't decreased again.
+        is_unstaked: bool,
+    },
+    Claim {
+        pool_id: PoolId,
+        /// A reward taken from the stake for the transfer. Equals 0 until the
+        /// reward is taken.
+        reward: u128,
+    },
+}
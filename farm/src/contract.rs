@@ -0,0 +1,506 @@
+use dex_farm_io::*;
+use gear_lib::tx_manager::{ActionKind, TransactionManager};
+use gstd::{errors::Result, exec, msg, prelude::*, ActorId, HashMap};
+use primitive_types::U256;
+
+mod utils;
+
+fn state_mut() -> &'static mut (Contract, TransactionManager<CachedAction>) {
+    unsafe { STATE.as_mut().expect("state isn't initialized") }
+}
+
+static mut STATE: Option<(Contract, TransactionManager<CachedAction>)> = None;
+
+#[derive(Default)]
+struct Contract {
+    admin: ActorId,
+    factory: ActorId,
+    reward_token: ActorId,
+    reward_per_second: u128,
+    total_alloc_point: u64,
+    pools: Vec<Pool>,
+    stakes: HashMap<(PoolId, ActorId), Stake>,
+}
+
+impl Contract {
+    fn check_admin(&self, msg_source: ActorId) -> Result<(), Error> {
+        if msg_source == self.admin {
+            Ok(())
+        } else {
+            Err(Error::AccessRestricted)
+        }
+    }
+
+    fn check_new_pool(&self, pair: ActorId) -> Result<(), Error> {
+        if self.pools.iter().any(|pool| pool.pair == pair) {
+            Err(Error::PoolExists)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Verifies that `pair` was created or registered by the linked Factory
+    /// contract.
+    async fn verify_pair(&self, pair: ActorId) -> Result<(), Error> {
+        let reply: Result<dex_pair_io::Event, dex_pair_io::Error> = utils::send(
+            pair,
+            dex_pair_io::Action::new(dex_pair_io::InnerAction::GetInfo),
+        )?
+        .await?;
+        let Ok(dex_pair_io::Event::Info {
+            factory,
+            token,
+            fee_tier,
+        }) = reply
+        else {
+            return Err(Error::PairVerificationFailed);
+        };
+
+        if factory != self.factory {
+            return Err(Error::PairVerificationFailed);
+        }
+
+        let reply: Result<dex_factory_io::Event, dex_factory_io::Error> = utils::send(
+            factory,
+            dex_factory_io::Action::new(dex_factory_io::InnerAction::GetPair(
+                token.0, token.1, fee_tier,
+            )),
+        )?
+        .await?;
+
+        if let Ok(dex_factory_io::Event::Pair {
+            pair_actor: Some(pair_actor),
+            ..
+        }) = reply
+        {
+            if pair_actor == pair {
+                return Ok(());
+            }
+        }
+
+        Err(Error::PairVerificationFailed)
+    }
+
+    async fn add_pool(
+        &mut self,
+        msg_source: ActorId,
+        pair: ActorId,
+        alloc_point: u32,
+    ) -> Result<Event, Error> {
+        self.check_admin(msg_source)?;
+        self.check_new_pool(pair)?;
+        self.verify_pair(pair).await?;
+
+        // The same pair could be added while the verification was in progress.
+        self.check_new_pool(pair)?;
+
+        let pool_id = self.pools.len().try_into().map_err(|_| Error::Overflow)?;
+
+        self.update_pools();
+        self.total_alloc_point += u64::from(alloc_point);
+        self.pools.push(Pool {
+            pair,
+            alloc_point,
+            last_reward_ts: exec::block_timestamp(),
+            ..Default::default()
+        });
+
+        Ok(Event::PoolAdded {
+            pool_id,
+            pair,
+            alloc_point,
+        })
+    }
+
+    fn set_pool(
+        &mut self,
+        msg_source: ActorId,
+        pool_id: PoolId,
+        alloc_point: u32,
+    ) -> Result<Event, Error> {
+        self.check_admin(msg_source)?;
+
+        let old_alloc_point = self.pool(pool_id)?.alloc_point;
+
+        self.update_pools();
+        self.total_alloc_point =
+            self.total_alloc_point - u64::from(old_alloc_point) + u64::from(alloc_point);
+        self.pools[pool_id as usize].alloc_point = alloc_point;
+
+        Ok(Event::PoolSet {
+            pool_id,
+            alloc_point,
+        })
+    }
+
+    fn set_reward_per_second(
+        &mut self,
+        msg_source: ActorId,
+        reward_per_second: u128,
+    ) -> Result<Event, Error> {
+        self.check_admin(msg_source)?;
+        self.update_pools();
+        self.reward_per_second = reward_per_second;
+
+        Ok(Event::RewardPerSecondSet(reward_per_second))
+    }
+
+    fn pool(&self, pool_id: PoolId) -> Result<&Pool, Error> {
+        self.pools.get(pool_id as usize).ok_or(Error::PoolNotFound)
+    }
+
+    /// Accumulates rewards of all pools up to the current block. Must be
+    /// called before changing the reward distribution.
+    fn update_pools(&mut self) {
+        let block_ts = exec::block_timestamp();
+
+        for pool in &mut self.pools {
+            pool.acc_reward_per_share = pool.acc_reward_per_share_at(
+                block_ts,
+                self.reward_per_second,
+                self.total_alloc_point,
+            );
+            pool.last_reward_ts = block_ts;
+        }
+    }
+
+    /// Accumulates rewards of a pool up to the current block, moves the
+    /// accrued reward of `user` to [`Stake::unclaimed`], & then changes the
+    /// stake by `amount`.
+    fn update_stake(
+        &mut self,
+        pool_id: PoolId,
+        user: ActorId,
+        amount: Amount,
+        is_deposit: bool,
+    ) -> Result<&mut Stake, Error> {
+        let pool = self
+            .pools
+            .get_mut(pool_id as usize)
+            .ok_or(Error::PoolNotFound)?;
+        let stake = self.stakes.entry((pool_id, user)).or_default();
+
+        if !is_deposit && stake.amount < amount {
+            return Err(Error::InsufficientStake);
+        }
+
+        let block_ts = exec::block_timestamp();
+
+        pool.acc_reward_per_share =
+            pool.acc_reward_per_share_at(block_ts, self.reward_per_second, self.total_alloc_point);
+        pool.last_reward_ts = block_ts;
+        stake.unclaimed += stake.accrued_reward(pool.acc_reward_per_share);
+
+        if is_deposit {
+            stake.amount += amount;
+            pool.total_staked += amount;
+        } else {
+            stake.amount -= amount;
+            pool.total_staked -= amount;
+        }
+
+        stake.reward_debt = stake.amount * pool.acc_reward_per_share / ACC_REWARD_PRECISION;
+
+        Ok(stake)
+    }
+
+    async fn deposit(
+        &mut self,
+        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        msg_source: ActorId,
+        pool_id: PoolId,
+        amount: Amount,
+    ) -> Result<Event, Error> {
+        if amount.is_zero() {
+            return Err(Error::InsufficientAmount);
+        }
+
+        let pair = self.pool(pool_id)?.pair;
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::Deposit {
+                pool_id,
+                amount,
+                is_transferred: false,
+            }),
+        )?;
+        let is_transferred = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::Deposit {
+                pool_id: cached_pool_id,
+                amount: cached_amount,
+                is_transferred,
+            } = tx_data
+            {
+                (*cached_pool_id == pool_id && *cached_amount == amount).then_some(is_transferred)
+            } else {
+                None
+            }
+        })?;
+
+        // An interrupted deposit could've already transferred liquidity tokens.
+        if !*is_transferred {
+            utils::transfer_lp(
+                pair,
+                dex_pair_io::InnerAction::TransferFrom {
+                    from: msg_source,
+                    to: exec::program_id(),
+                    amount,
+                },
+            )
+            .await?;
+
+            *is_transferred = true;
+        }
+
+        // Rewards are accumulated after the transfer since the block could
+        // change while it was in progress.
+        self.update_stake(pool_id, msg_source, amount, true)?;
+
+        Ok(Event::Deposited {
+            pool_id,
+            user: msg_source,
+            amount,
+        })
+    }
+
+    async fn withdraw(
+        &mut self,
+        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        msg_source: ActorId,
+        pool_id: PoolId,
+        amount: Amount,
+    ) -> Result<Event, Error> {
+        if amount.is_zero() {
+            return Err(Error::InsufficientAmount);
+        }
+
+        let pair = self.pool(pool_id)?.pair;
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::Withdraw {
+                pool_id,
+                amount,
+                is_unstaked: false,
+            }),
+        )?;
+        let is_unstaked = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::Withdraw {
+                pool_id: cached_pool_id,
+                amount: cached_amount,
+                is_unstaked,
+            } = tx_data
+            {
+                (*cached_pool_id == pool_id && *cached_amount == amount).then_some(is_unstaked)
+            } else {
+                None
+            }
+        })?;
+
+        // The stake is decreased before the transfer, so it can't be withdrawn
+        // twice. An interrupted withdrawal has already decreased it.
+        if !*is_unstaked {
+            self.update_stake(pool_id, msg_source, amount, false)?;
+
+            *is_unstaked = true;
+        }
+
+        if let Err(error) = utils::transfer_lp(
+            pair,
+            dex_pair_io::InnerAction::Transfer {
+                to: msg_source,
+                amount,
+            },
+        )
+        .await
+        {
+            self.update_stake(pool_id, msg_source, amount, true)?;
+
+            return Err(error);
+        }
+
+        Ok(Event::Withdrawn {
+            pool_id,
+            user: msg_source,
+            amount,
+        })
+    }
+
+    async fn claim(
+        &mut self,
+        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        msg_source: ActorId,
+        pool_id: PoolId,
+    ) -> Result<Event, Error> {
+        self.pool(pool_id)?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::Claim { pool_id, reward: 0 }),
+        )?;
+        let reward = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::Claim {
+                pool_id: cached_pool_id,
+                reward,
+            } = tx_data
+            {
+                (*cached_pool_id == pool_id).then_some(reward)
+            } else {
+                None
+            }
+        })?;
+
+        // An interrupted claim has already taken the reward from the stake.
+        if *reward == 0 {
+            if !self.stakes.contains_key(&(pool_id, msg_source)) {
+                return Err(Error::NothingToClaim);
+            }
+
+            let stake = self.update_stake(pool_id, msg_source, U256::zero(), true)?;
+
+            if stake.unclaimed.is_zero() {
+                return Err(Error::NothingToClaim);
+            }
+
+            *reward = stake.unclaimed.try_into().map_err(|_| Error::Overflow)?;
+            stake.unclaimed = U256::zero();
+        }
+
+        let reward = *reward;
+
+        if let Err(error) = utils::transfer_tokens(
+            &mut tx_guard.stepper,
+            self.reward_token,
+            exec::program_id(),
+            msg_source,
+            reward,
+        )
+        .await
+        {
+            self.stakes
+                .entry((pool_id, msg_source))
+                .or_default()
+                .unclaimed += U256::from(reward);
+
+            return Err(error);
+        }
+
+        Ok(Event::Claimed {
+            pool_id,
+            user: msg_source,
+            reward,
+        })
+    }
+}
+
+#[no_mangle]
+extern "C" fn init() {
+    let result = process_init();
+    let is_err = result.is_err();
+
+    msg::reply(result, 0).expect("failed to encode or reply from `init()`");
+
+    if is_err {
+        exec::exit(ActorId::zero());
+    }
+}
+
+fn process_init() -> Result<(), Error> {
+    let Initialize {
+        admin,
+        factory,
+        reward_token,
+        reward_per_second,
+    } = msg::load()?;
+
+    if admin.is_zero() || factory.is_zero() || reward_token.is_zero() {
+        return Err(Error::ZeroActorId);
+    }
+
+    let contract = Contract {
+        admin,
+        factory,
+        reward_token,
+        reward_per_second,
+        ..Default::default()
+    };
+
+    unsafe { STATE = Some((contract, TransactionManager::default())) };
+
+    Ok(())
+}
+
+#[gstd::async_main]
+async fn main() {
+    msg::reply(process_handle().await, 0).expect("failed to encode or reply `handle()`");
+}
+
+async fn process_handle() -> Result<Event, Error> {
+    let Action {
+        action,
+        kind: action_kind,
+    } = msg::load()?;
+    let (contract, tx_manager) = state_mut();
+    let msg_source = msg::source();
+
+    match action {
+        InnerAction::AddPool { pair, alloc_point } => {
+            contract.add_pool(msg_source, pair, alloc_point).await
+        }
+        InnerAction::SetPool {
+            pool_id,
+            alloc_point,
+        } => contract.set_pool(msg_source, pool_id, alloc_point),
+        InnerAction::SetRewardPerSecond(reward_per_second) => {
+            contract.set_reward_per_second(msg_source, reward_per_second)
+        }
+        InnerAction::Deposit { pool_id, amount } => {
+            contract
+                .deposit((tx_manager, action_kind), msg_source, pool_id, amount)
+                .await
+        }
+        InnerAction::Withdraw { pool_id, amount } => {
+            contract
+                .withdraw((tx_manager, action_kind), msg_source, pool_id, amount)
+                .await
+        }
+        InnerAction::Claim(pool_id) => {
+            contract
+                .claim((tx_manager, action_kind), msg_source, pool_id)
+                .await
+        }
+    }
+}
+
+#[no_mangle]
+extern "C" fn state() {
+    let (
+        Contract {
+            admin,
+            factory,
+            reward_token,
+            reward_per_second,
+            total_alloc_point,
+            pools,
+            stakes,
+        },
+        tx_manager,
+    ) = state_mut();
+
+    msg::reply(
+        State {
+            admin: *admin,
+            factory: *factory,
+            reward_token: *reward_token,
+            reward_per_second: *reward_per_second,
+            total_alloc_point: *total_alloc_point,
+            pools: pools.clone(),
+            stakes: stakes.iter().map(|(k, v)| (*k, *v)).collect(),
+            cached_actions: tx_manager
+                .cached_transactions()
+                .map(|(k, v)| (*k, *v))
+                .collect(),
+        },
+        0,
+    )
+    .expect("failed to encode or reply from `state()`");
+}
@@ -0,0 +1,49 @@
+use dex_farm_io::*;
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gear_lib::tx_manager::Stepper;
+use gstd::{
+    errors::Result,
+    msg::{self, CodecMessageFuture},
+    prelude::*,
+    ActorId,
+};
+
+pub fn send<T: Decode>(to: ActorId, payload: impl Encode) -> Result<CodecMessageFuture<T>> {
+    msg::send_for_reply_as(to, payload, 0, 0)
+}
+
+pub async fn transfer_tokens(
+    stepper: &mut Stepper,
+    token: ActorId,
+    sender: ActorId,
+    recipient: ActorId,
+    amount: u128,
+) -> Result<(), Error> {
+    let payload = FTokenAction::Message {
+        transaction_id: stepper.step()?,
+        payload: LogicAction::Transfer {
+            sender,
+            recipient,
+            amount,
+        },
+    };
+
+    match send(token, payload)?.await? {
+        FTokenEvent::Ok => Ok(()),
+        FTokenEvent::Err => Err(Error::TransferFailed),
+        _ => Err(Error::UnexpectedTokenReply),
+    }
+}
+
+/// Sends an action to a Pair contract & expects a transfer of liquidity
+/// tokens in reply.
+pub async fn transfer_lp(pair: ActorId, action: dex_pair_io::InnerAction) -> Result<(), Error> {
+    let reply: Result<dex_pair_io::Event, dex_pair_io::Error> =
+        send(pair, dex_pair_io::Action::new(action))?.await?;
+
+    match reply {
+        Ok(dex_pair_io::Event::Transfer(_)) => Ok(()),
+        Ok(_) => Err(Error::UnexpectedTokenReply),
+        Err(error) => Err(Error::LpTransferFailed(error)),
+    }
+}
@@ -0,0 +1,7 @@
+use gear_wasm_builder::WasmBuilder;
+
+fn main() {
+    WasmBuilder::new_metawasm()
+        .exclude_features(["binary-vendor"])
+        .build()
+}
@@ -0,0 +1,37 @@
+use dex_farm_io::*;
+use gmeta::{metawasm, Metadata};
+use gstd::{prelude::*, ActorId};
+use primitive_types::U256;
+
+#[metawasm]
+pub mod metafns {
+    pub type State = <ContractMetadata as Metadata>::State;
+
+    pub fn pools(state: State) -> Vec<Pool> {
+        state.pools
+    }
+
+    pub fn pool(state: State, pool_id: PoolId) -> Option<Pool> {
+        state.pools.get(pool_id as usize).copied()
+    }
+
+    pub fn stake(state: State, pool_id: PoolId, user: ActorId) -> Stake {
+        state.stake(pool_id, user)
+    }
+
+    pub fn pending_reward(state: State, pool_id: PoolId, user: ActorId, block_ts: u64) -> U256 {
+        state.pending_reward(pool_id, user, block_ts)
+    }
+
+    pub fn reward_per_second(state: State) -> u128 {
+        state.reward_per_second
+    }
+
+    pub fn total_alloc_point(state: State) -> u64 {
+        state.total_alloc_point
+    }
+
+    pub fn is_action_cached(state: State, actor: ActorId, action: CachedAction) -> bool {
+        state.cached_actions.contains(&(actor, action))
+    }
+}
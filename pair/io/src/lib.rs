@@ -1,13 +1,14 @@
 #![no_std]
 
+use blake2::{digest::consts::U32, Blake2b, Digest};
 use gear_lib::tx_manager;
 use gmeta::{InOut, Metadata};
-use gstd::{errors::Error as GstdError, prelude::*, ActorId};
-use primitive_types::U256;
+use gstd::{errors::Error as GstdError, prelude::*, ActorId, MessageId, ReservationId};
+use primitive_types::{U256, U512};
 
 pub use gear_lib::{
     tokens::{
-        fungible::{encodable::FTState, FTError, FTTransfer},
+        fungible::{encodable::FTState, FTApproval, FTError, FTTransfer},
         types::Amount,
     },
     tx_manager::TransactionManagerError,
@@ -20,6 +21,59 @@ pub use gear_lib::{
 /// on the first mint (first [`InnerAction::AddLiquidity`]).
 pub const MINIMUM_LIQUIDITY: u64 = 10u64.pow(3);
 
+/// A swap fee in basis points (hundredths of 1%).
+///
+/// Must be less than [`FEE_DENOMINATOR`].
+pub type FeeTier = u16;
+
+/// The denominator of [`FeeTier`].
+pub const FEE_DENOMINATOR: FeeTier = 10000;
+/// The 0.3% fee tier.
+pub const DEFAULT_FEE_TIER: FeeTier = 30;
+
+/// The denominator of amounts in basis points (price impacts & slippage
+/// tolerances).
+pub const BPS_DENOMINATOR: u16 = 10000;
+
+/// The duration (1 hour in ms) of a [`StatsBucket`].
+pub const STATS_BUCKET_DURATION: u64 = 60 * 60 * 1000;
+/// The maximum number of [`StatsBucket`]s in [`State::recent_stats`] (the
+/// last 24 hours).
+pub const STATS_BUCKETS: u64 = 24;
+
+/// The default [`TxConfig::expiry`] (1 day in ms).
+pub const DEFAULT_TX_EXPIRY: u64 = 24 * 60 * 60 * 1000;
+/// The minimum [`TxConfig::expiry`] (1 hour in ms).
+///
+/// Messages can't wait for replies that long, so an expired transaction
+/// can't be resumed by its original message.
+pub const MIN_TX_EXPIRY: u64 = 60 * 60 * 1000;
+/// The default [`TxConfig::max_cached_txs`].
+pub const DEFAULT_MAX_CACHED_TXS: u32 = 1000;
+/// Gas reserved on a transaction's creation for [`InnerAction::Complete`].
+pub const TX_COMPLETION_GAS: u64 = 5_000_000_000;
+/// A number of blocks (1 day with 3-second blocks) for which
+/// [`TX_COMPLETION_GAS`] is reserved.
+pub const TX_COMPLETION_GAS_DURATION: u32 = 28_800;
+/// Gas reserved for the signal handler by every message holding [`Lock`].
+pub const SIGNAL_GAS: u64 = 100_000_000;
+
+/// A number of blocks after a [`Commitment`]'s block during which it can be
+/// revealed.
+pub const REVEAL_WINDOW: u32 = 100;
+
+/// A number of blocks during which [`Order`]s are collected into a [`Batch`].
+pub const BATCH_EPOCH: u32 = 10;
+/// The maximum number of [`Order`]s in a [`Batch`].
+pub const MAX_BATCH_ORDERS: u32 = 100;
+/// The maximum number of [`Batch::orders`] paid out or refunded by one
+/// [`InnerAction::SettleBatch`] message, so [`TX_COMPLETION_GAS`] is enough to
+/// complete it.
+pub const MAX_SETTLED_ORDERS: u32 = 5;
+/// Gas reserved on a [`Batch`]'s creation for its settlement with delayed
+/// [`InnerAction::SettleBatch`].
+pub const BATCH_SETTLEMENT_GAS: u64 = 50_000_000_000;
+
 pub struct ContractMetadata;
 
 impl Metadata for ContractMetadata {
@@ -27,7 +81,7 @@ impl Metadata for ContractMetadata {
     type Handle = InOut<Action, Result<Event, Error>>;
     type Reply = ();
     type Others = ();
-    type Signal = ();
+    type Signal = Action;
     type State = State;
 }
 
@@ -38,6 +92,141 @@ impl Metadata for ContractMetadata {
 pub struct Initialize {
     pub pair: (ActorId, ActorId),
     pub factory: ActorId,
+    /// A fee charged from the input amount of every swap.
+    pub fee_tier: FeeTier,
+    /// The current fee receiver of the Factory contract.
+    ///
+    /// The contract caches it and then gets its updates from the Factory
+    /// contract via [`InnerAction::UpdateFeeTo`].
+    pub fee_to: ActorId,
+    /// An actor whose initial liquidity the contract awaits.
+    ///
+    /// Until the first liquidity addition, only this actor can add liquidity
+    /// (directly or through the Factory contract with
+    /// [`InnerAction::AddLiquidityFor`]), & [`InnerAction::Sync`] is
+    /// forbidden, so nobody can set the initial price before it. The Factory
+    /// contract can stop awaiting it with [`InnerAction::ClearSeeder`].
+    ///
+    /// If it's [`None`], anyone can add the initial liquidity.
+    pub seeder: Option<ActorId>,
+    /// The current config of cached transactions of the Factory contract.
+    ///
+    /// Further updates come from the Factory contract via
+    /// [`InnerAction::UpdateTxConfig`].
+    pub tx_config: TxConfig,
+    /// The current limits of swaps of the Factory contract.
+    ///
+    /// Further updates come from the Factory contract via
+    /// [`InnerAction::UpdateCircuitBreaker`].
+    pub circuit_breaker: CircuitBreaker,
+    /// Standards of the A & B tokens (`pair`).
+    pub token_standard: (TokenStandard, TokenStandard),
+}
+
+/// A standard of a fungible token contract, which determines how the
+/// contract transfers the token & queries its balances.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub enum TokenStandard {
+    /// The sharded fungible token (`ft-main-io`).
+    #[default]
+    ShardedFt,
+    /// A fungible token built on gear-lib's `FTState` with the same interface
+    /// as liquidity tokens of the Pair contract ([`InnerAction::Transfer`],
+    /// [`InnerAction::TransferFrom`], & [`InnerAction::GetLpBalance`]).
+    ///
+    /// Its transfers don't carry transaction IDs, so, unlike
+    /// [`TokenStandard::ShardedFt`] ones, they aren't deduplicated. A resumed
+    /// transaction doesn't repeat its transfers that have got a reply (see
+    /// [`CachedTx::transferred`]), & an abandoned one checks the contract's
+    /// balance instead of repeating a deposit transfer without a reply. A user
+    /// must approve the contract to transfer their tokens with
+    /// [`InnerAction::Approve`].
+    GearLibFt,
+}
+
+/// The config of cached transactions.
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub struct TxConfig {
+    /// A period (in ms) after a transaction's creation during which it can
+    /// be resumed with [`ActionKind::Retry`](tx_manager::ActionKind::Retry).
+    /// After this period, the transaction can only be abandoned with
+    /// [`InnerAction::Abandon`], unless it has started transferring tokens out
+    /// of the contract (see [`CachedTx::is_settling`]).
+    ///
+    /// Mustn't be less than [`MIN_TX_EXPIRY`].
+    pub expiry: u64,
+    /// The maximum number of cached transactions.
+    ///
+    /// Each actor can have only 1 cached transaction, & new transactions of
+    /// other actors are rejected until expired transactions are abandoned.
+    ///
+    /// Mustn't equal to 0.
+    pub max_cached_txs: u32,
+}
+
+impl Default for TxConfig {
+    fn default() -> Self {
+        Self {
+            expiry: DEFAULT_TX_EXPIRY,
+            max_cached_txs: DEFAULT_MAX_CACHED_TXS,
+        }
+    }
+}
+
+impl TxConfig {
+    pub fn check(&self) -> Result<(), Error> {
+        if self.expiry < MIN_TX_EXPIRY || self.max_cached_txs == 0 {
+            Err(Error::InvalidTxConfig)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Limits of swaps that protect the price from a single large or
+/// manipulative trade.
+///
+/// Each limit is disabled if it equals 0.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct CircuitBreaker {
+    /// The maximum deviation (in basis points) of the A token's price after a
+    /// swap from its TWAP (time-weighted average price) over `twap_window`.
+    pub max_deviation: u16,
+    /// A period (in ms) over which the TWAP is measured.
+    ///
+    /// Mustn't equal to 0 if `max_deviation` is enabled.
+    pub twap_window: u64,
+    /// The maximum input amount of a swap in basis points of the input
+    /// token's reserve.
+    ///
+    /// Mustn't be greater than [`BPS_DENOMINATOR`].
+    pub max_trade_size: u16,
+}
+
+impl CircuitBreaker {
+    pub fn check(&self) -> Result<(), Error> {
+        if (self.max_deviation != 0 && self.twap_window == 0)
+            || self.max_trade_size > BPS_DENOMINATOR
+        {
+            Err(Error::InvalidCircuitBreaker)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// [`State::cumulative_price`] of the A token at some timestamp.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct PriceObservation {
+    pub cumulative_price: U256,
+    /// Timestamp (in ms) of the observation.
+    pub block_ts: u64,
 }
 
 /// The contract state.
@@ -46,9 +235,17 @@ pub struct State {
     /// [`ActorId`] of the Factory contract from which [`ActorId`] of the fee
     /// receiver (`fee_to`) is obtained.
     pub factory: ActorId,
+    /// The cached [`ActorId`] of the fee receiver.
+    ///
+    /// If it equals [`ActorId::zero()`], the 0.05% commission is disabled.
+    pub fee_to: ActorId,
 
     /// The pair of SFT [ActorId]s that are used for swaps.
     pub token: (ActorId, ActorId),
+    /// Standards of the tokens. See [`Initialize::token_standard`].
+    pub token_standard: (TokenStandard, TokenStandard),
+    /// A fee charged from the input amount of every swap.
+    pub fee_tier: FeeTier,
     /// The record of tokens reserve in the SFT pair (`token`).
     pub reserve: (u128, u128),
     /// https://docs.uniswap.org/contracts/v2/concepts/core-concepts/oracles
@@ -58,8 +255,365 @@ pub struct State {
     /// A product of `reserve`s. Used for the 0.05% commission calculation.
     pub k_last: U256,
     pub ft_state: FTState,
+    /// An actor whose initial liquidity the contract awaits. See
+    /// [`Initialize::seeder`].
+    pub seeder: Option<ActorId>,
+    /// The pool parameters at the last liquidity addition of each LP.
+    pub lp_snapshots: Vec<(ActorId, LpSnapshot)>,
+    /// Trading statistics for the whole lifetime of the contract.
+    pub stats: TradingStats,
+    /// Trading statistics for the last [`STATS_BUCKETS`] hours in ascending
+    /// order of [`StatsBucket::start`].
+    pub recent_stats: Vec<StatsBucket>,
+    pub tx_config: TxConfig,
 
     pub cached_actions: Vec<(ActorId, CachedAction)>,
+    /// Info about cached transactions from `cached_actions`.
+    pub cached_txs: Vec<(ActorId, CachedTx)>,
+    /// Tokens that the contract failed to refund, keyed by (recipient,
+    /// token).
+    ///
+    /// They aren't a part of the reserve & can be claimed with
+    /// [`InnerAction::Claim`].
+    pub owed: Vec<((ActorId, ActorId), u128)>,
+    /// A lock held by an action that's changing `reserve`.
+    pub lock: Option<Lock>,
+    /// Unrevealed swap commitments. An actor can have only one at a time.
+    pub commitments: Vec<(ActorId, Commitment)>,
+    /// The batch of [`Order`]s awaiting the settlement.
+    pub batch: Option<Batch>,
+    pub circuit_breaker: CircuitBreaker,
+    /// Up to 2 observations from which the TWAP for [`CircuitBreaker`] is
+    /// derived, in ascending order of [`PriceObservation::block_ts`]. A new
+    /// observation is taken on a reserve change at least
+    /// [`CircuitBreaker::twap_window`] after the last one.
+    pub price_observations: Vec<PriceObservation>,
+}
+
+impl State {
+    /// Returns the liquidity position of `actor`.
+    pub fn lp_position(&self, actor: ActorId) -> LpPosition {
+        let liquidity = self.ft_state.balance_of(actor);
+        let total_supply = self.ft_state.total_supply();
+
+        if liquidity.is_zero() || total_supply.is_zero() {
+            return LpPosition {
+                liquidity,
+                fee_earnings: self.lp_snapshot(actor).map(|_| (0, 0)),
+                ..Default::default()
+            };
+        }
+
+        let hidden::U256PairTuple(reserve) = self.reserve.into();
+        let underlying = (
+            liquidity * reserve.0 / total_supply,
+            liquidity * reserve.1 / total_supply,
+        );
+        let fee_earnings = self.lp_snapshot(actor).map(|snapshot| {
+            let hidden::U256PairTuple(snapshot_reserve) = snapshot.reserve.into();
+            let root_k = (reserve.0 * reserve.1).integer_sqrt();
+            let snapshot_root_k = (snapshot_reserve.0 * snapshot_reserve.1).integer_sqrt();
+
+            if root_k.is_zero() || snapshot.total_supply.is_zero() {
+                return (0, 0);
+            }
+
+            // The value of 1 liquidity token grows only by fees, so the
+            // principal is `underlying` scaled by the growth of
+            // `root_k / total_supply` since the snapshot.
+            let numerator = snapshot_root_k.full_mul(total_supply);
+            let denominator = snapshot.total_supply.full_mul(root_k);
+            let fee = |underlying: U256| {
+                let principal = U512::from(underlying) * numerator / denominator;
+
+                underlying
+                    .saturating_sub(principal.try_into().unwrap_or(U256::MAX))
+                    .low_u128()
+            };
+
+            (fee(underlying.0), fee(underlying.1))
+        });
+
+        LpPosition {
+            liquidity,
+            // Can't be more than `reserve`, so casting doesn't lose data.
+            underlying: (underlying.0.low_u128(), underlying.1.low_u128()),
+            fee_earnings,
+        }
+    }
+
+    /// Returns trading statistics of [`State::recent_stats`] buckets that
+    /// end after `since`.
+    pub fn stats_since(&self, since: u64) -> TradingStats {
+        let mut stats = TradingStats::default();
+
+        for bucket in &self.recent_stats {
+            if bucket.start.saturating_add(STATS_BUCKET_DURATION) > since {
+                stats.add(&bucket.stats);
+            }
+        }
+
+        stats
+    }
+
+    /// Returns an amount of `token` owed to `actor`.
+    pub fn owed(&self, actor: ActorId, token: ActorId) -> u128 {
+        self.owed
+            .iter()
+            .find_map(|(key, amount)| (*key == (actor, token)).then_some(*amount))
+            .unwrap_or_default()
+    }
+
+    fn lp_snapshot(&self, actor: ActorId) -> Option<LpSnapshot> {
+        self.lp_snapshots
+            .iter()
+            .find_map(|(lp, snapshot)| (*lp == actor).then_some(*snapshot))
+    }
+}
+
+/// Info about a cached transaction.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct CachedTx {
+    /// Timestamp (in ms) of the transaction's creation.
+    pub created_at: u64,
+    /// An actor whose tokens the transaction transfers to the contract.
+    pub depositor: ActorId,
+    /// Amounts of the A & B tokens that the transaction transfers to the
+    /// contract. They're refunded on [`InnerAction::Abandon`].
+    pub deposit: (u128, u128),
+    /// Whether the transaction has started transferring tokens out of the
+    /// contract, so it can only be finished. Such a transaction can be
+    /// resumed even after its expiry, & abandoning it resumes it too.
+    pub is_settling: bool,
+    /// An action that created the transaction.
+    pub action: Option<InnerAction>,
+    /// The last message that processed the transaction.
+    pub message: MessageId,
+    /// A transaction ID of the transaction's last transfer that has got a
+    /// reply. Gear-lib FT contracts execute a repeated transfer again, so
+    /// transfers up to this one aren't sent again when the transaction is
+    /// resumed.
+    pub transferred: Option<u64>,
+    /// A transaction ID of the transaction's transfer that has failed. It
+    /// fails again instead of being sent when the transaction is resumed.
+    pub failed: Option<u64>,
+    /// Gas reserved for [`InnerAction::Complete`]. Becomes [`None`] after
+    /// it's used.
+    pub reservation: Option<ReservationId>,
+}
+
+impl CachedTx {
+    pub fn is_expired(&self, expiry: u64, block_ts: u64) -> bool {
+        block_ts > self.created_at.saturating_add(expiry)
+    }
+}
+
+/// A lock of [`State::reserve`] & the contract's token balances.
+///
+/// Actions that change them are serialized: while one of them awaits replies
+/// from the token contracts, others fail with [`Error::Busy`]. The lock of an
+/// interrupted message passes to [`InnerAction::Complete`] that the signal
+/// handler sends for its transaction.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct Lock {
+    /// An actor that sent the message holding the lock.
+    pub actor: ActorId,
+    /// A message that holds the lock.
+    pub message: MessageId,
+    /// Timestamp (in ms) of the lock's acquisition.
+    pub locked_at: u64,
+}
+
+impl Lock {
+    /// Checks whether the lock has been held longer than `expiry`, e.g.
+    /// because its message ran out of gas without sending a signal.
+    pub fn is_stale(&self, expiry: u64, block_ts: u64) -> bool {
+        block_ts > self.locked_at.saturating_add(expiry)
+    }
+}
+
+/// A committed swap that's hidden until [`InnerAction::RevealSwap`].
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct Commitment {
+    /// [`SwapReveal::hash()`] of the committed swap.
+    pub hash: [u8; 32],
+    /// Escrowed amounts of the A & B tokens. The input amount of the swap is
+    /// taken from them, & the rest is refunded.
+    pub deposit: (u128, u128),
+    /// A block height of the commitment.
+    pub committed_at: u32,
+}
+
+impl Commitment {
+    /// Checks whether the commitment can't be revealed anymore (see
+    /// [`REVEAL_WINDOW`]).
+    pub fn is_timed_out(&self, block_height: u32) -> bool {
+        block_height > self.committed_at.saturating_add(REVEAL_WINDOW)
+    }
+}
+
+/// Parameters of a committed swap of an exact amount of input tokens.
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub struct SwapReveal {
+    pub swap_kind: SwapKind,
+    pub amount_in: u128,
+    /// A minimum amount of output tokens that must be received for the swap
+    /// not to revert.
+    pub amount_out_min: u128,
+    /// A recipient of output tokens.
+    pub to: ActorId,
+    /// A random value that prevents guessing the parameters from the hash.
+    pub salt: [u8; 32],
+}
+
+impl SwapReveal {
+    /// Returns the hash that should be committed with
+    /// [`InnerAction::CommitSwap`].
+    pub fn hash(&self) -> [u8; 32] {
+        Blake2b::<U32>::digest(self.encode()).into()
+    }
+}
+
+/// A swap order of an exact amount of input tokens settled in a [`Batch`].
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
+pub struct Order {
+    pub swap_kind: SwapKind,
+    pub amount_in: u128,
+    /// A minimum amount of output tokens that must be received for the order
+    /// to be filled. Otherwise, the order is refunded.
+    pub amount_out_min: u128,
+    /// A recipient of output tokens.
+    pub to: ActorId,
+}
+
+/// Swap orders collected during [`BATCH_EPOCH`] & settled at a single
+/// clearing price.
+#[derive(Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
+pub struct Batch {
+    pub id: u64,
+    /// A block height from which the batch can be settled.
+    pub ends_at: u32,
+    /// Orders & [`ActorId`]s of their traders in the submission order. A
+    /// trader can have only one order in a batch.
+    pub orders: Vec<(ActorId, Order)>,
+    /// Set when the settlement begins.
+    pub clearing: Option<Clearing>,
+}
+
+/// A result of a [`Batch`]'s clearing.
+///
+/// Opposite orders are netted against each other, & only the excess of one
+/// of the tokens is swapped through the reserve. Both sides of the batch
+/// trade at the price of this swap.
+#[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, TypeInfo, Hash)]
+pub struct Clearing {
+    /// Output amounts of [`Batch::orders`]. An order with 0 is refunded.
+    pub payouts: Vec<u128>,
+    /// A direction of the swap through the reserve.
+    pub swap_kind: SwapKind,
+    /// Input & output amounts of the swap through the reserve.
+    pub swap: (u128, u128),
+    /// A number of [`Batch::orders`] from the start that have been paid out or
+    /// refunded.
+    pub paid: u32,
+}
+
+/// The pool parameters at an LP's last liquidity addition.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct LpSnapshot {
+    /// The tokens reserve right after the addition.
+    pub reserve: (u128, u128),
+    /// The total supply of liquidity tokens right after the addition.
+    pub total_supply: U256,
+}
+
+/// Trading statistics of the A & B tokens.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct TradingStats {
+    /// Amounts of the A & B tokens swapped into the contract.
+    pub volume_in: (U256, U256),
+    /// Amounts of the A & B tokens swapped out of the contract.
+    pub volume_out: (U256, U256),
+    /// Swap fees charged from input amounts in the A & B tokens.
+    ///
+    /// Includes the 0.05% commission if it's enabled.
+    pub fees: (U256, U256),
+    /// A number of swaps.
+    pub swaps: u64,
+}
+
+impl TradingStats {
+    /// Records a swap of `in_amount` for `out_amount` with `fee` charged
+    /// from `in_amount`.
+    pub fn record(&mut self, kind: SwapKind, in_amount: u128, out_amount: u128, fee: u128) {
+        let (volume_in, volume_out, fees) = match kind {
+            SwapKind::AForB => (
+                &mut self.volume_in.0,
+                &mut self.volume_out.1,
+                &mut self.fees.0,
+            ),
+            SwapKind::BForA => (
+                &mut self.volume_in.1,
+                &mut self.volume_out.0,
+                &mut self.fees.1,
+            ),
+        };
+
+        *volume_in = volume_in.saturating_add(in_amount.into());
+        *volume_out = volume_out.saturating_add(out_amount.into());
+        *fees = fees.saturating_add(fee.into());
+        self.swaps = self.swaps.saturating_add(1);
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        let add = |a: &mut (U256, U256), b: (U256, U256)| {
+            a.0 = a.0.saturating_add(b.0);
+            a.1 = a.1.saturating_add(b.1);
+        };
+
+        add(&mut self.volume_in, other.volume_in);
+        add(&mut self.volume_out, other.volume_out);
+        add(&mut self.fees, other.fees);
+        self.swaps = self.swaps.saturating_add(other.swaps);
+    }
+}
+
+/// Trading statistics for a [`STATS_BUCKET_DURATION`] period.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct StatsBucket {
+    /// Timestamp (in ms) of the period start. Is a multiple of
+    /// [`STATS_BUCKET_DURATION`].
+    pub start: u64,
+    pub stats: TradingStats,
+}
+
+/// A liquidity position of an actor.
+#[derive(
+    Default, Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash,
+)]
+pub struct LpPosition {
+    /// An amount of liquidity tokens.
+    pub liquidity: Amount,
+    /// Amounts of the A & B tokens that `liquidity` currently represents.
+    pub underlying: (u128, u128),
+    /// A part of `underlying` earned from swap fees since the last liquidity
+    /// addition of the actor.
+    ///
+    /// It's [`None`] if the actor has never added liquidity (e.g. it only
+    /// received liquidity tokens by a transfer).
+    pub fee_earnings: Option<(u128, u128)>,
 }
 
 /// A part of [`Action`].
@@ -72,6 +626,8 @@ pub enum InnerAction {
     /// - `amount_a_desired` & `amount_b_desired` mustn't equal to 0.
     /// - On the first addition (first mint), a resulted amount of pool tokens
     /// must be more than [`MINIMUM_LIQUIDITY`].
+    /// - If the contract awaits the initial liquidity, [`msg::source()`] must
+    /// be the seeder (see [`Initialize::seeder`]).
     ///
     /// On success, replies with [`Event::AddedLiquidity`].
     AddLiquidity {
@@ -93,6 +649,36 @@ pub enum InnerAction {
         deadline: u64,
     },
 
+    /// Adds liquidity to the contract from `provider`'s fungible tokens and
+    /// mints liquidity tokens to it.
+    ///
+    /// Lets the Factory contract seed the initial liquidity right after the
+    /// contract creation.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the Factory contract.
+    /// - If the contract awaits the initial liquidity, `provider` must be the
+    /// seeder (see [`Initialize::seeder`]).
+    /// - The same as for [`InnerAction::AddLiquidity`].
+    ///
+    /// On success, replies with [`Event::AddedLiquidity`].
+    AddLiquidityFor {
+        /// An actor whose fungible tokens are added as liquidity.
+        ///
+        /// It must approve the contract to transfer them.
+        provider: ActorId,
+        /// See [`InnerAction::AddLiquidity`].
+        amount_a_desired: u128,
+        /// See [`InnerAction::AddLiquidity`].
+        amount_b_desired: u128,
+        /// See [`InnerAction::AddLiquidity`].
+        amount_a_min: u128,
+        /// See [`InnerAction::AddLiquidity`].
+        amount_b_min: u128,
+        /// Timestamp (in ms) after which this action will revert.
+        deadline: u64,
+    },
+
     /// Removes liquidity from the contract by burning [`msg::source()`]'s
     /// liquidity tokens and transferring an appropriate amount of fungible
     /// tokens from the contract to it.
@@ -123,6 +709,7 @@ pub enum InnerAction {
     /// # Requirements
     /// - `to` mustn't equal to the contract's SFT pair.
     /// - `amount_in` mustn't equal to 0.
+    /// - The swap must be within [`CircuitBreaker`] limits.
     ///
     /// On success, replies with [`Event::Swap`].
     SwapExactTokensForTokens {
@@ -143,6 +730,7 @@ pub enum InnerAction {
     /// # Requirements
     /// - `to` mustn't equal to the contract's SFT pair.
     /// - `amount_out` mustn't equal to 0.
+    /// - The swap must be within [`CircuitBreaker`] limits.
     ///
     /// On success, replies with [`Event::Swap`].
     SwapTokensForExactTokens {
@@ -157,9 +745,94 @@ pub enum InnerAction {
         deadline: u64,
     },
 
+    /// Commits a swap that will be executed by [`InnerAction::RevealSwap`] &
+    /// escrows tokens of [`msg::source()`] for it.
+    ///
+    /// Unlike other swaps, the swap parameters stay hidden until the reveal,
+    /// so nobody can place their actions around the swap. Escrowing both
+    /// tokens or more than the input amount hides the swap direction & amount
+    /// as well.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] mustn't have an unrevealed commitment.
+    /// - `deposit` mustn't equal to (0, 0).
+    ///
+    /// On success, replies with [`Event::SwapCommitted`].
+    CommitSwap {
+        /// [`SwapReveal::hash()`].
+        hash: [u8; 32],
+        /// Amounts of the A & B tokens to escrow.
+        deposit: (u128, u128),
+    },
+
+    /// Reveals & executes the swap committed by [`msg::source()`] against the
+    /// current reserve. The escrow that exceeds the input amount is refunded
+    /// to [`msg::source()`].
+    ///
+    /// # Requirements
+    /// - The reveal must be sent after the commitment's block & within
+    /// [`REVEAL_WINDOW`] blocks.
+    /// - The hash of given parameters must equal to the committed one.
+    /// - The escrowed amount of input tokens must be enough for the swap.
+    /// - `to` mustn't equal to the contract's SFT pair.
+    /// - The swap must be within [`CircuitBreaker`] limits.
+    ///
+    /// On success, replies with [`Event::Swap`].
+    RevealSwap(SwapReveal),
+
+    /// Refunds the escrow of an unrevealed commitment of a given
+    /// [`ActorId`] & removes the commitment.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the committer, or the commitment must be
+    /// timed out (see [`REVEAL_WINDOW`]).
+    ///
+    /// On success, replies with [`Event::SwapRefunded`].
+    RefundSwap(ActorId),
+
+    /// Submits a swap order of [`msg::source()`] to the current [`Batch`] &
+    /// escrows its input tokens.
+    ///
+    /// The first order of a batch starts it & schedules delayed
+    /// [`InnerAction::SettleBatch`] at [`Batch::ends_at`].
+    ///
+    /// # Requirements
+    /// - `to` mustn't equal to the contract's SFT pair.
+    /// - `amount_in` mustn't equal to 0.
+    /// - [`msg::source()`] mustn't have an order in the batch.
+    /// - The batch mustn't have [`MAX_BATCH_ORDERS`] orders or be settling.
+    ///
+    /// On success, replies with [`Event::OrderSubmitted`].
+    SubmitOrder(Order),
+
+    /// Settles the current [`Batch`] (see [`Clearing`]), transfers output
+    /// tokens of filled orders, & refunds other orders.
+    ///
+    /// Normally, it's sent by the contract itself at the end of the batch,
+    /// but anyone can send it after that (e.g. if the delayed message
+    /// failed).
+    ///
+    /// If the swap through the reserve exceeds [`CircuitBreaker`] limits, all
+    /// orders are refunded.
+    ///
+    /// One message settles at most [`MAX_SETTLED_ORDERS`] orders from
+    /// [`Clearing::paid`] & sends itself to continue with the rest. If this
+    /// fails, anyone can continue the settlement.
+    ///
+    /// # Requirements
+    /// - The batch must have ended (see [`Batch::ends_at`]).
+    /// - An interrupted settlement message must be resumed first.
+    ///
+    /// On success, replies with [`Event::BatchSettled`] or, if orders remain,
+    /// [`Event::BatchPartiallySettled`].
+    SettleBatch,
+
     /// Syncs the contract's tokens reserve with actual contract's balances by
     /// transferring excess tokens to some [`ActorId`].
     ///
+    /// Owed & escrowed tokens (see [`State::owed`], [`State::commitments`], &
+    /// [`State::batch`]) aren't considered excess.
+    ///
     /// On success, replies with [`Event::Skim`].
     Skim(
         /// A recipient of excess tokens.
@@ -169,6 +842,13 @@ pub enum InnerAction {
     /// Syncs the contract's tokens reserve with actual contract's balances by
     /// setting the reserve equal to the balances.
     ///
+    /// Owed & escrowed tokens (see [`State::owed`], [`State::commitments`], &
+    /// [`State::batch`]) are excluded from the balances.
+    ///
+    /// # Requirements
+    /// - The contract mustn't await the initial liquidity (see
+    /// [`Initialize::seeder`]).
+    ///
     /// On success, replies with [`Event::Sync`].
     Sync,
 
@@ -180,6 +860,176 @@ pub enum InnerAction {
     ///
     /// On success, replies with [`Event::Transfer`].
     Transfer { to: ActorId, amount: Amount },
+
+    /// Allows `spender` to transfer up to `amount` of [`msg::source()`]'s
+    /// liquidity tokens with [`InnerAction::TransferFrom`].
+    ///
+    /// Lets other programs (e.g. a farm) take liquidity tokens from users.
+    ///
+    /// On success, replies with [`Event::Approval`].
+    Approve { spender: ActorId, amount: Amount },
+
+    /// Transfers liquidity tokens from `from` on behalf of it.
+    ///
+    /// # Requirements
+    /// - `from` must approve [`msg::source()`] to transfer the same or a
+    /// greater amount of liquidity tokens than a given one.
+    /// - `from` must have the same or a greater amount of liquidity tokens than
+    /// a given one.
+    ///
+    /// On success, replies with [`Event::Transfer`].
+    TransferFrom {
+        from: ActorId,
+        to: ActorId,
+        amount: Amount,
+    },
+
+    /// Updates the cached [`ActorId`] of the fee receiver (`fee_to`).
+    ///
+    /// The Factory contract broadcasts this action to all its Pair contracts
+    /// when its `fee_to` is changed.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the Factory contract.
+    ///
+    /// On success, replies with [`Event::FeeToUpdated`].
+    UpdateFeeTo(ActorId),
+
+    /// Refreshes the cached [`ActorId`] of the fee receiver (`fee_to`) by
+    /// getting it from the Factory contract.
+    ///
+    /// A fallback for the case when [`InnerAction::UpdateFeeTo`] from the
+    /// Factory contract wasn't delivered.
+    ///
+    /// On success, replies with [`Event::FeeToUpdated`].
+    RefreshFeeTo,
+
+    /// Updates the config of cached transactions.
+    ///
+    /// The Factory contract broadcasts this action to all its Pair contracts
+    /// when its config is changed.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the Factory contract.
+    /// - [`TxConfig`] must be valid (see its fields).
+    ///
+    /// On success, replies with [`Event::TxConfigUpdated`].
+    UpdateTxConfig(TxConfig),
+
+    /// Updates the limits of swaps.
+    ///
+    /// The Factory contract broadcasts this action to all its Pair contracts
+    /// when its limits are changed.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the Factory contract.
+    /// - [`CircuitBreaker`] must be valid (see its fields).
+    ///
+    /// On success, replies with [`Event::CircuitBreakerUpdated`].
+    UpdateCircuitBreaker(CircuitBreaker),
+
+    /// Stops awaiting the initial liquidity from the seeder (see
+    /// [`Initialize::seeder`]), so anyone can add it.
+    ///
+    /// The Factory contract sends this action when it fails to add the
+    /// initial liquidity on behalf of the seeder.
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the Factory contract.
+    ///
+    /// On success, replies with [`Event::SeederCleared`].
+    ClearSeeder,
+
+    /// Abandons a cached transaction of a given [`ActorId`] & refunds tokens
+    /// that it transferred to the contract (see [`CachedTx::deposit`]).
+    ///
+    /// The actor can abandon its own transaction right away, & anyone else can
+    /// abandon an expired one. Refunds always go to the depositor. A transaction that has started transferring tokens out of
+    /// the contract (e.g. an interrupted [`InnerAction::RemoveLiquidity`]
+    /// after burning liquidity tokens) can't be refunded, so it's resumed
+    /// with the action that created it instead.
+    ///
+    /// # Requirements
+    /// - If [`msg::source()`] isn't the actor, the transaction must be expired
+    /// (see [`TxConfig::expiry`]).
+    ///
+    /// On success, replies with [`Event::Abandoned`] or an [`Event`] of the
+    /// resumed action.
+    Abandon(ActorId),
+
+    /// Completes an interrupted transaction of a given [`ActorId`].
+    ///
+    /// When a message processing a transaction is interrupted (e.g. runs out
+    /// of gas or waits for a reply for too long), the contract's signal
+    /// handler sends this action to the contract using gas reserved on the
+    /// transaction's creation (see [`TX_COMPLETION_GAS`]). A transaction that
+    /// has started transferring tokens out of the contract is resumed with
+    /// the action that created it, & other transactions are rolled back as
+    /// with [`InnerAction::Abandon`].
+    ///
+    /// # Requirements
+    /// - [`msg::source()`] must be the contract itself.
+    ///
+    /// On success, replies with an [`Event`] of the resumed action or
+    /// [`Event::Abandoned`].
+    Complete(ActorId),
+
+    /// Transfers tokens owed to [`msg::source()`] (see [`State::owed`]).
+    ///
+    /// # Requirements
+    /// - The contract must owe tokens to [`msg::source()`].
+    ///
+    /// On success, replies with [`Event::Claimed`].
+    Claim(
+        /// One of the contract's tokens.
+        ActorId,
+    ),
+
+    /// Gets the contract's immutable parameters.
+    ///
+    /// Lets other programs (e.g. the Factory contract) verify the contract
+    /// since they can't read its state.
+    ///
+    /// On success, replies with [`Event::Info`].
+    GetInfo,
+
+    /// Gets the contract's tokens reserve.
+    ///
+    /// This and the following `Get*` actions let other programs compose with
+    /// the contract since they can't read its state. They don't mutate the
+    /// state & ignore [`ActionKind`](tx_manager::ActionKind).
+    ///
+    /// On success, replies with [`Event::Reserves`].
+    GetReserves,
+
+    /// Calculates an amount of output tokens that will be received for a
+    /// given amount of input tokens with the current reserve.
+    ///
+    /// On success, replies with [`Event::AmountOut`].
+    GetAmountOut {
+        swap_kind: SwapKind,
+        amount_in: u128,
+    },
+
+    /// Calculates an amount of input tokens that must be sent to receive a
+    /// given amount of output tokens with the current reserve.
+    ///
+    /// On success, replies with [`Event::AmountIn`].
+    GetAmountIn {
+        swap_kind: SwapKind,
+        amount_out: u128,
+    },
+
+    /// Gets an amount of liquidity tokens of a given [`ActorId`].
+    ///
+    /// On success, replies with [`Event::LpBalance`].
+    GetLpBalance(ActorId),
+
+    /// Gets the cumulative prices of the tokens accumulated up to the current
+    /// block.
+    ///
+    /// On success, replies with [`Event::CumulativePrices`].
+    GetCumulativePrices,
 }
 
 /// Sends the contract info about what it should do.
@@ -233,8 +1083,105 @@ pub enum Event {
         /// A recipient of skimmed tokens.
         to: ActorId,
     },
-    /// Should be returned from [`InnerAction::Transfer`].
+    /// Should be returned from
+    /// [`InnerAction::Transfer`]/[`InnerAction::TransferFrom`].
     Transfer(FTTransfer),
+    /// Should be returned from [`InnerAction::Approve`].
+    Approval(FTApproval),
+    /// Should be returned from
+    /// [`InnerAction::UpdateFeeTo`]/[`InnerAction::RefreshFeeTo`].
+    FeeToUpdated(
+        /// New `fee_to`.
+        ActorId,
+    ),
+    /// Should be returned from [`InnerAction::UpdateTxConfig`].
+    TxConfigUpdated(TxConfig),
+    /// Should be returned from [`InnerAction::UpdateCircuitBreaker`].
+    CircuitBreakerUpdated(CircuitBreaker),
+    /// Should be returned from [`InnerAction::ClearSeeder`].
+    SeederCleared,
+    /// Should be returned from [`InnerAction::Abandon`].
+    Abandoned {
+        /// An actor whose transaction was abandoned.
+        actor: ActorId,
+        /// A recipient of refunded tokens.
+        depositor: ActorId,
+        /// Refunded amounts of the A & B tokens.
+        ///
+        /// Includes amounts that failed to be transferred & were recorded as
+        /// owed (see [`State::owed`]).
+        refund: (u128, u128),
+    },
+    /// Should be returned from [`InnerAction::CommitSwap`].
+    SwapCommitted {
+        actor: ActorId,
+        hash: [u8; 32],
+        deposit: (u128, u128),
+    },
+    /// Should be returned from [`InnerAction::RefundSwap`].
+    SwapRefunded {
+        actor: ActorId,
+        refund: (u128, u128),
+    },
+    /// Should be returned from [`InnerAction::SubmitOrder`].
+    OrderSubmitted {
+        actor: ActorId,
+        batch: u64,
+        order: Order,
+    },
+    /// Should be returned from [`InnerAction::SettleBatch`].
+    BatchSettled {
+        id: u64,
+        /// A number of filled orders.
+        filled: u32,
+        /// See [`Clearing::swap_kind`].
+        swap_kind: SwapKind,
+        /// See [`Clearing::swap`].
+        swap: (u128, u128),
+    },
+    /// Should be returned from [`InnerAction::SettleBatch`] that left some
+    /// orders of the batch unsettled.
+    BatchPartiallySettled {
+        id: u64,
+        /// See [`Clearing::paid`].
+        paid: u32,
+    },
+    /// Should be returned from [`InnerAction::Claim`].
+    Claimed {
+        actor: ActorId,
+        token: ActorId,
+        amount: u128,
+    },
+    /// Should be returned from [`InnerAction::GetInfo`].
+    Info {
+        factory: ActorId,
+        token: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    },
+    /// Should be returned from [`InnerAction::GetReserves`].
+    Reserves {
+        /// The current amount of the A token in the contract's reserve.
+        reserve_a: u128,
+        /// The current amount of the B token in the contract's reserve.
+        reserve_b: u128,
+        /// Timestamp (in ms) of the last reserve update.
+        last_block_ts: u64,
+    },
+    /// Should be returned from [`InnerAction::GetAmountOut`].
+    AmountOut(u128),
+    /// Should be returned from [`InnerAction::GetAmountIn`].
+    AmountIn(u128),
+    /// Should be returned from [`InnerAction::GetLpBalance`].
+    LpBalance { actor: ActorId, balance: Amount },
+    /// Should be returned from [`InnerAction::GetCumulativePrices`].
+    CumulativePrices {
+        /// The cumulative price of the A token in the B token.
+        price_a: U256,
+        /// The cumulative price of the B token in the A token.
+        price_b: U256,
+        /// Timestamp (in ms) up to which the prices were accumulated.
+        block_ts: u64,
+    },
 }
 
 impl From<FTTransfer> for Event {
@@ -243,6 +1190,12 @@ impl From<FTTransfer> for Event {
     }
 }
 
+impl From<FTApproval> for Event {
+    fn from(value: FTApproval) -> Self {
+        Self::Approval(value)
+    }
+}
+
 #[derive(Debug, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, TypeInfo, Hash)]
 pub enum SwapKind {
     AForB,
@@ -273,6 +1226,9 @@ pub enum Error {
     /// Most often, the reason is that a user didn't give an approval to the
     /// contract or didn't have enough tokens to transfer.
     TransferFailed,
+    /// One of the contract's FT contracts replied with an unexpected
+    /// payload, e.g. because its [`TokenStandard`] was misconfigured.
+    UnexpectedTokenReply,
     /// An overflow occurred during calculations.
     Overflow,
     /// A specified deadline for an action was exceeded.
@@ -283,6 +1239,60 @@ pub enum Error {
     /// The contract failed to get fee receiver (`fee_to`) [`ActorId`] from the
     /// linked Factory contract.
     FeeToGettingFailed,
+    /// [`msg::source()`] doesn't have the right to perform an action.
+    AccessRestricted,
+    /// A given [`FeeTier`] isn't less than [`FEE_DENOMINATOR`].
+    InvalidFeeTier,
+    /// The contract awaits the initial liquidity from another actor (see
+    /// [`Initialize::seeder`]).
+    NotSeeded,
+    /// A given [`TxConfig`] is invalid.
+    InvalidTxConfig,
+    /// A given [`CircuitBreaker`] is invalid.
+    InvalidCircuitBreaker,
+    /// A swap's input amount exceeds [`CircuitBreaker::max_trade_size`].
+    TradeTooLarge,
+    /// A swap would move the price further than
+    /// [`CircuitBreaker::max_deviation`] from the TWAP.
+    PriceDeviationExceeded,
+    /// A given [`ActorId`] doesn't have a cached transaction.
+    TxNotFound,
+    /// A cached transaction is expired & hasn't started transferring tokens
+    /// out of the contract, so it can only be abandoned.
+    TxExpired,
+    /// A cached transaction isn't expired yet, so only its actor can abandon
+    /// it.
+    TxNotExpired,
+    /// The contract has [`TxConfig::max_cached_txs`] cached transactions.
+    TxCacheFull,
+    /// The contract doesn't owe a given token to [`msg::source()`].
+    NothingToClaim,
+    /// [`msg::source()`] already has an unrevealed commitment.
+    CommitmentExists,
+    CommitmentNotFound,
+    /// Given parameters don't match the commitment, or its escrow isn't
+    /// enough for the swap.
+    InvalidReveal,
+    /// The commitment can't be revealed in its own block.
+    RevealTooEarly,
+    /// The commitment can't be revealed after [`REVEAL_WINDOW`].
+    RevealWindowClosed,
+    /// The commitment can be refunded only by its committer until
+    /// [`REVEAL_WINDOW`] ends.
+    RevealWindowOpen,
+    /// [`msg::source()`] already has an order in the current batch.
+    OrderExists,
+    /// The current batch has [`MAX_BATCH_ORDERS`] orders.
+    BatchFull,
+    BatchNotFound,
+    /// The current batch hasn't reached [`Batch::ends_at`] yet.
+    BatchNotEnded,
+    /// The current batch is being settled, so it doesn't accept orders, or
+    /// its interrupted settlement message must be resumed first.
+    BatchSettling,
+    /// Another action that changes the reserve is in progress. The action
+    /// can be sent again after it finishes. See [`Lock`].
+    Busy,
     TxCacheError(TransactionManagerError),
 }
 
@@ -308,7 +1318,37 @@ impl From<FTError> for Error {
 pub enum CachedAction {
     Swap(u128),
     AddLiquidity((u128, u128)),
-    RemovedLiquidity { amount: Amount, is_burned: bool },
+    RemovedLiquidity {
+        amount: Amount,
+        is_burned: bool,
+    },
+    Claim {
+        token: ActorId,
+        /// An amount taken from [`State::owed`] for the transfer. Equals 0
+        /// until the amount is taken.
+        amount: u128,
+    },
+    CommitSwap((u128, u128)),
+    RevealSwap {
+        hash: [u8; 32],
+        /// An output amount calculated when the reveal was received.
+        out_amount: u128,
+        /// An escrow taken from [`State::commitments`]. Equals (0, 0) until
+        /// the escrow is taken.
+        deposit: (u128, u128),
+    },
+    RefundSwap {
+        actor: ActorId,
+        /// An escrow taken from [`State::commitments`]. Equals (0, 0) until
+        /// the escrow is taken.
+        deposit: (u128, u128),
+    },
+    SubmitOrder(Order),
+    SettleBatch {
+        id: u64,
+        /// [`Clearing::paid`] at the transaction's start.
+        from: u32,
+    },
     Other,
 }
 
@@ -340,34 +1380,41 @@ pub mod hidden {
         }
     }
 
-    pub fn calculate_out_amount(in_amount: u128, reserve: (u128, u128)) -> Result<u128, Error> {
+    pub fn calculate_out_amount(
+        in_amount: u128,
+        reserve: (u128, u128),
+        fee_tier: FeeTier,
+    ) -> Result<u128, Error> {
         perform_precalculate_check(in_amount, reserve)?;
 
-        let amount_with_fee: U256 = U256::from(in_amount) * 997;
+        let amount_with_fee: U256 = U256::from(in_amount) * (FEE_DENOMINATOR - fee_tier);
 
         amount_with_fee
             .checked_mul(reserve.1.into())
             .map_or(Err(Error::Overflow), |numerator| {
                 // Shouldn't overflow.
-                let denominator = U256::from(reserve.0) * 1000 + amount_with_fee;
+                let denominator = U256::from(reserve.0) * FEE_DENOMINATOR + amount_with_fee;
 
                 // Shouldn't be more than u128::MAX, so casting doesn't lose data.
                 Ok((numerator / denominator).low_u128())
             })
     }
 
-    pub fn calculate_in_amount(out_amount: u128, reserve: (u128, u128)) -> Result<u128, Error> {
+    pub fn calculate_in_amount(
+        out_amount: u128,
+        reserve: (u128, u128),
+        fee_tier: FeeTier,
+    ) -> Result<u128, Error> {
         perform_precalculate_check(out_amount, reserve)?;
 
-        // The `u64` suffix is needed for a faster conversion.
         let numerator =
-            (U256::from(reserve.0) * U256::from(out_amount)).checked_mul(1000u64.into());
+            (U256::from(reserve.0) * U256::from(out_amount)).checked_mul(FEE_DENOMINATOR.into());
 
         if let (Some(numerator), Some(amount)) = (numerator, reserve.1.checked_sub(out_amount)) {
             if amount == 0 {
                 Err(Error::Overflow)
             } else {
-                let denominator = U256::from(amount) * 997;
+                let denominator = U256::from(amount) * (FEE_DENOMINATOR - fee_tier);
 
                 // Adding 1 here to avoid abuse of the case when a calculated input
                 // amount will equal 0.
@@ -380,6 +1427,193 @@ pub mod hidden {
         }
     }
 
+    /// Calculates the price impact (in basis points, rounded up) of swapping
+    /// `in_amount`.
+    ///
+    /// The impact is a relative difference between the current price & the
+    /// execution price of the swap without the fee.
+    pub fn price_impact(
+        in_amount: u128,
+        reserve: (u128, u128),
+        fee_tier: FeeTier,
+    ) -> Result<u16, Error> {
+        perform_precalculate_check(in_amount, reserve)?;
+
+        // Can't overflow.
+        let amount_with_fee = U256::from(in_amount) * (FEE_DENOMINATOR - fee_tier);
+        let numerator = amount_with_fee * BPS_DENOMINATOR;
+        let denominator = U256::from(reserve.0) * FEE_DENOMINATOR + amount_with_fee;
+
+        // Shouldn't be more than `BPS_DENOMINATOR`, so casting doesn't lose
+        // data.
+        Ok(((numerator + denominator - 1) / denominator).low_u32() as u16)
+    }
+
+    /// Returns the deviation of `price` from `twap` in basis points,
+    /// saturated to [`u16::MAX`].
+    pub fn price_deviation(price: U256, twap: U256) -> u16 {
+        if twap.is_zero() {
+            return if price.is_zero() { 0 } else { u16::MAX };
+        }
+
+        let difference = if price > twap {
+            price - twap
+        } else {
+            twap - price
+        };
+        let deviation = U512::from(difference) * BPS_DENOMINATOR / U512::from(twap);
+
+        if deviation > u16::MAX.into() {
+            u16::MAX
+        } else {
+            deviation.low_u32() as u16
+        }
+    }
+
+    /// Calculates the maximum input amount whose [`price_impact`] doesn't
+    /// exceed `max_impact`.
+    ///
+    /// Returns [`u128::MAX`] if `max_impact` isn't less than
+    /// [`BPS_DENOMINATOR`].
+    pub fn max_in_amount(
+        max_impact: u16,
+        reserve: (u128, u128),
+        fee_tier: FeeTier,
+    ) -> Result<u128, Error> {
+        if reserve.0 == 0 || reserve.1 == 0 {
+            return Err(Error::InsufficientLiquidity);
+        }
+
+        let Some(impact_remainder) = BPS_DENOMINATOR.checked_sub(max_impact).filter(|r| *r != 0)
+        else {
+            return Ok(u128::MAX);
+        };
+
+        let numerator = U256::from(reserve.0) * FEE_DENOMINATOR * max_impact;
+        let denominator = U256::from(impact_remainder) * (FEE_DENOMINATOR - fee_tier);
+
+        Ok((numerator / denominator).try_into().unwrap_or(u128::MAX))
+    }
+
+    /// Calculates the minimum acceptable output amount for `out_amount` with
+    /// a given slippage tolerance (in basis points).
+    pub fn amount_out_min(out_amount: u128, slippage: u16) -> u128 {
+        let remainder = BPS_DENOMINATOR.saturating_sub(slippage);
+
+        // Shouldn't be more than `out_amount`, so casting doesn't lose data.
+        (U256::from(out_amount) * remainder / BPS_DENOMINATOR).low_u128()
+    }
+
+    /// Calculates the maximum acceptable input amount (rounded up) for
+    /// `in_amount` with a given slippage tolerance (in basis points).
+    pub fn amount_in_max(in_amount: u128, slippage: u16) -> Result<u128, Error> {
+        // Can't overflow.
+        let numerator = U256::from(in_amount) * (u32::from(BPS_DENOMINATOR) + u32::from(slippage));
+
+        ((numerator + BPS_DENOMINATOR - 1) / BPS_DENOMINATOR)
+            .try_into()
+            .map_or(Err(Error::Overflow), Ok)
+    }
+
+    /// Clears a batch of `orders` against `reserve`.
+    ///
+    /// Orders that can't get their minimum output amounts are refunded, &
+    /// the batch is cleared again without them.
+    pub fn clear_batch(orders: &[Order], reserve: (u128, u128), fee_tier: FeeTier) -> Clearing {
+        let mut is_filled = vec![true; orders.len()];
+
+        loop {
+            let (mut total_a, mut total_b) = (0u128, 0u128);
+
+            for (order, _) in orders.iter().zip(&is_filled).filter(|(_, filled)| **filled) {
+                // Can't overflow since amounts are escrowed.
+                match order.swap_kind {
+                    SwapKind::AForB => total_a += order.amount_in,
+                    SwapKind::BForA => total_b += order.amount_in,
+                }
+            }
+
+            let mut swap_kind = SwapKind::AForB;
+            let (mut excess_total, mut other_total) = (total_a, total_b);
+            let mut swap = net_swap((total_a, total_b), reserve, fee_tier);
+
+            if swap.0 == 0 {
+                let reverse_swap = net_swap((total_b, total_a), (reserve.1, reserve.0), fee_tier);
+
+                if reverse_swap.0 != 0 {
+                    swap_kind = SwapKind::BForA;
+                    (excess_total, other_total) = (total_b, total_a);
+                    swap = reverse_swap;
+                }
+            }
+
+            let payouts: Vec<_> = orders
+                .iter()
+                .zip(&is_filled)
+                .map(|(order, filled)| {
+                    if !filled {
+                        return 0;
+                    }
+
+                    let (pool, total) = if order.swap_kind == swap_kind {
+                        (other_total + swap.1, excess_total)
+                    } else {
+                        (excess_total - swap.0, other_total)
+                    };
+
+                    // Shouldn't be more than `pool`, so casting doesn't lose
+                    // data.
+                    (U256::from(order.amount_in) * U256::from(pool) / total).low_u128()
+                })
+                .collect();
+            let mut is_changed = false;
+
+            for ((order, payout), filled) in orders.iter().zip(&payouts).zip(&mut is_filled) {
+                if *filled && (*payout == 0 || *payout < order.amount_out_min) {
+                    *filled = false;
+                    is_changed = true;
+                }
+            }
+
+            if !is_changed {
+                return Clearing {
+                    payouts,
+                    swap_kind,
+                    swap,
+                    paid: 0,
+                };
+            }
+        }
+    }
+
+    /// Returns input & output amounts of a swap of the excess of
+    /// `total.0` over `total.1` through `reserve`.
+    ///
+    /// The input amount `x` is the maximum one for which
+    /// `x * (total.1 + out(x)) <= total.0 * out(x)`, so the swap price is the
+    /// same as the price at which the rest of `total.0` is netted against
+    /// `total.1`.
+    fn net_swap(total: (u128, u128), reserve: (u128, u128), fee_tier: FeeTier) -> (u128, u128) {
+        let out_amount =
+            |in_amount| calculate_out_amount(in_amount, reserve, fee_tier).unwrap_or_default();
+        let (mut low, mut high) = (0, total.0);
+
+        while low < high {
+            let mid = high - (high - low) / 2;
+            let out = out_amount(mid);
+
+            if U256::from(mid) * (U256::from(total.1) + out)
+                <= U256::from(total.0) * U256::from(out)
+            {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        (low, out_amount(low))
+    }
+
     pub const fn perform_precalculate_check(
         amount: u128,
         reserve: (u128, u128),
@@ -403,7 +1637,11 @@ pub mod hidden {
 
     #[cfg(test)]
     mod tests {
-        use super::{calculate_in_amount, calculate_out_amount, quote_unchecked, Error};
+        use super::{
+            amount_in_max, amount_out_min, calculate_in_amount, calculate_out_amount, clear_batch,
+            max_in_amount, price_deviation, price_impact, quote_unchecked, ActorId, Clearing,
+            Error, Order, SwapKind, DEFAULT_FEE_TIER, U256,
+        };
 
         #[test]
         fn quote() {
@@ -414,59 +1652,244 @@ pub mod hidden {
         #[test]
         fn calculate_oa() {
             assert_eq!(
-                calculate_out_amount(0, (0, 1)),
+                calculate_out_amount(0, (0, 1), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientLiquidity)
             );
             assert_eq!(
-                calculate_out_amount(0, (1, 0)),
+                calculate_out_amount(0, (1, 0), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientLiquidity)
             );
             assert_eq!(
-                calculate_out_amount(0, (1, 1)),
+                calculate_out_amount(0, (1, 1), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientAmount)
             );
 
             assert_eq!(
-                calculate_out_amount(u128::MAX, (1, u128::MAX)),
+                calculate_out_amount(u128::MAX, (1, u128::MAX), DEFAULT_FEE_TIER),
                 Err(Error::Overflow)
             );
 
             // (10000 * 997) * 10000 // (10000 * 1000 + (10000 * 997))
-            assert_eq!(calculate_out_amount(10000, (10000, 10000)), Ok(4992));
+            assert_eq!(
+                calculate_out_amount(10000, (10000, 10000), DEFAULT_FEE_TIER),
+                Ok(4992)
+            );
             // (1234 * 997) * 54321 // (12345 * 1000 + (1234 * 997))
-            assert_eq!(calculate_out_amount(1234, (12345, 54321)), Ok(4922));
+            assert_eq!(
+                calculate_out_amount(1234, (12345, 54321), DEFAULT_FEE_TIER),
+                Ok(4922)
+            );
+            // (10000 * 9900) * 10000 // (10000 * 10000 + (10000 * 9900))
+            assert_eq!(calculate_out_amount(10000, (10000, 10000), 100), Ok(4974));
         }
 
         #[test]
         fn calculate_ia() {
             assert_eq!(
-                calculate_in_amount(0, (0, 1)),
+                calculate_in_amount(0, (0, 1), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientLiquidity)
             );
             assert_eq!(
-                calculate_in_amount(0, (1, 0)),
+                calculate_in_amount(0, (1, 0), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientLiquidity)
             );
             assert_eq!(
-                calculate_in_amount(0, (1, 1)),
+                calculate_in_amount(0, (1, 1), DEFAULT_FEE_TIER),
                 Err(Error::InsufficientAmount)
             );
 
             assert_eq!(
-                calculate_in_amount(u128::MAX, (u128::MAX, 1)),
+                calculate_in_amount(u128::MAX, (u128::MAX, 1), DEFAULT_FEE_TIER),
                 Err(Error::Overflow)
             );
             // reserve.1 - out_amount == 0
-            assert_eq!(calculate_in_amount(12345, (1, 12345)), Err(Error::Overflow));
             assert_eq!(
-                calculate_in_amount(u128::MAX / 100 - 1, (u128::MAX / 100, u128::MAX / 100)),
+                calculate_in_amount(12345, (1, 12345), DEFAULT_FEE_TIER),
+                Err(Error::Overflow)
+            );
+            assert_eq!(
+                calculate_in_amount(
+                    u128::MAX / 100 - 1,
+                    (u128::MAX / 100, u128::MAX / 100),
+                    DEFAULT_FEE_TIER
+                ),
                 Err(Error::Overflow)
             );
 
             // 5000 * 10000 * 1000 // ((10000 - 5000) * 997) + 1
-            assert_eq!(calculate_in_amount(5000, (10000, 10000)), Ok(10031));
+            assert_eq!(
+                calculate_in_amount(5000, (10000, 10000), DEFAULT_FEE_TIER),
+                Ok(10031)
+            );
             // 1234 * 12345 * 1000 // ((54321 - 1234) * 997) + 1
-            assert_eq!(calculate_in_amount(1234, (12345, 54321)), Ok(288));
+            assert_eq!(
+                calculate_in_amount(1234, (12345, 54321), DEFAULT_FEE_TIER),
+                Ok(288)
+            );
+            // 5000 * 10000 * 10000 // ((10000 - 5000) * 9900) + 1
+            assert_eq!(calculate_in_amount(5000, (10000, 10000), 100), Ok(10102));
+        }
+
+        #[test]
+        fn price_impacts() {
+            assert_eq!(
+                price_impact(1, (0, 1), DEFAULT_FEE_TIER),
+                Err(Error::InsufficientLiquidity)
+            );
+            assert_eq!(
+                price_impact(0, (1, 1), DEFAULT_FEE_TIER),
+                Err(Error::InsufficientAmount)
+            );
+
+            // ⌈10000 * 10000 * 9970 / (10000 * 10000 + 10000 * 9970)⌉
+            assert_eq!(
+                price_impact(10000, (10000, 10000), DEFAULT_FEE_TIER),
+                Ok(4993)
+            );
+            // ⌈100 * 10000 * 10000 / (1000000 * 10000 + 100 * 10000)⌉
+            assert_eq!(price_impact(100, (1000000, 1), 0), Ok(1));
+            assert_eq!(price_impact(u128::MAX, (1, 1), 0), Ok(10000));
+
+            assert_eq!(
+                max_in_amount(100, (0, 1), DEFAULT_FEE_TIER),
+                Err(Error::InsufficientLiquidity)
+            );
+            assert_eq!(max_in_amount(0, (10000, 10000), DEFAULT_FEE_TIER), Ok(0));
+            assert_eq!(
+                max_in_amount(10000, (1, 1), DEFAULT_FEE_TIER),
+                Ok(u128::MAX)
+            );
+            assert_eq!(max_in_amount(9999, (u128::MAX, 1), 0), Ok(u128::MAX));
+
+            // 1000000 * 10000 * 100 // (9900 * 9970)
+            let max_amount = max_in_amount(100, (1000000, 1000000), DEFAULT_FEE_TIER).unwrap();
+
+            assert_eq!(max_amount, 10131);
+            assert_eq!(
+                price_impact(max_amount, (1000000, 1000000), DEFAULT_FEE_TIER),
+                Ok(100)
+            );
+            assert_eq!(
+                price_impact(max_amount + 1, (1000000, 1000000), DEFAULT_FEE_TIER),
+                Ok(101)
+            );
+        }
+
+        #[test]
+        fn slippage() {
+            assert_eq!(amount_out_min(10000, 50), 9950);
+            assert_eq!(amount_out_min(999, 50), 994);
+            assert_eq!(amount_out_min(10000, u16::MAX), 0);
+
+            assert_eq!(amount_in_max(10000, 50), Ok(10050));
+            assert_eq!(amount_in_max(999, 50), Ok(1004));
+            assert_eq!(amount_in_max(u128::MAX, 0), Ok(u128::MAX));
+            assert_eq!(amount_in_max(u128::MAX, 1), Err(Error::Overflow));
+        }
+
+        #[test]
+        fn price_deviations() {
+            let price = U256::from(10000) << 128;
+
+            assert_eq!(price_deviation(price, price), 0);
+            assert_eq!(price_deviation(price * 11 / 10, price), 1000);
+            assert_eq!(price_deviation(price * 9 / 10, price), 1000);
+            assert_eq!(price_deviation(price * 3, price), 20000);
+            assert_eq!(price_deviation(price * 10, price / 10), u16::MAX);
+            assert_eq!(price_deviation(price, U256::zero()), u16::MAX);
+        }
+
+        #[test]
+        fn batch_clearing() {
+            const RESERVE: (u128, u128) = (100000, 100000);
+
+            let order = |swap_kind, amount_in, amount_out_min| Order {
+                swap_kind,
+                amount_in,
+                amount_out_min,
+                to: ActorId::zero(),
+            };
+
+            // One-sided batches are swapped through the reserve entirely.
+            assert_eq!(
+                clear_batch(
+                    &[
+                        order(SwapKind::AForB, 1000, 0),
+                        order(SwapKind::AForB, 3000, 0)
+                    ],
+                    RESERVE,
+                    DEFAULT_FEE_TIER
+                ),
+                Clearing {
+                    payouts: vec![958, 2876],
+                    swap_kind: SwapKind::AForB,
+                    swap: (4000, 3835),
+                    paid: 0,
+                }
+            );
+
+            // Balanced batches don't touch the reserve.
+            assert_eq!(
+                clear_batch(
+                    &[
+                        order(SwapKind::AForB, 1000, 0),
+                        order(SwapKind::BForA, 1000, 0)
+                    ],
+                    RESERVE,
+                    DEFAULT_FEE_TIER
+                ),
+                Clearing {
+                    payouts: vec![1000, 1000],
+                    swap_kind: SwapKind::AForB,
+                    swap: (0, 0),
+                    paid: 0,
+                }
+            );
+
+            // Only the excess is swapped through the reserve.
+            assert_eq!(
+                clear_batch(
+                    &[
+                        order(SwapKind::AForB, 1000, 0),
+                        order(SwapKind::BForA, 10000, 0)
+                    ],
+                    RESERVE,
+                    DEFAULT_FEE_TIER
+                ),
+                Clearing {
+                    payouts: vec![1093, 9156],
+                    swap_kind: SwapKind::BForA,
+                    swap: (8907, 8156),
+                    paid: 0,
+                }
+            );
+
+            // Orders with unreachable minimums are refunded.
+            assert_eq!(
+                clear_batch(
+                    &[
+                        order(SwapKind::AForB, 1000, 0),
+                        order(SwapKind::BForA, 1000, 1001)
+                    ],
+                    RESERVE,
+                    DEFAULT_FEE_TIER
+                ),
+                Clearing {
+                    payouts: vec![987, 0],
+                    swap_kind: SwapKind::AForB,
+                    swap: (1000, 987),
+                    paid: 0,
+                }
+            );
+            assert_eq!(
+                clear_batch(&[order(SwapKind::AForB, 1000, 0)], (0, 0), DEFAULT_FEE_TIER),
+                Clearing {
+                    payouts: vec![0],
+                    swap_kind: SwapKind::AForB,
+                    swap: (0, 0),
+                    paid: 0,
+                }
+            );
         }
     }
 }
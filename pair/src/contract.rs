@@ -1,7 +1,12 @@
-use dex_factory_io::{Action as FactoryAction, Error as FactoryError, Event as FactoryEvent};
+use core::{future::Future, pin::Pin};
+use dex_factory_io::{
+    Action as FactoryAction, Error as FactoryError, Event as FactoryEvent,
+    InnerAction as FactoryInnerAction,
+};
 use dex_pair_io::{
     hidden::{
-        calculate_in_amount, calculate_out_amount, quote, quote_reserve_unchecked, U256PairTuple,
+        calculate_in_amount, calculate_out_amount, clear_batch, price_deviation, quote,
+        quote_reserve_unchecked, U256PairTuple,
     },
     *,
 };
@@ -9,9 +14,10 @@ use gear_lib::{
     tokens::fungible::FTState,
     tx_manager::{ActionKind, Stepper, TransactionManager},
 };
-use gstd::{errors::Result, exec, msg, prelude::*, ActorId};
+use gstd::{exec, msg, prelude::*, ActorId, HashMap, MessageId, ReservationId};
 use primitive_types::U256;
 
+mod adapter;
 mod utils;
 
 fn state_mut() -> &'static mut (Contract, TransactionManager<CachedAction>) {
@@ -23,24 +29,270 @@ static mut STATE: Option<(Contract, TransactionManager<CachedAction>)> = None;
 #[derive(Default)]
 struct Contract {
     factory: ActorId,
+    fee_to: ActorId,
 
     token: (ActorId, ActorId),
+    token_standard: (TokenStandard, TokenStandard),
+    fee_tier: FeeTier,
     reserve: (u128, u128),
     cumulative_price: (U256, U256),
     last_block_ts: u64,
     k_last: U256,
     ft_state: FTState,
+    seeder: Option<ActorId>,
+    lp_snapshots: HashMap<ActorId, LpSnapshot>,
+    stats: TradingStats,
+    recent_stats: Vec<StatsBucket>,
+    tx_config: TxConfig,
+    cached_txs: HashMap<ActorId, CachedTx>,
+    owed: HashMap<(ActorId, ActorId), u128>,
+    lock: Option<Lock>,
+    commitments: HashMap<ActorId, Commitment>,
+    batch: Option<Batch>,
+    batch_nonce: u64,
+    circuit_breaker: CircuitBreaker,
+    price_observations: Vec<PriceObservation>,
 }
 
 impl Contract {
+    /// Acquires [`Lock`] for the current message.
+    ///
+    /// A stale lock is taken over, & a lock passed to the current message by
+    /// [`Contract::pass_lock()`] is inherited. Otherwise, [`Error::Busy`] is
+    /// returned.
+    fn lock(&mut self, actor: ActorId) -> Result<(), Error> {
+        let block_ts = exec::block_timestamp();
+        let message = msg::id();
+
+        if self.lock.is_some_and(|lock| {
+            lock.message != message && !lock.is_stale(self.tx_config.expiry, block_ts)
+        }) {
+            return Err(Error::Busy);
+        }
+
+        self.lock = Some(Lock {
+            actor,
+            message,
+            locked_at: block_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Releases [`Lock`] if it's held by `message`.
+    fn unlock(&mut self, message: MessageId) {
+        if self.lock.is_some_and(|lock| lock.message == message) {
+            self.lock = None;
+        }
+    }
+
+    /// Passes [`Lock`] held by `message` to `successor`, so no other message
+    /// can acquire it in between.
+    fn pass_lock(&mut self, message: MessageId, successor: MessageId) {
+        if let Some(lock) = self.lock.as_mut().filter(|lock| lock.message == message) {
+            lock.message = successor;
+        }
+    }
+
+    /// Saves info about a new transaction of `actor` or checks that its cached
+    /// transaction can be resumed.
+    ///
+    /// Must be called before acquiring the transaction since an error after
+    /// that would remove the transaction from the cache.
+    fn begin_tx(
+        &mut self,
+        actor: ActorId,
+        (kind, action): (&ActionKind, InnerAction),
+        depositor: ActorId,
+        deposit: (u128, u128),
+    ) -> Result<(), Error> {
+        let block_ts = exec::block_timestamp();
+
+        match kind {
+            ActionKind::New => {
+                if !self.cached_txs.contains_key(&actor)
+                    && self.cached_txs.len() >= self.tx_config.max_cached_txs as usize
+                {
+                    return Err(Error::TxCacheFull);
+                }
+
+                let reservation =
+                    ReservationId::reserve(TX_COMPLETION_GAS, TX_COMPLETION_GAS_DURATION)?;
+
+                self.remove_tx(actor);
+                self.cached_txs.insert(
+                    actor,
+                    CachedTx {
+                        created_at: block_ts,
+                        depositor,
+                        deposit,
+                        is_settling: false,
+                        action: Some(action),
+                        message: msg::id(),
+                        transferred: None,
+                        failed: None,
+                        reservation: Some(reservation),
+                    },
+                );
+            }
+            ActionKind::Retry => {
+                if let Some(cached_tx) = self.cached_txs.get_mut(&actor) {
+                    // A settling transaction has already taken tokens from the
+                    // contract, so it must be finished even after its expiry.
+                    if !cached_tx.is_settling
+                        && cached_tx.is_expired(self.tx_config.expiry, block_ts)
+                    {
+                        return Err(Error::TxExpired);
+                    }
+
+                    cached_tx.message = msg::id();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes info about a transaction of `actor` & returns its unused
+    /// reserved gas.
+    fn remove_tx(&mut self, actor: ActorId) {
+        if let Some(CachedTx {
+            reservation: Some(reservation),
+            ..
+        }) = self.cached_txs.remove(&actor)
+        {
+            // The reservation could've already expired.
+            let _ = reservation.unreserve();
+        }
+    }
+
+    fn mark_settling(&mut self, actor: ActorId) {
+        if let Some(cached_tx) = self.cached_txs.get_mut(&actor) {
+            cached_tx.is_settling = true;
+        }
+    }
+
+    /// Removes info about a transaction of `actor` that isn't cached anymore.
+    fn forget_finished_tx(
+        &mut self,
+        tx_manager: &TransactionManager<CachedAction>,
+        actor: ActorId,
+    ) {
+        if self.cached_txs.contains_key(&actor)
+            && !tx_manager
+                .cached_transactions()
+                .any(|(cached_actor, _)| *cached_actor == actor)
+        {
+            self.remove_tx(actor);
+        }
+    }
+
+    /// Refunds the deposit of a cached transaction of `actor` & removes the
+    /// transaction.
+    async fn roll_back(
+        &mut self,
+        tx_manager: &mut TransactionManager<CachedAction>,
+        actor: ActorId,
+    ) -> Result<Event, Error> {
+        let cached_tx = self.cached_txs.get_mut(&actor).ok_or(Error::TxNotFound)?;
+
+        cached_tx.message = msg::id();
+
+        let cached_tx = *cached_tx;
+        let mut tx_guard = tx_manager
+            .acquire_transaction(actor, ActionKind::Retry.to_tx_kind(CachedAction::Other))?;
+        let token = [self.token.0, self.token.1];
+        let mut refund = [0; 2];
+
+        for (i, amount) in [cached_tx.deposit.0, cached_tx.deposit.1]
+            .into_iter()
+            .enumerate()
+        {
+            if amount == 0 {
+                continue;
+            }
+
+            match self
+                .replay_deposit(&mut tx_guard.stepper, token[i], cached_tx.depositor, amount)
+                .await
+            {
+                Ok(()) => refund[i] = amount,
+                Err(Error::TransferFailed) => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        for (i, amount) in refund.into_iter().enumerate() {
+            if amount != 0 {
+                self.refund(&mut tx_guard.stepper, token[i], cached_tx.depositor, amount)
+                    .await?;
+            }
+        }
+
+        self.remove_tx(actor);
+
+        Ok(Event::Abandoned {
+            actor,
+            depositor: cached_tx.depositor,
+            refund: (refund[0], refund[1]),
+        })
+    }
+
+    fn update_tx_config(
+        &mut self,
+        msg_source: ActorId,
+        tx_config: TxConfig,
+    ) -> Result<Event, Error> {
+        if msg_source != self.factory {
+            return Err(Error::AccessRestricted);
+        }
+
+        tx_config.check()?;
+        self.tx_config = tx_config;
+
+        Ok(Event::TxConfigUpdated(tx_config))
+    }
+
+    fn update_circuit_breaker(
+        &mut self,
+        msg_source: ActorId,
+        circuit_breaker: CircuitBreaker,
+    ) -> Result<Event, Error> {
+        if msg_source != self.factory {
+            return Err(Error::AccessRestricted);
+        }
+
+        circuit_breaker.check()?;
+        self.circuit_breaker = circuit_breaker;
+
+        Ok(Event::CircuitBreakerUpdated(circuit_breaker))
+    }
+
+    fn clear_seeder(&mut self, msg_source: ActorId) -> Result<Event, Error> {
+        if msg_source != self.factory {
+            return Err(Error::AccessRestricted);
+        }
+
+        self.seeder = None;
+
+        Ok(Event::SeederCleared)
+    }
+
     async fn add_liquidity(
         &mut self,
-        (tx_manager, kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
         msg_source: ActorId,
+        provider: ActorId,
         desired_amount: (u128, u128),
         min_amount: (u128, u128),
         to: ActorId,
     ) -> Result<Event, Error> {
+        self.check_seeder(provider)?;
+
         // Calculating an input amount
         let amount = if self.reserve == (0, 0) {
             desired_amount
@@ -65,6 +317,8 @@ impl Contract {
             }
         };
 
+        self.begin_tx(msg_source, (&kind, action), provider, amount)?;
+
         let mut tx_guard = tx_manager.acquire_transaction(
             msg_source,
             kind.to_tx_kind(CachedAction::AddLiquidity(amount)),
@@ -83,7 +337,7 @@ impl Contract {
             return Err(Error::Overflow);
         };
 
-        let (is_fee_on, fee_receiver, fee) = self.calculate_fee().await?;
+        let (is_fee_on, fee_receiver, fee) = self.calculate_fee()?;
         let U256PairTuple(amount_u256) = amount.into();
         let program_id = exec::program_id();
 
@@ -100,7 +354,7 @@ impl Contract {
                 .update_liquidity(
                     &mut tx_guard.stepper,
                     program_id,
-                    msg_source,
+                    provider,
                     amount,
                     balance,
                     liquidity,
@@ -141,7 +395,7 @@ impl Contract {
                 .update_liquidity(
                     &mut tx_guard.stepper,
                     program_id,
-                    msg_source,
+                    provider,
                     amount,
                     balance,
                     liquidity,
@@ -166,15 +420,30 @@ impl Contract {
         self.ft_state
             .mint(to, liquidity)
             .expect("unchecked condition occurred for `FTState`");
+        self.seeder = None;
+        self.lp_snapshots.insert(
+            to,
+            LpSnapshot {
+                reserve: self.reserve,
+                total_supply: self.ft_state.total_supply(),
+            },
+        );
 
         Ok(event)
     }
 
+    fn check_seeder(&self, provider: ActorId) -> Result<(), Error> {
+        match self.seeder {
+            Some(seeder) if seeder != provider => Err(Error::NotSeeded),
+            _ => Ok(()),
+        }
+    }
+
     async fn update_liquidity(
         &mut self,
         stepper: &mut Stepper,
         program_id: ActorId,
-        msg_source: ActorId,
+        provider: ActorId,
         amount: (u128, u128),
         balance: (u128, u128),
         liquidity: U256,
@@ -183,19 +452,22 @@ impl Contract {
             return Err(Error::InsufficientLiquidity);
         }
 
-        utils::transfer_tokens(stepper, self.token.0, msg_source, program_id, amount.0).await?;
+        self.transfer_tokens(stepper, self.token.0, provider, program_id, amount.0)
+            .await?;
 
-        if let Err(error) =
-            utils::transfer_tokens(stepper, self.token.1, msg_source, program_id, amount.1).await
+        if let Err(error) = self
+            .transfer_tokens(stepper, self.token.1, provider, program_id, amount.1)
+            .await
         {
-            utils::transfer_tokens(stepper, self.token.0, program_id, msg_source, amount.0).await?;
+            self.refund(stepper, self.token.0, provider, amount.0)
+                .await?;
 
             Err(error)
         } else {
             self.update(balance);
 
             Ok(Event::AddedLiquidity {
-                sender: msg_source,
+                sender: provider,
                 amount_a: amount.0,
                 amount_b: amount.1,
                 liquidity,
@@ -203,13 +475,8 @@ impl Contract {
         }
     }
 
-    async fn calculate_fee(&self) -> Result<(bool, ActorId, U256), Error> {
-        let fee_to_result: Result<FactoryEvent, FactoryError> =
-            utils::send(self.factory, FactoryAction::GetFeeTo)?.await?;
-        let Ok(FactoryEvent::FeeToSet(fee_receiver)) = fee_to_result else {
-                return Err(Error::FeeToGettingFailed);
-        };
-
+    fn calculate_fee(&self) -> Result<(bool, ActorId, U256), Error> {
+        let fee_receiver = self.fee_to;
         let is_fee_on = !fee_receiver.is_zero();
         let mut fee = U256::zero();
 
@@ -254,7 +521,7 @@ impl Contract {
             return Err(Error::InsufficientLiquidity);
         }
 
-        let (is_fee_on, fee_receiver, fee) = self.calculate_fee().await?;
+        let (is_fee_on, fee_receiver, fee) = self.calculate_fee()?;
         let U256PairTuple(reserve) = self.reserve.into();
 
         // Calculating an output amount
@@ -294,11 +561,14 @@ impl Contract {
             .expect("unchecked overflow occurred for `FTState`");
 
         *is_burned = true;
+        self.mark_settling(msg_source);
 
         let program_id = exec::program_id();
 
-        utils::transfer_tokens(stepper, self.token.0, program_id, to, amount.0).await?;
-        utils::transfer_tokens(stepper, self.token.1, program_id, to, amount.1).await?;
+        self.transfer_tokens(stepper, self.token.0, program_id, to, amount.0)
+            .await?;
+        self.transfer_tokens(stepper, self.token.1, program_id, to, amount.1)
+            .await?;
 
         let balance = (self.reserve.0 - amount.0, self.reserve.1 - amount.1);
 
@@ -324,7 +594,32 @@ impl Contract {
         })
     }
 
-    async fn skim(&self, stepper: &mut Stepper, to: ActorId) -> Result<Event, Error> {
+    fn update_fee_to(&mut self, msg_source: ActorId, fee_to: ActorId) -> Result<Event, Error> {
+        if msg_source != self.factory {
+            return Err(Error::AccessRestricted);
+        }
+
+        self.fee_to = fee_to;
+
+        Ok(Event::FeeToUpdated(fee_to))
+    }
+
+    async fn refresh_fee_to(&mut self) -> Result<Event, Error> {
+        let fee_to_result: Result<FactoryEvent, FactoryError> = utils::send(
+            self.factory,
+            FactoryAction::new(FactoryInnerAction::GetFeeTo),
+        )?
+        .await?;
+        let Ok(FactoryEvent::FeeToSet(fee_to)) = fee_to_result else {
+            return Err(Error::FeeToGettingFailed);
+        };
+
+        self.fee_to = fee_to;
+
+        Ok(Event::FeeToUpdated(fee_to))
+    }
+
+    async fn skim(&mut self, stepper: &mut Stepper, to: ActorId) -> Result<Event, Error> {
         let program_id = exec::program_id();
         let contract_balance = self.balances(program_id).await?;
 
@@ -335,8 +630,10 @@ impl Contract {
             return Err(Error::Overflow);
         };
 
-        utils::transfer_tokens(stepper, self.token.0, program_id, to, excess_a).await?;
-        utils::transfer_tokens(stepper, self.token.1, program_id, to, excess_b).await?;
+        self.transfer_tokens(stepper, self.token.0, program_id, to, excess_a)
+            .await?;
+        self.transfer_tokens(stepper, self.token.1, program_id, to, excess_b)
+            .await?;
 
         Ok(Event::Skim {
             amount_a: excess_a,
@@ -357,16 +654,379 @@ impl Contract {
         })
     }
 
-    async fn balances(&self, program_id: ActorId) -> Result<(u128, u128)> {
+    /// Returns the contract's balances without owed tokens.
+    async fn balances(&self, program_id: ActorId) -> Result<(u128, u128), Error> {
+        let escrow = self.total_escrow();
+
         Ok((
-            utils::balance_of(self.token.0, program_id).await?,
-            utils::balance_of(self.token.1, program_id).await?,
+            adapter::balance_of((self.token.0, self.token_standard.0), program_id)
+                .await?
+                .saturating_sub(self.total_owed(self.token.0))
+                .saturating_sub(escrow.0),
+            adapter::balance_of((self.token.1, self.token_standard.1), program_id)
+                .await?
+                .saturating_sub(self.total_owed(self.token.1))
+                .saturating_sub(escrow.1),
         ))
     }
 
+    fn total_escrow(&self) -> (u128, u128) {
+        let commitments = self
+            .commitments
+            .values()
+            .map(|commitment| commitment.deposit);
+        // Orders escrow their input amounts until the settlement & then their
+        // refunds or payouts until they're transferred.
+        let orders = self.batch.iter().flat_map(|batch| {
+            batch.orders.iter().enumerate().map(|(i, (_, order))| {
+                let normalize_balance = self.swap_pattern(order.swap_kind).normalize_balance;
+
+                match batch.clearing.as_ref() {
+                    Some(clearing) if i < clearing.paid as usize => (0, 0),
+                    Some(clearing) if clearing.payouts[i] != 0 => {
+                        normalize_balance((0, clearing.payouts[i]))
+                    }
+                    _ => normalize_balance((order.amount_in, 0)),
+                }
+            })
+        });
+
+        commitments.chain(orders).fold((0, 0), |total, amount| {
+            (
+                total.0.saturating_add(amount.0),
+                total.1.saturating_add(amount.1),
+            )
+        })
+    }
+
+    fn total_owed(&self, token: ActorId) -> u128 {
+        self.owed
+            .iter()
+            .filter(|((_, owed_token), _)| *owed_token == token)
+            .fold(0, |total, (_, amount)| total.saturating_add(*amount))
+    }
+
+    /// Transfers `amount` of `token` back to `recipient`, or records it as
+    /// owed if the token contract rejects the transfer or fails to reply
+    /// (e.g. panics).
+    async fn refund(
+        &mut self,
+        stepper: &mut Stepper,
+        token: ActorId,
+        recipient: ActorId,
+        amount: u128,
+    ) -> Result<(), Error> {
+        self.transfer_or_replay(
+            stepper,
+            (token, exec::program_id(), recipient, amount),
+            true,
+        )
+        .await
+    }
+
+    /// Transfers `token` according to its [`TokenStandard`].
+    async fn transfer_tokens(
+        &mut self,
+        stepper: &mut Stepper,
+        token: ActorId,
+        sender: ActorId,
+        recipient: ActorId,
+        amount: u128,
+    ) -> Result<(), Error> {
+        self.transfer_or_replay(stepper, (token, sender, recipient, amount), false)
+            .await
+    }
+
+    /// Replays a deposit transfer of a transaction that's rolled back.
+    ///
+    /// Sharded FT contracts don't execute a transfer with the same transaction
+    /// ID again, so one that hasn't got a reply is sent again. Other standards
+    /// would take the deposit twice, so instead the deposit is considered
+    /// transferred if the contract holds that much of the token beyond the
+    /// reserve.
+    async fn replay_deposit(
+        &mut self,
+        stepper: &mut Stepper,
+        token: ActorId,
+        depositor: ActorId,
+        amount: u128,
+    ) -> Result<(), Error> {
+        let program_id = exec::program_id();
+
+        if self.standard_of(token) == TokenStandard::ShardedFt {
+            return self
+                .transfer_tokens(stepper, token, depositor, program_id, amount)
+                .await;
+        }
+
+        let transaction_id = stepper.step()?;
+
+        if let Some(result) = self.replayed_transfer(transaction_id) {
+            return result;
+        }
+
+        let balance = self.balances(program_id).await?;
+        let excess = if token == self.token.0 {
+            balance.0.saturating_sub(self.reserve.0)
+        } else {
+            balance.1.saturating_sub(self.reserve.1)
+        };
+
+        let result = if excess < amount {
+            Err(Error::TransferFailed)
+        } else {
+            Ok(())
+        };
+
+        self.record_transfer(transaction_id, &result);
+
+        result
+    }
+
+    fn standard_of(&self, token: ActorId) -> TokenStandard {
+        if token == self.token.0 {
+            self.token_standard.0
+        } else {
+            self.token_standard.1
+        }
+    }
+
+    /// Returns the result of a transfer of a transaction processed by the
+    /// current message if the transfer has already got a reply.
+    fn replayed_transfer(&self, transaction_id: u64) -> Option<Result<(), Error>> {
+        let message = msg::id();
+        let cached_tx = self.cached_txs.values().find(|cached_tx| {
+            cached_tx.message == message
+                && cached_tx
+                    .transferred
+                    .is_some_and(|transferred| transaction_id <= transferred)
+        })?;
+
+        Some(if cached_tx.failed == Some(transaction_id) {
+            Err(Error::TransferFailed)
+        } else {
+            Ok(())
+        })
+    }
+
+    /// Sends a transfer of a transaction processed by the current message &
+    /// records it in [`CachedTx::transferred`] once it gets a reply.
+    ///
+    /// Gear-lib FT contracts execute a repeated transfer again, so a recorded
+    /// transfer isn't sent again when the transaction is resumed, & only its
+    /// result is replayed. The amount of a failed refund is recorded as owed.
+    async fn transfer_or_replay(
+        &mut self,
+        stepper: &mut Stepper,
+        (token, sender, recipient, amount): (ActorId, ActorId, ActorId, u128),
+        is_refund: bool,
+    ) -> Result<(), Error> {
+        let transaction_id = stepper.step()?;
+
+        if let Some(result) = self.replayed_transfer(transaction_id) {
+            return result;
+        }
+
+        let standard = self.standard_of(token);
+        let mut result =
+            adapter::transfer_tokens(transaction_id, (token, standard), sender, recipient, amount)
+                .await;
+
+        if is_refund && matches!(result, Err(Error::TransferFailed | Error::GstdError(_))) {
+            self.add_owed(recipient, token, amount);
+
+            result = Ok(());
+        }
+
+        self.record_transfer(transaction_id, &result);
+
+        result
+    }
+
+    /// Records a transfer of a transaction processed by the current message
+    /// that has got a reply.
+    fn record_transfer(&mut self, transaction_id: u64, result: &Result<(), Error>) {
+        let message = msg::id();
+
+        if let Some(cached_tx) = self
+            .cached_txs
+            .values_mut()
+            .find(|cached_tx| cached_tx.message == message)
+        {
+            cached_tx.transferred = Some(transaction_id);
+
+            if result.is_err() {
+                cached_tx.failed = Some(transaction_id);
+            }
+        }
+    }
+
+    fn add_owed(&mut self, actor: ActorId, token: ActorId, amount: u128) {
+        let owed = self.owed.entry((actor, token)).or_default();
+
+        *owed = owed.saturating_add(amount);
+    }
+
+    async fn claim(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+        token: ActorId,
+    ) -> Result<Event, Error> {
+        self.begin_tx(msg_source, (&kind, action), msg_source, (0, 0))?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::Claim { token, amount: 0 }),
+        )?;
+        let amount = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::Claim {
+                token: cached_token,
+                amount,
+            } = tx_data
+            {
+                (*cached_token == token).then_some(amount)
+            } else {
+                None
+            }
+        })?;
+
+        // An interrupted claim has already taken the owed amount.
+        if *amount == 0 {
+            *amount = self
+                .owed
+                .remove(&(msg_source, token))
+                .ok_or(Error::NothingToClaim)?;
+            self.mark_settling(msg_source);
+        }
+
+        let amount = *amount;
+
+        if let Err(error) = self
+            .transfer_tokens(
+                &mut tx_guard.stepper,
+                token,
+                exec::program_id(),
+                msg_source,
+                amount,
+            )
+            .await
+        {
+            self.add_owed(msg_source, token, amount);
+
+            return Err(error);
+        }
+
+        Ok(Event::Claimed {
+            actor: msg_source,
+            token,
+            amount,
+        })
+    }
+
     fn update(&mut self, balance: (u128, u128)) {
         let block_ts = exec::block_timestamp();
+
+        self.cumulative_price = self.cumulative_price_at(block_ts);
+        self.observe_price(block_ts);
+        self.reserve = balance;
+        self.last_block_ts = block_ts;
+    }
+
+    /// Takes a [`PriceObservation`] if the last one is at least
+    /// [`CircuitBreaker::twap_window`] old.
+    fn observe_price(&mut self, block_ts: u64) {
+        // The price is undefined while the reserve is empty, so older
+        // observations mustn't leak into the TWAP.
+        if self.reserve == (0, 0) {
+            self.price_observations.clear();
+        }
+
+        if self.price_observations.last().is_some_and(|observation| {
+            block_ts - observation.block_ts < self.circuit_breaker.twap_window
+        }) {
+            return;
+        }
+
+        self.price_observations.push(PriceObservation {
+            cumulative_price: self.cumulative_price.0,
+            block_ts,
+        });
+
+        if self.price_observations.len() > 2 {
+            self.price_observations.remove(0);
+        }
+    }
+
+    /// Returns the TWAP of the A token since the oldest
+    /// [`PriceObservation`], or [`None`] if there's no history yet.
+    fn twap(&self, block_ts: u64) -> Option<U256> {
+        let observation = self.price_observations.first()?;
+        let time_elapsed = block_ts - observation.block_ts;
+
+        if time_elapsed == 0 {
+            return None;
+        }
+
+        Some(
+            self.cumulative_price_at(block_ts)
+                .0
+                .overflowing_sub(observation.cumulative_price)
+                .0
+                / time_elapsed,
+        )
+    }
+
+    /// Checks a swap against [`CircuitBreaker`].
+    fn check_circuit_breaker(
+        &self,
+        kind: SwapKind,
+        (in_amount, out_amount): (u128, u128),
+    ) -> Result<(), Error> {
+        let CircuitBreaker {
+            max_deviation,
+            max_trade_size,
+            ..
+        } = self.circuit_breaker;
+        let SwapPattern {
+            reserve,
+            normalize_balance,
+            ..
+        } = self.swap_pattern(kind);
+
+        if max_trade_size != 0
+            && U256::from(in_amount) * BPS_DENOMINATOR > U256::from(reserve.0) * max_trade_size
+        {
+            return Err(Error::TradeTooLarge);
+        }
+
+        if max_deviation != 0 {
+            if let Some(twap) = self.twap(exec::block_timestamp()) {
+                let U256PairTuple(reserve) = normalize_balance((
+                    reserve.0.checked_add(in_amount).ok_or(Error::Overflow)?,
+                    reserve.1 - out_amount,
+                ))
+                .into();
+                let price = (reserve.1 << U256::from(128u64)) / reserve.0;
+
+                if price_deviation(price, twap) > max_deviation {
+                    return Err(Error::PriceDeviationExceeded);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cumulative prices as if the reserve was updated at
+    /// `block_ts`.
+    fn cumulative_price_at(&self, block_ts: u64) -> (U256, U256) {
         let time_elapsed = block_ts - self.last_block_ts;
+        let mut cumulative_price = self.cumulative_price;
 
         if time_elapsed > 0 && self.reserve != (0, 0) {
             let U256PairTuple(reserve) = self.reserve.into();
@@ -379,12 +1039,48 @@ impl Contract {
                     .0
             };
 
-            self.cumulative_price.0 += calculate_cp(reserve);
-            self.cumulative_price.1 += calculate_cp((reserve.1, reserve.0));
+            cumulative_price.0 += calculate_cp(reserve);
+            cumulative_price.1 += calculate_cp((reserve.1, reserve.0));
         }
 
-        self.reserve = balance;
-        self.last_block_ts = block_ts;
+        cumulative_price
+    }
+
+    fn amount_out(&self, kind: SwapKind, in_amount: u128) -> Result<Event, Error> {
+        calculate_out_amount(in_amount, self.swap_pattern(kind).reserve, self.fee_tier)
+            .map(Event::AmountOut)
+    }
+
+    fn amount_in(&self, kind: SwapKind, out_amount: u128) -> Result<Event, Error> {
+        calculate_in_amount(out_amount, self.swap_pattern(kind).reserve, self.fee_tier)
+            .map(Event::AmountIn)
+    }
+
+    fn record_stats(&mut self, kind: SwapKind, in_amount: u128, out_amount: u128) {
+        let block_ts = exec::block_timestamp();
+        let bucket_start = block_ts - block_ts % STATS_BUCKET_DURATION;
+        // Shouldn't be more than `in_amount`, so casting doesn't lose data.
+        let fee = (U256::from(in_amount) * self.fee_tier / FEE_DENOMINATOR).low_u128();
+        let window_start = bucket_start.saturating_sub((STATS_BUCKETS - 1) * STATS_BUCKET_DURATION);
+
+        self.stats.record(kind, in_amount, out_amount, fee);
+        self.recent_stats
+            .retain(|bucket| bucket.start >= window_start);
+
+        match self.recent_stats.last_mut() {
+            Some(bucket) if bucket.start == bucket_start => {
+                bucket.stats.record(kind, in_amount, out_amount, fee)
+            }
+            _ => {
+                let mut stats = TradingStats::default();
+
+                stats.record(kind, in_amount, out_amount, fee);
+                self.recent_stats.push(StatsBucket {
+                    start: bucket_start,
+                    stats,
+                });
+            }
+        }
     }
 
     fn swap_pattern(&self, kind: SwapKind) -> SwapPattern {
@@ -414,7 +1110,7 @@ impl Contract {
         self.check_recipient(to)?;
 
         let swap_pattern = self.swap_pattern(kind);
-        let out_amount = calculate_out_amount(in_amount, swap_pattern.reserve)?;
+        let out_amount = calculate_out_amount(in_amount, swap_pattern.reserve, self.fee_tier)?;
 
         if out_amount < min_out_amount {
             return Err(Error::InsufficientLatterAmount);
@@ -441,7 +1137,11 @@ impl Contract {
 
     async fn swap_tokens_for_exact_tokens(
         &mut self,
-        (tx_manager, action_kind): (&mut TransactionManager<CachedAction>, ActionKind),
+        (tx_manager, action_kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
         msg_source: ActorId,
         out_amount: u128,
         max_in_amount: u128,
@@ -451,7 +1151,14 @@ impl Contract {
         self.check_recipient(to)?;
 
         let swap_pattern = self.swap_pattern(swap_kind);
-        let in_amount = calculate_in_amount(out_amount, swap_pattern.reserve)?;
+        let in_amount = calculate_in_amount(out_amount, swap_pattern.reserve, self.fee_tier)?;
+
+        self.begin_tx(
+            msg_source,
+            (&action_kind, action),
+            msg_source,
+            (swap_pattern.normalize_balance)((in_amount, 0)),
+        )?;
 
         let mut tx_guard = tx_manager.acquire_transaction(
             msg_source,
@@ -490,14 +1197,20 @@ impl Contract {
             normalize_balance,
         }: SwapPattern,
     ) -> Result<Event, Error> {
+        self.check_circuit_breaker(kind, (in_amount, out_amount))?;
+
         let program_id = exec::program_id();
 
-        utils::transfer_tokens(stepper, in_token, msg_source, program_id, in_amount).await?;
+        self.transfer_tokens(stepper, in_token, msg_source, program_id, in_amount)
+            .await?;
+        self.mark_settling(msg_source);
 
-        if let Err(error) =
-            utils::transfer_tokens(stepper, out_token, program_id, to, out_amount).await
+        if let Err(error) = self
+            .transfer_tokens(stepper, out_token, program_id, to, out_amount)
+            .await
         {
-            utils::transfer_tokens(stepper, in_token, program_id, msg_source, in_amount).await?;
+            self.refund(stepper, in_token, msg_source, in_amount)
+                .await?;
 
             return Err(error);
         }
@@ -506,6 +1219,7 @@ impl Contract {
             reserve.0 + in_amount,
             reserve.1 - out_amount,
         )));
+        self.record_stats(kind, in_amount, out_amount);
 
         Ok(Event::Swap {
             sender: msg_source,
@@ -515,7 +1229,540 @@ impl Contract {
             kind,
         })
     }
-}
+
+    async fn commit_swap(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+        hash: [u8; 32],
+        deposit: (u128, u128),
+    ) -> Result<Event, Error> {
+        if self.commitments.contains_key(&msg_source) {
+            return Err(Error::CommitmentExists);
+        }
+
+        if deposit == (0, 0) {
+            return Err(Error::InsufficientAmount);
+        }
+
+        self.begin_tx(msg_source, (&kind, action), msg_source, deposit)?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::CommitSwap(deposit)),
+        )?;
+
+        tx_guard
+            .tx_data
+            .check_tx_data(|tx_data| tx_data == &CachedAction::CommitSwap(deposit))?;
+
+        let program_id = exec::program_id();
+
+        // Zero amounts are skipped as in `roll_back()`, so both take the same
+        // steps.
+        if deposit.0 != 0 {
+            self.transfer_tokens(
+                &mut tx_guard.stepper,
+                self.token.0,
+                msg_source,
+                program_id,
+                deposit.0,
+            )
+            .await?;
+        }
+
+        if deposit.1 != 0 {
+            if let Err(error) = self
+                .transfer_tokens(
+                    &mut tx_guard.stepper,
+                    self.token.1,
+                    msg_source,
+                    program_id,
+                    deposit.1,
+                )
+                .await
+            {
+                self.refund_pair(&mut tx_guard.stepper, msg_source, (deposit.0, 0))
+                    .await?;
+
+                return Err(error);
+            }
+        }
+
+        self.commitments.insert(
+            msg_source,
+            Commitment {
+                hash,
+                deposit,
+                committed_at: exec::block_height(),
+            },
+        );
+
+        Ok(Event::SwapCommitted {
+            actor: msg_source,
+            hash,
+            deposit,
+        })
+    }
+
+    async fn reveal_swap(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+        reveal: SwapReveal,
+    ) -> Result<Event, Error> {
+        let hash = reveal.hash();
+        let swap_pattern = self.swap_pattern(reveal.swap_kind);
+
+        // An interrupted reveal has already been checked & has taken the
+        // escrow.
+        let out_amount = if let ActionKind::New = kind {
+            let commitment = self
+                .commitments
+                .get(&msg_source)
+                .ok_or(Error::CommitmentNotFound)?;
+            let block_height = exec::block_height();
+
+            if block_height <= commitment.committed_at {
+                return Err(Error::RevealTooEarly);
+            }
+
+            if commitment.is_timed_out(block_height) {
+                return Err(Error::RevealWindowClosed);
+            }
+
+            if hash != commitment.hash
+                || reveal.amount_in > (swap_pattern.normalize_balance)(commitment.deposit).0
+            {
+                return Err(Error::InvalidReveal);
+            }
+
+            self.check_recipient(reveal.to)?;
+
+            let out_amount =
+                calculate_out_amount(reveal.amount_in, swap_pattern.reserve, self.fee_tier)?;
+
+            if out_amount < reveal.amount_out_min {
+                return Err(Error::InsufficientLatterAmount);
+            }
+
+            self.check_circuit_breaker(reveal.swap_kind, (reveal.amount_in, out_amount))?;
+
+            out_amount
+        } else {
+            0
+        };
+
+        self.begin_tx(msg_source, (&kind, action), msg_source, (0, 0))?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::RevealSwap {
+                hash,
+                out_amount,
+                deposit: (0, 0),
+            }),
+        )?;
+        let (out_amount, deposit) = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::RevealSwap {
+                hash: cached_hash,
+                out_amount,
+                deposit,
+            } = tx_data
+            {
+                (*cached_hash == hash).then_some((*out_amount, deposit))
+            } else {
+                None
+            }
+        })?;
+
+        if *deposit == (0, 0) {
+            *deposit = self
+                .commitments
+                .remove(&msg_source)
+                .ok_or(Error::CommitmentNotFound)?
+                .deposit;
+            self.mark_settling(msg_source);
+        }
+
+        let deposit = *deposit;
+        let SwapPattern {
+            token: (_, out_token),
+            reserve,
+            normalize_balance,
+        } = swap_pattern;
+
+        if let Err(error) = self
+            .transfer_tokens(
+                &mut tx_guard.stepper,
+                out_token,
+                exec::program_id(),
+                reveal.to,
+                out_amount,
+            )
+            .await
+        {
+            self.refund_pair(&mut tx_guard.stepper, msg_source, deposit)
+                .await?;
+
+            return Err(error);
+        }
+
+        let (escrow_in, escrow_out) = normalize_balance(deposit);
+
+        self.refund_pair(
+            &mut tx_guard.stepper,
+            msg_source,
+            normalize_balance((escrow_in - reveal.amount_in, escrow_out)),
+        )
+        .await?;
+        self.update(normalize_balance((
+            reserve.0 + reveal.amount_in,
+            reserve.1 - out_amount,
+        )));
+        self.record_stats(reveal.swap_kind, reveal.amount_in, out_amount);
+
+        Ok(Event::Swap {
+            sender: msg_source,
+            in_amount: reveal.amount_in,
+            out_amount,
+            to: reveal.to,
+            kind: reveal.swap_kind,
+        })
+    }
+
+    async fn refund_swap(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+        actor: ActorId,
+    ) -> Result<Event, Error> {
+        if let ActionKind::New = kind {
+            let commitment = self
+                .commitments
+                .get(&actor)
+                .ok_or(Error::CommitmentNotFound)?;
+
+            if msg_source != actor && !commitment.is_timed_out(exec::block_height()) {
+                return Err(Error::RevealWindowOpen);
+            }
+        }
+
+        self.begin_tx(msg_source, (&kind, action), msg_source, (0, 0))?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::RefundSwap {
+                actor,
+                deposit: (0, 0),
+            }),
+        )?;
+        let deposit = tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::RefundSwap {
+                actor: cached_actor,
+                deposit,
+            } = tx_data
+            {
+                (*cached_actor == actor).then_some(deposit)
+            } else {
+                None
+            }
+        })?;
+
+        // An interrupted refund has already taken the escrow.
+        if *deposit == (0, 0) {
+            *deposit = self
+                .commitments
+                .remove(&actor)
+                .ok_or(Error::CommitmentNotFound)?
+                .deposit;
+            self.mark_settling(msg_source);
+        }
+
+        let deposit = *deposit;
+
+        self.refund_pair(&mut tx_guard.stepper, actor, deposit)
+            .await?;
+
+        Ok(Event::SwapRefunded {
+            actor,
+            refund: deposit,
+        })
+    }
+
+    async fn submit_order(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+        order: Order,
+    ) -> Result<Event, Error> {
+        self.check_recipient(order.to)?;
+
+        if order.amount_in == 0 {
+            return Err(Error::InsufficientAmount);
+        }
+
+        if let Some(batch) = &self.batch {
+            if batch.clearing.is_some() {
+                return Err(Error::BatchSettling);
+            }
+
+            if batch.orders.iter().any(|(trader, _)| *trader == msg_source) {
+                return Err(Error::OrderExists);
+            }
+
+            if batch.orders.len() >= MAX_BATCH_ORDERS as usize {
+                return Err(Error::BatchFull);
+            }
+        }
+
+        let swap_pattern = self.swap_pattern(order.swap_kind);
+
+        self.begin_tx(
+            msg_source,
+            (&kind, action),
+            msg_source,
+            (swap_pattern.normalize_balance)((order.amount_in, 0)),
+        )?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::SubmitOrder(order)),
+        )?;
+
+        tx_guard
+            .tx_data
+            .check_tx_data(|tx_data| tx_data == &CachedAction::SubmitOrder(order))?;
+
+        self.transfer_tokens(
+            &mut tx_guard.stepper,
+            swap_pattern.token.0,
+            msg_source,
+            exec::program_id(),
+            order.amount_in,
+        )
+        .await?;
+
+        let batch = self.batch.get_or_insert_with(|| {
+            let id = self.batch_nonce;
+
+            self.batch_nonce = id.wrapping_add(1);
+            schedule_settlement();
+
+            Batch {
+                id,
+                ends_at: exec::block_height().saturating_add(BATCH_EPOCH),
+                ..Default::default()
+            }
+        });
+
+        batch.orders.push((msg_source, order));
+
+        Ok(Event::OrderSubmitted {
+            actor: msg_source,
+            batch: batch.id,
+            order,
+        })
+    }
+
+    async fn settle_batch(
+        &mut self,
+        (tx_manager, kind, action): (
+            &mut TransactionManager<CachedAction>,
+            ActionKind,
+            InnerAction,
+        ),
+        msg_source: ActorId,
+    ) -> Result<Event, Error> {
+        let batch = self.batch.as_ref().ok_or(Error::BatchNotFound)?;
+        let id = batch.id;
+        let paid = batch.clearing.as_ref().map_or(0, |clearing| clearing.paid);
+
+        if let ActionKind::New = kind {
+            if exec::block_height() < batch.ends_at {
+                return Err(Error::BatchNotEnded);
+            }
+
+            // An interrupted settlement message could've transferred its
+            // current order, so only its resumption can continue from there.
+            if self
+                .cached_txs
+                .values()
+                .any(|cached_tx| cached_tx.action == Some(InnerAction::SettleBatch))
+            {
+                return Err(Error::BatchSettling);
+            }
+        }
+
+        self.begin_tx(msg_source, (&kind, action), msg_source, (0, 0))?;
+
+        let mut tx_guard = tx_manager.acquire_transaction(
+            msg_source,
+            kind.to_tx_kind(CachedAction::SettleBatch { id, from: paid }),
+        )?;
+
+        // Every settlement message transfers payouts without a deposit to
+        // refund, so an interrupted one can only be resumed.
+        self.mark_settling(msg_source);
+
+        let from = *tx_guard.tx_data.check_and_get_tx_data(|tx_data| {
+            if let CachedAction::SettleBatch {
+                id: cached_id,
+                from,
+            } = tx_data
+            {
+                (*cached_id == id).then_some(from)
+            } else {
+                None
+            }
+        })? as usize;
+
+        let Batch {
+            orders, clearing, ..
+        } = self.batch.clone().ok_or(Error::BatchNotFound)?;
+
+        // An interrupted settlement has already cleared the batch.
+        let Clearing {
+            payouts,
+            swap_kind,
+            swap,
+            paid,
+        } = if let Some(clearing) = clearing {
+            clearing
+        } else {
+            let batch_orders: Vec<_> = orders.iter().map(|(_, order)| *order).collect();
+            let mut clearing = clear_batch(&batch_orders, self.reserve, self.fee_tier);
+
+            // A batch that trips the circuit breaker is refunded entirely
+            // rather than left unsettled.
+            if clearing.swap.0 != 0
+                && self
+                    .check_circuit_breaker(clearing.swap_kind, clearing.swap)
+                    .is_err()
+            {
+                clearing = Clearing {
+                    payouts: vec![0; orders.len()],
+                    swap_kind: clearing.swap_kind,
+                    swap: (0, 0),
+                    paid: 0,
+                };
+            }
+
+            let SwapPattern {
+                reserve,
+                normalize_balance,
+                ..
+            } = self.swap_pattern(clearing.swap_kind);
+
+            if clearing.swap.0 != 0 {
+                self.update(normalize_balance((
+                    reserve.0 + clearing.swap.0,
+                    reserve.1 - clearing.swap.1,
+                )));
+                self.record_stats(clearing.swap_kind, clearing.swap.0, clearing.swap.1);
+            }
+
+            if let Some(batch) = &mut self.batch {
+                batch.clearing = Some(clearing.clone());
+            }
+
+            clearing
+        };
+
+        let end = orders.len().min(from + MAX_SETTLED_ORDERS as usize);
+
+        // Payouts are transferred like refunds, so a failed transfer doesn't
+        // block other orders.
+        for (i, ((trader, order), payout)) in
+            orders.iter().zip(&payouts).enumerate().take(end).skip(from)
+        {
+            // Orders settled before an interruption only keep transaction IDs
+            // in step.
+            if i < paid as usize {
+                tx_guard.stepper.step()?;
+
+                continue;
+            }
+
+            let SwapPattern {
+                token: (in_token, out_token),
+                ..
+            } = self.swap_pattern(order.swap_kind);
+
+            if *payout == 0 {
+                self.refund(&mut tx_guard.stepper, in_token, *trader, order.amount_in)
+                    .await?;
+            } else {
+                self.refund(&mut tx_guard.stepper, out_token, order.to, *payout)
+                    .await?;
+            }
+
+            if let Some(clearing) = self
+                .batch
+                .as_mut()
+                .and_then(|batch| batch.clearing.as_mut())
+            {
+                clearing.paid += 1;
+            }
+        }
+
+        if end < orders.len() {
+            continue_settlement();
+
+            // Shouldn't be more than `MAX_BATCH_ORDERS`.
+            return Ok(Event::BatchPartiallySettled {
+                id,
+                paid: end as u32,
+            });
+        }
+
+        self.batch = None;
+
+        Ok(Event::BatchSettled {
+            id,
+            // Shouldn't be more than `MAX_BATCH_ORDERS`.
+            filled: payouts.iter().filter(|payout| **payout != 0).count() as u32,
+            swap_kind,
+            swap,
+        })
+    }
+
+    /// Refunds nonzero amounts of the A & B tokens to `recipient`.
+    async fn refund_pair(
+        &mut self,
+        stepper: &mut Stepper,
+        recipient: ActorId,
+        amount: (u128, u128),
+    ) -> Result<(), Error> {
+        if amount.0 != 0 {
+            self.refund(stepper, self.token.0, recipient, amount.0)
+                .await?;
+        }
+
+        if amount.1 != 0 {
+            self.refund(stepper, self.token.1, recipient, amount.1)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
 
 struct SwapPattern {
     token: (ActorId, ActorId),
@@ -523,12 +1770,45 @@ struct SwapPattern {
     normalize_balance: fn((u128, u128)) -> (u128, u128),
 }
 
-fn check_deadline(deadline: u64) -> Result<(), Error> {
-    if exec::block_timestamp() > deadline {
-        Err(Error::DeadlineExceeded)
-    } else {
-        Ok(())
+/// Sends delayed [`InnerAction::SettleBatch`] at the end of a new batch.
+///
+/// If this fails, the batch can still be settled by anyone after its end.
+fn schedule_settlement() {
+    let Ok(reservation) = ReservationId::reserve(BATCH_SETTLEMENT_GAS, BATCH_EPOCH + 1) else {
+        return;
+    };
+
+    if msg::send_delayed_from_reservation(
+        reservation,
+        exec::program_id(),
+        Action::new(InnerAction::SettleBatch),
+        0,
+        BATCH_EPOCH,
+    )
+    .is_err()
+    {
+        let _ = reservation.unreserve();
+    }
+}
+
+/// Sends [`InnerAction::SettleBatch`] to continue a partially settled batch.
+///
+/// If this fails, anyone can continue the settlement.
+fn continue_settlement() {
+    let _ = msg::send(exec::program_id(), Action::new(InnerAction::SettleBatch), 0);
+}
+
+/// Checks `deadline` of a new transaction.
+///
+/// A retry resumes a transaction whose deadline was checked on its creation.
+fn check_deadline(kind: ActionKind, deadline: u64) -> Result<(), Error> {
+    if let ActionKind::New = kind {
+        if exec::block_timestamp() > deadline {
+            return Err(Error::DeadlineExceeded);
+        }
     }
+
+    Ok(())
 }
 
 #[no_mangle]
@@ -547,6 +1827,12 @@ fn process_init() -> Result<(), Error> {
     let Initialize {
         pair: token,
         factory,
+        fee_tier,
+        fee_to,
+        seeder,
+        tx_config,
+        circuit_breaker,
+        token_standard,
     } = msg::load()?;
 
     if token.0.is_zero() || token.1.is_zero() {
@@ -557,11 +1843,24 @@ fn process_init() -> Result<(), Error> {
         return Err(Error::IdenticalTokens);
     }
 
+    if fee_tier >= FEE_DENOMINATOR {
+        return Err(Error::InvalidFeeTier);
+    }
+
+    tx_config.check()?;
+    circuit_breaker.check()?;
+
     unsafe {
         STATE = Some((
             Contract {
                 token,
+                token_standard,
+                fee_tier,
                 factory,
+                fee_to,
+                seeder,
+                tx_config,
+                circuit_breaker,
                 ..Default::default()
             },
             TransactionManager::default(),
@@ -571,18 +1870,153 @@ fn process_init() -> Result<(), Error> {
     Ok(())
 }
 
-#[gstd::async_main]
+#[gstd::async_main(handle_signal = process_signal)]
 async fn main() {
     msg::reply(process_handle().await, 0).expect("failed to encode or reply `handle()`");
 }
 
+/// Sends [`InnerAction::Complete`] from the gas reservation of a transaction
+/// whose message was interrupted & passes [`Lock`] to it, or releases the lock
+/// if there's nothing to complete.
+fn process_signal() {
+    let (contract, _) = state_mut();
+    let Ok(message) = msg::signal_from() else {
+        return;
+    };
+
+    let completion = contract
+        .cached_txs
+        .iter_mut()
+        .find(|(_, cached_tx)| cached_tx.message == message)
+        .and_then(|(&actor, cached_tx)| {
+            let reservation = cached_tx.reservation.take()?;
+            let completion = msg::send_from_reservation(
+                reservation,
+                exec::program_id(),
+                Action::new(InnerAction::Complete(actor)),
+                0,
+            );
+
+            if completion.is_err() {
+                cached_tx.reservation = Some(reservation);
+            }
+
+            completion.ok()
+        });
+
+    if let Some(completion) = completion {
+        contract.pass_lock(message, completion);
+    } else {
+        contract.unlock(message);
+    }
+}
+
 async fn process_handle() -> Result<Event, Error> {
     let Action {
         action,
         kind: action_kind,
     } = msg::load()?;
-    let (contract, tx_manager) = state_mut();
     let msg_source = msg::source();
+    let is_locking = locks_reserve(&action);
+
+    if is_locking {
+        // Lets the signal handler release the lock or complete a transaction
+        // if this message is interrupted.
+        exec::system_reserve_gas(SIGNAL_GAS)?;
+        state_mut().0.lock(msg_source)?;
+    }
+
+    let actor = if let InnerAction::Complete(actor) | InnerAction::Abandon(actor) = action {
+        actor
+    } else {
+        msg_source
+    };
+    let result = process_action(msg_source, action, action_kind).await;
+    let (contract, tx_manager) = state_mut();
+
+    if is_locking {
+        contract.unlock(msg::id());
+    }
+
+    contract.forget_finished_tx(tx_manager, actor);
+
+    result
+}
+
+/// Checks whether `action` changes the reserve or the contract's token
+/// balances & so must hold [`Lock`].
+fn locks_reserve(action: &InnerAction) -> bool {
+    matches!(
+        action,
+        InnerAction::AddLiquidity { .. }
+            | InnerAction::AddLiquidityFor { .. }
+            | InnerAction::RemoveLiquidity { .. }
+            | InnerAction::SwapExactTokensForTokens { .. }
+            | InnerAction::SwapTokensForExactTokens { .. }
+            | InnerAction::Skim(_)
+            | InnerAction::Sync
+            | InnerAction::Abandon(_)
+            | InnerAction::Complete(_)
+            | InnerAction::Claim(_)
+            | InnerAction::CommitSwap { .. }
+            | InnerAction::RevealSwap(_)
+            | InnerAction::RefundSwap(_)
+            | InnerAction::SubmitOrder(_)
+            | InnerAction::SettleBatch
+    )
+}
+
+/// Finishes an interrupted transaction of `actor`.
+async fn complete(msg_source: ActorId, actor: ActorId) -> Result<Event, Error> {
+    if msg_source != exec::program_id() {
+        return Err(Error::AccessRestricted);
+    }
+
+    finish_tx(actor).await
+}
+
+/// Finishes a transaction of `actor` that's abandoned by the actor itself or
+/// has expired.
+async fn abandon(msg_source: ActorId, actor: ActorId) -> Result<Event, Error> {
+    let contract = &state_mut().0;
+    let cached_tx = contract.cached_txs.get(&actor).ok_or(Error::TxNotFound)?;
+
+    if msg_source != actor
+        && !cached_tx.is_expired(contract.tx_config.expiry, exec::block_timestamp())
+    {
+        return Err(Error::TxNotExpired);
+    }
+
+    finish_tx(actor).await
+}
+
+/// Resumes or rolls back a cached transaction of `actor`.
+///
+/// A transaction that has already taken tokens from the contract can only be
+/// finished, otherwise its deposit is refunded.
+async fn finish_tx(actor: ActorId) -> Result<Event, Error> {
+    let (contract, tx_manager) = state_mut();
+    let cached_tx = contract.cached_txs.get(&actor).ok_or(Error::TxNotFound)?;
+
+    match (cached_tx.is_settling, cached_tx.action) {
+        (true, Some(action)) => {
+            // Boxed since `process_action()` finishes transactions with this
+            // function.
+            let resumption: Pin<Box<dyn Future<Output = Result<Event, Error>>>> =
+                Box::pin(process_action(actor, action, ActionKind::Retry));
+
+            resumption.await
+        }
+        _ => contract.roll_back(tx_manager, actor).await,
+    }
+}
+
+async fn process_action(
+    msg_source: ActorId,
+    action: InnerAction,
+    action_kind: ActionKind,
+) -> Result<Event, Error> {
+    let (contract, tx_manager) = state_mut();
 
     match action {
         InnerAction::AddLiquidity {
@@ -593,11 +2027,12 @@ async fn process_handle() -> Result<Event, Error> {
             to,
             deadline,
         } => {
-            check_deadline(deadline)?;
+            check_deadline(action_kind, deadline)?;
 
             contract
                 .add_liquidity(
-                    (tx_manager, action_kind),
+                    (tx_manager, action_kind, action),
+                    msg_source,
                     msg_source,
                     (amount_a_desired, amount_b_desired),
                     (amount_a_min, amount_b_min),
@@ -605,6 +2040,31 @@ async fn process_handle() -> Result<Event, Error> {
                 )
                 .await
         }
+        InnerAction::AddLiquidityFor {
+            provider,
+            amount_a_desired,
+            amount_b_desired,
+            amount_a_min,
+            amount_b_min,
+            deadline,
+        } => {
+            if msg_source != contract.factory {
+                return Err(Error::AccessRestricted);
+            }
+
+            check_deadline(action_kind, deadline)?;
+
+            contract
+                .add_liquidity(
+                    (tx_manager, action_kind, action),
+                    msg_source,
+                    provider,
+                    (amount_a_desired, amount_b_desired),
+                    (amount_a_min, amount_b_min),
+                    provider,
+                )
+                .await
+        }
         InnerAction::RemoveLiquidity {
             liquidity,
             amount_a_min,
@@ -612,6 +2072,8 @@ async fn process_handle() -> Result<Event, Error> {
             to,
             deadline,
         } => {
+            contract.begin_tx(msg_source, (&action_kind, action), msg_source, (0, 0))?;
+
             let mut tx_guard = tx_manager.acquire_transaction(
                 msg_source,
                 action_kind.to_tx_kind(CachedAction::RemovedLiquidity {
@@ -632,7 +2094,7 @@ async fn process_handle() -> Result<Event, Error> {
                 }
             })?;
 
-            check_deadline(deadline)?;
+            check_deadline(action_kind, deadline)?;
 
             contract
                 .remove_liquidity(
@@ -652,6 +2114,10 @@ async fn process_handle() -> Result<Event, Error> {
             deadline,
             swap_kind,
         } => {
+            let deposit = (contract.swap_pattern(swap_kind).normalize_balance)((amount_in, 0));
+
+            contract.begin_tx(msg_source, (&action_kind, action), msg_source, deposit)?;
+
             let mut tx_guard = tx_manager.acquire_transaction(
                 msg_source,
                 action_kind.to_tx_kind(CachedAction::Swap(amount_in)),
@@ -661,7 +2127,7 @@ async fn process_handle() -> Result<Event, Error> {
                 .tx_data
                 .check_tx_data(|tx_data| *tx_data == CachedAction::Swap(amount_in))?;
 
-            check_deadline(deadline)?;
+            check_deadline(action_kind, deadline)?;
 
             contract
                 .swap_exact_tokens_for_tokens(
@@ -681,11 +2147,11 @@ async fn process_handle() -> Result<Event, Error> {
             deadline,
             swap_kind,
         } => {
-            check_deadline(deadline)?;
+            check_deadline(action_kind, deadline)?;
 
             contract
                 .swap_tokens_for_exact_tokens(
-                    (tx_manager, action_kind),
+                    (tx_manager, action_kind, action),
                     msg_source,
                     amount_out,
                     amount_in_max,
@@ -694,7 +2160,34 @@ async fn process_handle() -> Result<Event, Error> {
                 )
                 .await
         }
+        InnerAction::CommitSwap { hash, deposit } => {
+            contract
+                .commit_swap((tx_manager, action_kind, action), msg_source, hash, deposit)
+                .await
+        }
+        InnerAction::RevealSwap(reveal) => {
+            contract
+                .reveal_swap((tx_manager, action_kind, action), msg_source, reveal)
+                .await
+        }
+        InnerAction::RefundSwap(actor) => {
+            contract
+                .refund_swap((tx_manager, action_kind, action), msg_source, actor)
+                .await
+        }
+        InnerAction::SubmitOrder(order) => {
+            contract
+                .submit_order((tx_manager, action_kind, action), msg_source, order)
+                .await
+        }
+        InnerAction::SettleBatch => {
+            contract
+                .settle_batch((tx_manager, action_kind, action), msg_source)
+                .await
+        }
         InnerAction::Skim(to) => {
+            contract.begin_tx(msg_source, (&action_kind, action), msg_source, (0, 0))?;
+
             let mut tx_guard = tx_manager
                 .acquire_transaction(msg_source, action_kind.to_tx_kind(CachedAction::Other))?;
 
@@ -704,12 +2197,74 @@ async fn process_handle() -> Result<Event, Error> {
 
             contract.skim(&mut tx_guard.stepper, to).await
         }
-        InnerAction::Sync => contract.sync().await,
+        InnerAction::Sync => {
+            if contract.seeder.is_some() {
+                return Err(Error::NotSeeded);
+            }
+
+            contract.sync().await
+        }
         InnerAction::Transfer { to, amount } => contract
             .ft_state
             .transfer(to, amount)
             .map(Into::into)
             .map_err(Into::into),
+        InnerAction::Approve { spender, amount } => contract
+            .ft_state
+            .approve(spender, amount)
+            .map(Into::into)
+            .map_err(Into::into),
+        InnerAction::TransferFrom { from, to, amount } => contract
+            .ft_state
+            .transfer_from(from, to, amount)
+            .map(Into::into)
+            .map_err(Into::into),
+        InnerAction::UpdateFeeTo(fee_to) => contract.update_fee_to(msg_source, fee_to),
+        InnerAction::RefreshFeeTo => contract.refresh_fee_to().await,
+        InnerAction::UpdateTxConfig(tx_config) => contract.update_tx_config(msg_source, tx_config),
+        InnerAction::UpdateCircuitBreaker(circuit_breaker) => {
+            contract.update_circuit_breaker(msg_source, circuit_breaker)
+        }
+        InnerAction::ClearSeeder => contract.clear_seeder(msg_source),
+        InnerAction::Abandon(actor) => abandon(msg_source, actor).await,
+        InnerAction::Complete(actor) => complete(msg_source, actor).await,
+        InnerAction::Claim(token) => {
+            contract
+                .claim((tx_manager, action_kind, action), msg_source, token)
+                .await
+        }
+        InnerAction::GetInfo => Ok(Event::Info {
+            factory: contract.factory,
+            token: contract.token,
+            fee_tier: contract.fee_tier,
+        }),
+        InnerAction::GetReserves => Ok(Event::Reserves {
+            reserve_a: contract.reserve.0,
+            reserve_b: contract.reserve.1,
+            last_block_ts: contract.last_block_ts,
+        }),
+        InnerAction::GetAmountOut {
+            swap_kind,
+            amount_in,
+        } => contract.amount_out(swap_kind, amount_in),
+        InnerAction::GetAmountIn {
+            swap_kind,
+            amount_out,
+        } => contract.amount_in(swap_kind, amount_out),
+        InnerAction::GetLpBalance(actor) => Ok(Event::LpBalance {
+            actor,
+            balance: contract.ft_state.balance_of(actor),
+        }),
+        InnerAction::GetCumulativePrices => {
+            let block_ts = exec::block_timestamp();
+            let (price_a, price_b) = contract.cumulative_price_at(block_ts);
+
+            Ok(Event::CumulativePrices {
+                price_a,
+                price_b,
+                block_ts,
+            })
+        }
     }
 }
 
@@ -718,13 +2273,29 @@ extern "C" fn state() {
     let (
         Contract {
             factory,
+            fee_to,
 
             token,
+            token_standard,
+            fee_tier,
             reserve,
             cumulative_price,
             last_block_ts,
             k_last,
             ft_state,
+            seeder,
+            lp_snapshots,
+            stats,
+            recent_stats,
+            tx_config,
+            cached_txs,
+            owed,
+            lock,
+            commitments,
+            batch,
+            batch_nonce: _,
+            circuit_breaker,
+            price_observations,
         },
         tx_manager,
     ) = state_mut();
@@ -732,8 +2303,11 @@ extern "C" fn state() {
     msg::reply(
         State {
             factory: *factory,
+            fee_to: *fee_to,
 
             token: *token,
+            token_standard: *token_standard,
+            fee_tier: *fee_tier,
             reserve: *reserve,
             cumulative_price: *cumulative_price,
 
@@ -741,11 +2315,23 @@ extern "C" fn state() {
             k_last: *k_last,
 
             ft_state: ft_state.clone().into(),
+            seeder: *seeder,
+            lp_snapshots: lp_snapshots.iter().map(|(k, v)| (*k, *v)).collect(),
+            stats: *stats,
+            recent_stats: recent_stats.clone(),
+            tx_config: *tx_config,
 
             cached_actions: tx_manager
                 .cached_transactions()
                 .map(|(k, v)| (*k, *v))
                 .collect(),
+            cached_txs: cached_txs.iter().map(|(k, v)| (*k, *v)).collect(),
+            owed: owed.iter().map(|(k, v)| (*k, *v)).collect(),
+            lock: *lock,
+            commitments: commitments.iter().map(|(k, v)| (*k, *v)).collect(),
+            batch: batch.clone(),
+            circuit_breaker: *circuit_breaker,
+            price_observations: price_observations.clone(),
         },
         0,
     )
@@ -0,0 +1,162 @@
+use super::utils;
+use dex_pair_io::*;
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gstd::{exec, prelude::*, ActorId};
+
+/// Builds payloads for a fungible token standard & interprets its replies.
+trait TokenAdapter {
+    type Action: Encode;
+    type Reply: Decode;
+
+    fn transfer(
+        transaction_id: u64,
+        sender: ActorId,
+        recipient: ActorId,
+        amount: u128,
+    ) -> Self::Action;
+
+    fn transfer_result(reply: Self::Reply) -> Result<(), Error>;
+
+    fn balance_of(actor: ActorId) -> Self::Action;
+
+    fn balance(reply: Self::Reply) -> Result<u128, Error>;
+}
+
+/// See [`TokenStandard::ShardedFt`].
+struct ShardedFt;
+
+impl TokenAdapter for ShardedFt {
+    type Action = FTokenAction;
+    type Reply = FTokenEvent;
+
+    fn transfer(
+        transaction_id: u64,
+        sender: ActorId,
+        recipient: ActorId,
+        amount: u128,
+    ) -> Self::Action {
+        FTokenAction::Message {
+            transaction_id,
+            payload: LogicAction::Transfer {
+                sender,
+                recipient,
+                amount,
+            },
+        }
+    }
+
+    fn transfer_result(reply: Self::Reply) -> Result<(), Error> {
+        match reply {
+            FTokenEvent::Ok => Ok(()),
+            FTokenEvent::Err => Err(Error::TransferFailed),
+            _ => Err(Error::UnexpectedTokenReply),
+        }
+    }
+
+    fn balance_of(actor: ActorId) -> Self::Action {
+        FTokenAction::GetBalance(actor)
+    }
+
+    fn balance(reply: Self::Reply) -> Result<u128, Error> {
+        if let FTokenEvent::Balance(balance) = reply {
+            Ok(balance)
+        } else {
+            Err(Error::UnexpectedTokenReply)
+        }
+    }
+}
+
+/// See [`TokenStandard::GearLibFt`].
+struct GearLibFt;
+
+impl TokenAdapter for GearLibFt {
+    type Action = Action;
+    type Reply = Result<Event, Error>;
+
+    fn transfer(_: u64, sender: ActorId, recipient: ActorId, amount: u128) -> Self::Action {
+        let amount = amount.into();
+
+        // The contract doesn't need an approval to transfer its own tokens.
+        Action::new(if sender == exec::program_id() {
+            InnerAction::Transfer {
+                to: recipient,
+                amount,
+            }
+        } else {
+            InnerAction::TransferFrom {
+                from: sender,
+                to: recipient,
+                amount,
+            }
+        })
+    }
+
+    fn transfer_result(reply: Self::Reply) -> Result<(), Error> {
+        match reply {
+            Ok(Event::Transfer(_)) => Ok(()),
+            Ok(_) => Err(Error::UnexpectedTokenReply),
+            Err(_) => Err(Error::TransferFailed),
+        }
+    }
+
+    fn balance_of(actor: ActorId) -> Self::Action {
+        Action::new(InnerAction::GetLpBalance(actor))
+    }
+
+    fn balance(reply: Self::Reply) -> Result<u128, Error> {
+        if let Ok(Event::LpBalance { balance, .. }) = reply {
+            balance.try_into().map_err(|_| Error::Overflow)
+        } else {
+            Err(Error::UnexpectedTokenReply)
+        }
+    }
+}
+
+pub async fn transfer_tokens(
+    transaction_id: u64,
+    (token, standard): (ActorId, TokenStandard),
+    sender: ActorId,
+    recipient: ActorId,
+    amount: u128,
+) -> Result<(), Error> {
+    match standard {
+        TokenStandard::ShardedFt => {
+            transfer::<ShardedFt>(token, transaction_id, sender, recipient, amount).await
+        }
+        TokenStandard::GearLibFt => {
+            transfer::<GearLibFt>(token, transaction_id, sender, recipient, amount).await
+        }
+    }
+}
+
+pub async fn balance_of(
+    (token, standard): (ActorId, TokenStandard),
+    actor: ActorId,
+) -> Result<u128, Error> {
+    match standard {
+        TokenStandard::ShardedFt => balance::<ShardedFt>(token, actor).await,
+        TokenStandard::GearLibFt => balance::<GearLibFt>(token, actor).await,
+    }
+}
+
+async fn transfer<T: TokenAdapter>(
+    token: ActorId,
+    transaction_id: u64,
+    sender: ActorId,
+    recipient: ActorId,
+    amount: u128,
+) -> Result<(), Error> {
+    let reply = utils::send(
+        token,
+        T::transfer(transaction_id, sender, recipient, amount),
+    )?
+    .await?;
+
+    T::transfer_result(reply)
+}
+
+async fn balance<T: TokenAdapter>(token: ActorId, actor: ActorId) -> Result<u128, Error> {
+    let reply = utils::send(token, T::balance_of(actor))?.await?;
+
+    T::balance(reply)
+}
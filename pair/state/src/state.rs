@@ -1,5 +1,8 @@
 use dex_pair_io::{
-    hidden::{calculate_in_amount, calculate_out_amount, quote},
+    hidden::{
+        amount_in_max, amount_out_min, calculate_in_amount, calculate_out_amount, max_in_amount,
+        price_impact, quote,
+    },
     *,
 };
 use gmeta::{metawasm, Metadata};
@@ -51,10 +54,14 @@ pub mod metafns {
         in_amount: u128,
     ) -> Result<u128, Error> {
         match swap_kind {
-            SwapKind::AForB => super::calculate_out_amount(in_amount, state.reserve),
-            SwapKind::BForA => {
-                super::calculate_out_amount(in_amount, (state.reserve.1, state.reserve.0))
+            SwapKind::AForB => {
+                super::calculate_out_amount(in_amount, state.reserve, state.fee_tier)
             }
+            SwapKind::BForA => super::calculate_out_amount(
+                in_amount,
+                (state.reserve.1, state.reserve.0),
+                state.fee_tier,
+            ),
         }
     }
 
@@ -64,10 +71,138 @@ pub mod metafns {
         out_amount: u128,
     ) -> Result<u128, Error> {
         match swap_kind {
-            SwapKind::AForB => super::calculate_in_amount(out_amount, state.reserve),
-            SwapKind::BForA => {
-                super::calculate_in_amount(out_amount, (state.reserve.1, state.reserve.0))
+            SwapKind::AForB => {
+                super::calculate_in_amount(out_amount, state.reserve, state.fee_tier)
             }
+            SwapKind::BForA => super::calculate_in_amount(
+                out_amount,
+                (state.reserve.1, state.reserve.0),
+                state.fee_tier,
+            ),
         }
     }
+
+    pub fn fee_to(state: State) -> ActorId {
+        state.fee_to
+    }
+
+    pub fn fee_tier(state: State) -> FeeTier {
+        state.fee_tier
+    }
+
+    pub fn seeder(state: State) -> Option<ActorId> {
+        state.seeder
+    }
+
+    pub fn lp_position(state: State, actor: ActorId) -> LpPosition {
+        state.lp_position(actor)
+    }
+
+    pub fn stats(state: State) -> TradingStats {
+        state.stats
+    }
+
+    pub fn stats_since(state: State, since: u64) -> TradingStats {
+        state.stats_since(since)
+    }
+
+    /// Returns the price impact (in basis points) of swapping `in_amount`.
+    pub fn price_impact(state: State, swap_kind: SwapKind, in_amount: u128) -> Result<u16, Error> {
+        super::price_impact(
+            in_amount,
+            super::directed_reserve(state.reserve, swap_kind),
+            state.fee_tier,
+        )
+    }
+
+    /// Returns the maximum input amount whose price impact doesn't exceed
+    /// `max_impact` (in basis points).
+    pub fn max_in_amount(
+        state: State,
+        swap_kind: SwapKind,
+        max_impact: u16,
+    ) -> Result<u128, Error> {
+        super::max_in_amount(
+            max_impact,
+            super::directed_reserve(state.reserve, swap_kind),
+            state.fee_tier,
+        )
+    }
+
+    /// Returns `amount_out_min` for swapping `in_amount` with a given
+    /// slippage tolerance (in basis points).
+    pub fn amount_out_min(
+        state: State,
+        swap_kind: SwapKind,
+        in_amount: u128,
+        slippage: u16,
+    ) -> Result<u128, Error> {
+        super::calculate_out_amount(
+            in_amount,
+            super::directed_reserve(state.reserve, swap_kind),
+            state.fee_tier,
+        )
+        .map(|out_amount| super::amount_out_min(out_amount, slippage))
+    }
+
+    /// Returns `amount_in_max` for swapping for `out_amount` with a given
+    /// slippage tolerance (in basis points).
+    pub fn amount_in_max(
+        state: State,
+        swap_kind: SwapKind,
+        out_amount: u128,
+        slippage: u16,
+    ) -> Result<u128, Error> {
+        super::calculate_in_amount(
+            out_amount,
+            super::directed_reserve(state.reserve, swap_kind),
+            state.fee_tier,
+        )
+        .and_then(|in_amount| super::amount_in_max(in_amount, slippage))
+    }
+
+    pub fn tx_config(state: State) -> TxConfig {
+        state.tx_config
+    }
+
+    pub fn cached_tx(state: State, actor: ActorId) -> Option<CachedTx> {
+        state
+            .cached_txs
+            .into_iter()
+            .find_map(|(cached_actor, cached_tx)| (cached_actor == actor).then_some(cached_tx))
+    }
+
+    pub fn owed(state: State, actor: ActorId, token: ActorId) -> u128 {
+        state.owed(actor, token)
+    }
+
+    pub fn lock(state: State) -> Option<Lock> {
+        state.lock
+    }
+
+    pub fn commitment(state: State, actor: ActorId) -> Option<Commitment> {
+        state
+            .commitments
+            .into_iter()
+            .find_map(|(committer, commitment)| (committer == actor).then_some(commitment))
+    }
+
+    pub fn batch(state: State) -> Option<Batch> {
+        state.batch
+    }
+
+    pub fn circuit_breaker(state: State) -> CircuitBreaker {
+        state.circuit_breaker
+    }
+
+    pub fn token_standard(state: State) -> (TokenStandard, TokenStandard) {
+        state.token_standard
+    }
+}
+
+fn directed_reserve(reserve: (u128, u128), swap_kind: SwapKind) -> (u128, u128) {
+    match swap_kind {
+        SwapKind::AForB => reserve,
+        SwapKind::BForA => (reserve.1, reserve.0),
+    }
 }
@@ -165,6 +165,17 @@ async fn send_message_for_pair(
     send_message(client, listener, destination, payload).await
 }
 
+async fn calculate_handle_gas(
+    client: &GearApi,
+    destination: [u8; 32],
+    payload: impl Encode,
+) -> Result<u64> {
+    Ok(client
+        .calculate_handle_gas(None, destination.into(), payload.encode(), 0, true)
+        .await?
+        .min_limit)
+}
+
 async fn send_message_with_insufficient_gas(
     client: &GearApi,
     listener: &mut EventListener,
@@ -180,6 +191,144 @@ async fn send_message_with_insufficient_gas(
     )
 }
 
+/// Uploads 2 FT contracts & the Factory contract, creates a pair of the
+/// tokens, & mints `amount` of both tokens to [`ALICE`] with an approval for
+/// the pair.
+///
+/// Returns [`ActorId`]s of the A & B tokens & the pair.
+async fn deploy_pair(
+    client: &GearApi,
+    listener: &mut EventListener,
+    amount: u128,
+) -> Result<([u8; 32], [u8; 32], [u8; 32])> {
+    let storage_code_hash = upload_code_by_path(client, FT_STORAGE).await?;
+    let ft_logic_code_hash = upload_code_by_path(client, FT_LOGIC).await?;
+    let mut ft = [[0; 32]; 2];
+
+    for ft in &mut ft {
+        *ft = upload_program(
+            client,
+            listener,
+            FT_MAIN,
+            InitFToken {
+                storage_code_hash,
+                ft_logic_code_hash,
+            },
+        )
+        .await?;
+    }
+
+    ft.sort_unstable_by(|a, b| b.cmp(a));
+
+    let pair_code_hash = upload_code(client, WASM_BINARY_OPT).await?;
+    let (factory_actor_id, reply) =
+        upload_program_and_wait_reply::<Result<(), dex_factory_io::Error>>(
+            client,
+            listener,
+            dex_factory::WASM_BINARY_OPT.into(),
+            dex_factory_io::Initialize {
+                fee_to: ActorId::zero(),
+                admins: vec![ALICE.into()],
+                threshold: 1,
+                pair: pair_code_hash.into(),
+                fee_tiers: vec![DEFAULT_FEE_TIER],
+                timelock_delay: 0,
+            },
+        )
+        .await?;
+    assert_eq!(reply, Ok(()));
+
+    let reply: Result<dex_factory_io::Event, dex_factory_io::Error> = send_message(
+        client,
+        listener,
+        factory_actor_id,
+        dex_factory_io::Action::new(dex_factory_io::InnerAction::CreatePair(
+            ft[1].into(),
+            ft[0].into(),
+            DEFAULT_FEE_TIER,
+        )),
+    )
+    .await?;
+    let Ok(dex_factory_io::Event::PairCreated { pair_actor, .. }) = reply else {
+        unreachable!()
+    };
+
+    for ft in ft {
+        for (transaction_id, payload) in [
+            LogicAction::Mint {
+                recipient: ALICE.into(),
+                amount,
+            },
+            LogicAction::Approve {
+                approved_account: pair_actor,
+                amount,
+            },
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(
+                FTokenEvent::Ok,
+                send_message(
+                    client,
+                    listener,
+                    ft,
+                    FTokenAction::Message {
+                        transaction_id: transaction_id as u64,
+                        payload,
+                    },
+                )
+                .await?
+            );
+        }
+    }
+
+    Ok((ft[0], ft[1], pair_actor.into()))
+}
+
+async fn ft_balance(
+    client: &GearApi,
+    listener: &mut EventListener,
+    ft: [u8; 32],
+    actor: [u8; 32],
+) -> Result<u128> {
+    let FTokenEvent::Balance(balance) = send_message(
+        client,
+        listener,
+        ft,
+        FTokenAction::GetBalance(actor.into()),
+    )
+    .await?
+    else {
+        unreachable!()
+    };
+
+    Ok(balance)
+}
+
+/// Sends [`InnerAction::Abandon`] until a cached transaction of `actor` stops
+/// holding [`Lock`], e.g. because [`InnerAction::Complete`] finished it.
+async fn abandon_when_unlocked(
+    client: &GearApi,
+    listener: &mut EventListener,
+    pair: [u8; 32],
+    actor: [u8; 32],
+) -> Result<Result<Event, Error>> {
+    loop {
+        let reply = send_message_for_pair(
+            client,
+            listener,
+            pair,
+            Action::new(InnerAction::Abandon(actor.into())),
+        )
+        .await?;
+
+        if reply != Err(Error::Busy) {
+            break Ok(reply);
+        }
+    }
+}
+
 #[tokio::test]
 #[ignore]
 async fn state_consistency() -> Result<()> {
@@ -224,8 +373,11 @@ async fn state_consistency() -> Result<()> {
             dex_factory::WASM_BINARY_OPT.into(),
             dex_factory_io::Initialize {
                 fee_to: ActorId::zero(),
-                fee_to_setter: ActorId::zero(),
+                admins: vec![ALICE.into()],
+                threshold: 1,
                 pair: pair_code_hash.into(),
+                fee_tiers: vec![DEFAULT_FEE_TIER],
+                timelock_delay: 0,
             },
         )
         .await?;
@@ -235,11 +387,16 @@ async fn state_consistency() -> Result<()> {
         &client,
         &mut listener,
         factory_actor_id,
-        dex_factory_io::Action::CreatePair(ft_actor_id_b.into(), ft_actor_id_a.into()),
+        dex_factory_io::Action::new(dex_factory_io::InnerAction::CreatePair(
+            ft_actor_id_b.into(),
+            ft_actor_id_a.into(),
+            DEFAULT_FEE_TIER,
+        )),
     )
     .await?;
     let pair_actor_id = if let dex_factory_io::Event::PairCreated {
         token_pair: _,
+        fee_tier: _,
         pair_actor,
         pair_number,
     } = reply.unwrap()
@@ -413,3 +570,305 @@ async fn state_consistency() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[ignore]
+async fn factory_state_consistency() -> Result<()> {
+    use dex_factory_io::{pair_actor_id, InnerAction as FactoryInnerAction};
+
+    let client = GearApi::dev_from_path(env!("GEAR_NODE_PATH"))
+        .await
+        .unwrap();
+    let mut listener = client.subscribe().await?;
+
+    let pair_code_hash = upload_code(&client, WASM_BINARY_OPT).await?;
+    let (factory_actor_id, reply) =
+        upload_program_and_wait_reply::<Result<(), dex_factory_io::Error>>(
+            &client,
+            &mut listener,
+            dex_factory::WASM_BINARY_OPT.into(),
+            dex_factory_io::Initialize {
+                fee_to: ActorId::zero(),
+                admins: vec![ALICE.into()],
+                threshold: 1,
+                pair: pair_code_hash.into(),
+                fee_tiers: vec![DEFAULT_FEE_TIER],
+                timelock_delay: 0,
+            },
+        )
+        .await?;
+    assert_eq!(reply, Ok(()));
+
+    // The Pair contract doesn't interact with tokens on its initialization.
+    let token_pair = (ActorId::from([2; 32]), ActorId::from([1; 32]));
+    let action = dex_factory_io::Action::new(FactoryInnerAction::CreatePair(
+        token_pair.1,
+        token_pair.0,
+        DEFAULT_FEE_TIER,
+    ));
+
+    println!(
+        "{}",
+        send_message_with_insufficient_gas(&client, &mut listener, factory_actor_id, action)
+            .await?
+    );
+
+    // The retry must adopt the pair created by the interrupted attempt, if
+    // any, instead of creating a duplicate.
+    let reply: Result<dex_factory_io::Event, dex_factory_io::Error> =
+        send_message(&client, &mut listener, factory_actor_id, action.to_retry()).await?;
+    assert_eq!(
+        reply,
+        Ok(dex_factory_io::Event::PairCreated {
+            token_pair,
+            fee_tier: DEFAULT_FEE_TIER,
+            pair_actor: pair_actor_id(
+                factory_actor_id.into(),
+                pair_code_hash.into(),
+                token_pair,
+                DEFAULT_FEE_TIER
+            ),
+            pair_number: 1,
+        })
+    );
+
+    let reply: Result<dex_factory_io::Event, dex_factory_io::Error> =
+        send_message(&client, &mut listener, factory_actor_id, action).await?;
+    assert_eq!(reply, Err(dex_factory_io::Error::PairExist));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn fee_to_gas() -> Result<()> {
+    let client = GearApi::dev_from_path(env!("GEAR_NODE_PATH"))
+        .await
+        .unwrap();
+    let mut listener = client.subscribe().await?;
+
+    let amount = 100000;
+    let liquidity = amount / 2;
+    let (_, _, pair) = deploy_pair(&client, &mut listener, amount).await?;
+    let deadline = 999999999999999999;
+
+    assert_eq!(
+        send_message_for_pair(
+            &client,
+            &mut listener,
+            pair,
+            Action::new(InnerAction::AddLiquidity {
+                amount_a_desired: liquidity,
+                amount_b_desired: liquidity,
+                amount_a_min: 0,
+                amount_b_min: 0,
+                to: ALICE.into(),
+                deadline,
+            }),
+        )
+        .await?,
+        Ok(Event::AddedLiquidity {
+            sender: ALICE.into(),
+            amount_a: liquidity,
+            amount_b: liquidity,
+            liquidity: (liquidity - MINIMUM_LIQUIDITY as u128).into(),
+        }),
+    );
+
+    let add_gas = calculate_handle_gas(
+        &client,
+        pair,
+        Action::new(InnerAction::AddLiquidity {
+            amount_a_desired: liquidity,
+            amount_b_desired: liquidity,
+            amount_a_min: 0,
+            amount_b_min: 0,
+            to: ALICE.into(),
+            deadline,
+        }),
+    )
+    .await?;
+    let remove_gas = calculate_handle_gas(
+        &client,
+        pair,
+        Action::new(InnerAction::RemoveLiquidity {
+            liquidity: (liquidity / 2).into(),
+            amount_a_min: 0,
+            amount_b_min: 0,
+            to: ALICE.into(),
+            deadline,
+        }),
+    )
+    .await?;
+
+    // Before `fee_to` was cached, every mint & burn got it from the Factory
+    // contract with the same round trip as `InnerAction::RefreshFeeTo` does
+    // on top of a plain message like `InnerAction::GetReserves`.
+    let query_gas =
+        calculate_handle_gas(&client, pair, Action::new(InnerAction::GetReserves)).await?;
+    let refresh_gas =
+        calculate_handle_gas(&client, pair, Action::new(InnerAction::RefreshFeeTo)).await?;
+    let round_trip_gas = refresh_gas.saturating_sub(query_gas);
+
+    for (action, gas) in [("AddLiquidity", add_gas), ("RemoveLiquidity", remove_gas)] {
+        let old_gas = gas + round_trip_gas;
+        let saved_share = round_trip_gas * 100 / old_gas;
+
+        println!(
+            "`{action}`: {gas} gas with cached `fee_to`, {old_gas} gas with `GetFeeTo` on \
+            every call ({saved_share}% saved)."
+        );
+
+        // The round trip is a noticeable share of a mint or a burn.
+        assert!(saved_share >= 1);
+    }
+
+    assert_eq!(
+        send_message_for_pair(
+            &client,
+            &mut listener,
+            pair,
+            Action::new(InnerAction::RefreshFeeTo),
+        )
+        .await?,
+        Ok(Event::FeeToUpdated(ActorId::zero())),
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn signal_completion() -> Result<()> {
+    let client = GearApi::dev_from_path(env!("GEAR_NODE_PATH"))
+        .await
+        .unwrap();
+    let mut listener = client.subscribe().await?;
+
+    let amount = 100000;
+    let liquidity = amount / 2;
+    let swap_amount = liquidity / 2;
+    let (ft_a, ft_b, pair) = deploy_pair(&client, &mut listener, amount).await?;
+    let deadline = 999999999999999999;
+
+    assert_eq!(
+        send_message_for_pair(
+            &client,
+            &mut listener,
+            pair,
+            Action::new(InnerAction::AddLiquidity {
+                amount_a_desired: liquidity,
+                amount_b_desired: liquidity,
+                amount_a_min: 0,
+                amount_b_min: 0,
+                to: ALICE.into(),
+                deadline,
+            }),
+        )
+        .await?,
+        Ok(Event::AddedLiquidity {
+            sender: ALICE.into(),
+            amount_a: liquidity,
+            amount_b: liquidity,
+            liquidity: (liquidity - MINIMUM_LIQUIDITY as u128).into(),
+        }),
+    );
+
+    let Ok(Event::AmountOut(out_amount)) = send_message_for_pair(
+        &client,
+        &mut listener,
+        pair,
+        Action::new(InnerAction::GetAmountOut {
+            swap_kind: SwapKind::AForB,
+            amount_in: swap_amount,
+        }),
+    )
+    .await?
+    else {
+        unreachable!()
+    };
+
+    println!(
+        "{}",
+        send_message_with_insufficient_gas(
+            &client,
+            &mut listener,
+            pair,
+            Action::new(InnerAction::SwapExactTokensForTokens {
+                amount_in: swap_amount,
+                amount_out_min: 0,
+                to: ALICE.into(),
+                deadline,
+                swap_kind: SwapKind::AForB,
+            }),
+        )
+        .await?
+    );
+
+    // The signal handler finishes the swap if it has taken tokens from the
+    // contract, or rolls it back otherwise.
+    assert_eq!(
+        abandon_when_unlocked(&client, &mut listener, pair, ALICE).await?,
+        Err(Error::TxNotFound)
+    );
+
+    let Ok(Event::Reserves {
+        reserve_a,
+        reserve_b,
+        ..
+    }) = send_message_for_pair(
+        &client,
+        &mut listener,
+        pair,
+        Action::new(InnerAction::GetReserves),
+    )
+    .await?
+    else {
+        unreachable!()
+    };
+    let (reserve, balance) = if (reserve_a, reserve_b) == (liquidity, liquidity) {
+        (
+            (liquidity, liquidity),
+            (amount - liquidity, amount - liquidity),
+        )
+    } else {
+        (
+            (liquidity + swap_amount, liquidity - out_amount),
+            (
+                amount - liquidity - swap_amount,
+                amount - liquidity + out_amount,
+            ),
+        )
+    };
+
+    assert_eq!((reserve_a, reserve_b), reserve);
+    assert_eq!(
+        (
+            ft_balance(&client, &mut listener, ft_a, ALICE).await?,
+            ft_balance(&client, &mut listener, ft_b, ALICE).await?,
+        ),
+        balance
+    );
+
+    // An interrupted action without a transaction releases the lock too.
+
+    println!(
+        "{}",
+        send_message_with_insufficient_gas(
+            &client,
+            &mut listener,
+            pair,
+            Action::new(InnerAction::Sync),
+        )
+        .await?
+    );
+    assert_eq!(
+        send_message_for_pair(&client, &mut listener, pair, Action::new(InnerAction::Sync)).await?,
+        Ok(Event::Sync {
+            reserve_a,
+            reserve_b
+        }),
+    );
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use utils::{prelude::*, FungibleToken};
+use utils::{prelude::*, FungibleToken, MockToken, Response, SilentProgram};
 
 mod utils;
 
@@ -18,7 +18,7 @@ fn swaps_and_fee() {
 
     // Initialization of the contracts
 
-    let mut factory = Factory::initialize(&system, USERS[2], 0, 3).succeed();
+    let mut factory = Factory::initialize(&system, USERS[2], USERS[2], 3).succeed();
     let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
     let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
     let mut pair = Pair(system.get_program(pair_actor));
@@ -224,7 +224,7 @@ fn swap_errors() {
     let mut fungible_token_b = FungibleToken::initialize(&system);
     let mut fungible_token_a = FungibleToken::initialize(&system);
 
-    let mut factory = Factory::initialize(&system, 0, 0, 3).succeed();
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
     let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
     let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
     let mut pair = Pair(system.get_program(pair_actor));
@@ -275,23 +275,34 @@ fn swap_errors() {
 
 #[test]
 fn factory() {
+    use dex_factory_io::{AdminAction, Event};
+
     let system = utils::initialize_system();
     let mut factory = Factory::initialize(&system, 0, USERS[0], 1).succeed();
 
-    factory.state().fee_to_setter().eq(USERS[0].into());
+    factory.state().admins().eq(vec![USERS[0].into()]);
+    factory.state().threshold().eq(1);
     factory.state().fee_to().eq(ActorId::zero());
 
-    // FeeToSetter
+    // Admins
 
     factory
-        .fee_to_setter(USERS[0], ActorId::zero())
+        .queue_and_execute(USERS[0], AdminAction::AddAdmin(ActorId::zero()))
         .failed(dex_factory_io::Error::ZeroActorId);
     factory
-        .fee_to_setter(FOREIGN_USER, ActorId::zero())
+        .queue_and_execute(FOREIGN_USER, AdminAction::AddAdmin(USERS[1].into()))
         .failed(dex_factory_io::Error::AccessRestricted);
+    factory
+        .queue_and_execute(USERS[0], AdminAction::RemoveAdmin(USERS[0].into()))
+        .failed(dex_factory_io::Error::InvalidThreshold);
 
-    factory.fee_to_setter(USERS[0], USERS[1]).succeed(USERS[1]);
-    factory.state().fee_to_setter().eq(USERS[1].into());
+    factory
+        .queue_and_execute(USERS[0], AdminAction::AddAdmin(USERS[1].into()))
+        .succeed(Event::AdminAdded(USERS[1].into()));
+    factory
+        .queue_and_execute(USERS[1], AdminAction::RemoveAdmin(USERS[0].into()))
+        .succeed(Event::AdminRemoved(USERS[0].into()));
+    factory.state().admins().eq(vec![USERS[1].into()]);
 
     // FeeTo
 
@@ -321,10 +332,10 @@ fn factory() {
         .succeed((actor_pair, 1))
         .into();
 
-    factory
-        .state()
-        .all_pairs()
-        .eq(vec![(actor_pair, pair_actor)]);
+    factory.state().all_pairs().eq(vec![(
+        (actor_pair.0, actor_pair.1, DEFAULT_FEE_TIER),
+        pair_actor,
+    )]);
     factory.state().all_pairs_length().eq(1);
     factory.state().pair(actor_pair).eq(pair_actor);
 
@@ -340,7 +351,7 @@ fn add_liquidity() {
     let mut fungible_token_b = FungibleToken::initialize(&system);
     let mut fungible_token_a = FungibleToken::initialize(&system);
 
-    let mut factory = Factory::initialize(&system, 0, 0, 3).succeed();
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
     let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
     let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
     let mut pair = Pair(system.get_program(pair_actor));
@@ -385,7 +396,7 @@ fn add_liquidity_2() {
     let fungible_token_b = FungibleToken::initialize(&system);
     let mut fungible_token_a = FungibleToken::initialize(&system);
 
-    let mut factory = Factory::initialize(&system, 0, 0, 3).succeed();
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
     let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
     let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
     let mut pair = Pair(system.get_program(pair_actor));
@@ -431,7 +442,7 @@ fn remove_liquidity() {
     let mut fungible_token_b = FungibleToken::initialize(&system);
     let mut fungible_token_a = FungibleToken::initialize(&system);
 
-    let mut factory = Factory::initialize(&system, 0, 0, 3).succeed();
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
     let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
     let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
     let mut pair = Pair(system.get_program(pair_actor));
@@ -459,3 +470,2528 @@ fn remove_liquidity() {
     pair.remove_liquidity(USERS[0], 1, (0, 2), USERS[0])
         .failed(Error::InsufficientLatterAmount);
 }
+
+#[test]
+fn fee_to_caching() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    // Broadcasting from the factory
+
+    pair.state().fee_to().eq(ActorId::zero());
+    factory.fee_to(USERS[0], USERS[2]).succeed(USERS[2].into());
+    pair.state().fee_to().eq(USERS[2].into());
+
+    pair.update_fee_to(USERS[0], USERS[0])
+        .failed(Error::AccessRestricted);
+    pair.refresh_fee_to().succeed(USERS[2]);
+    pair.state().fee_to().eq(USERS[2].into());
+
+    // The fee collection without the factory round trip. `USERS[1]` isn't a
+    // program, so any message sent to it by the pair would never be replied.
+
+    let mut pair =
+        Pair::initialize(&system, actor_pair, USERS[1], DEFAULT_FEE_TIER, USERS[2], 4).succeed();
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ + SWAP_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+
+    let pair_reserve = (INIT_LIQ + SWAP_AMOUNT, INIT_LIQ - out_amount);
+    let U256PairTuple(reserve) = pair_reserve.into();
+    let root_k = (reserve.0 * reserve.1).integer_sqrt().low_u128();
+    let root_k_last = INIT_LIQ;
+    let fee = (INIT_LIQ * (root_k - root_k_last)) / (root_k * 5 + root_k_last);
+    let total_supply_with_fee = INIT_LIQ + fee;
+
+    pair.remove_liquidity(USERS[0], CLEAN_INIT_LIQ, (0, 0), USERS[0])
+        .succeed((
+            USERS[0],
+            (
+                CLEAN_INIT_LIQ * pair_reserve.0 / total_supply_with_fee,
+                CLEAN_INIT_LIQ * pair_reserve.1 / total_supply_with_fee,
+            ),
+            USERS[0],
+        ));
+    pair.state().balance_of(USERS[2]).eq(fee);
+}
+
+#[test]
+fn timelock() {
+    use dex_factory_io::{AdminAction, QueuedOperation};
+
+    const DELAY: u64 = 10 * 1000;
+
+    let system = utils::initialize_system();
+
+    Factory::initialize_with_timelock_delay(
+        &system,
+        0,
+        USERS[0],
+        dex_factory_io::MAX_TIMELOCK_DELAY + 1,
+        1,
+    )
+    .failed(dex_factory_io::Error::InvalidTimelockDelay);
+
+    let mut factory =
+        Factory::initialize_with_timelock_delay(&system, 0, USERS[0], DELAY, 2).succeed();
+
+    factory.state().timelock_delay().eq(DELAY);
+
+    // Queue
+
+    let action = AdminAction::FeeTo(USERS[1].into());
+    let eta = system.block_timestamp() + DELAY;
+
+    factory
+        .queue(FOREIGN_USER, action, eta)
+        .failed(dex_factory_io::Error::AccessRestricted);
+    factory
+        .queue(USERS[0], action, eta - 1)
+        .failed(dex_factory_io::Error::EtaTooEarly);
+    factory
+        .queue(USERS[0], AdminAction::TimelockDelay(u64::MAX), eta)
+        .failed(dex_factory_io::Error::InvalidTimelockDelay);
+
+    factory
+        .queue(USERS[0], action, eta)
+        .succeed((0, action, eta));
+    factory
+        .state()
+        .queued_operations()
+        .eq(vec![(0, QueuedOperation { action, eta })]);
+
+    // Execute
+
+    factory
+        .execute(FOREIGN_USER, 0)
+        .failed(dex_factory_io::Error::OperationNotReady);
+    factory.state().fee_to().eq(ActorId::zero());
+
+    system.spend_blocks((DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 0)
+        .succeed(dex_factory_io::Event::FeeToChanged {
+            fee_to: USERS[1].into(),
+            unnotified_pairs: vec![],
+        });
+    factory.state().fee_to().eq(USERS[1].into());
+    factory.state().queued_operations().eq(vec![]);
+    factory
+        .execute(FOREIGN_USER, 0)
+        .failed(dex_factory_io::Error::OperationNotFound);
+
+    // Cancel
+
+    let action = AdminAction::TimelockDelay(0);
+    let eta = system.block_timestamp() + DELAY;
+
+    factory
+        .queue(USERS[0], action, eta)
+        .succeed((1, action, eta));
+    factory
+        .cancel(FOREIGN_USER, 1)
+        .failed(dex_factory_io::Error::AccessRestricted);
+    factory.cancel(USERS[0], 1).succeed(1);
+    factory
+        .cancel(USERS[0], 1)
+        .failed(dex_factory_io::Error::OperationNotFound);
+
+    system.spend_blocks((DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 1)
+        .failed(dex_factory_io::Error::OperationNotFound);
+    factory.state().timelock_delay().eq(DELAY);
+}
+
+#[test]
+fn multisig() {
+    use dex_factory_io::{AdminAction, Error, Event, PendingProposal, Proposal};
+
+    const ETA_DELAY: u64 = 10 * 1000;
+
+    let system = utils::initialize_system();
+
+    Factory::initialize_with_multisig(&system, 0, USERS, 0, 0, 1).failed(Error::InvalidThreshold);
+    Factory::initialize_with_multisig(&system, 0, USERS, 4, 0, 2).failed(Error::InvalidThreshold);
+    Factory::initialize_with_multisig(&system, 0, &[USERS[0], USERS[0]], 1, 0, 3)
+        .failed(Error::AdminExists);
+
+    let mut factory = Factory::initialize_with_multisig(&system, 0, USERS, 2, 0, 4).succeed();
+
+    factory
+        .state()
+        .admins()
+        .eq(USERS.iter().copied().map(Into::into).collect());
+    factory.state().threshold().eq(2);
+
+    // Proposing & confirming
+
+    let action = AdminAction::FeeTo(USERS[2].into());
+    let eta = system.block_timestamp() + ETA_DELAY;
+    let proposal = Proposal::Queue { action, eta };
+
+    factory
+        .propose(FOREIGN_USER, proposal)
+        .failed(Error::AccessRestricted);
+    factory
+        .propose(USERS[0], proposal)
+        .succeed(Event::Confirmed {
+            id: 0,
+            confirmations: 1,
+        });
+    factory.state().proposals().eq(vec![(
+        0,
+        PendingProposal {
+            proposal,
+            confirmations: vec![USERS[0].into()],
+        },
+    )]);
+
+    factory.confirm(USERS[0], 0).failed(Error::AlreadyConfirmed);
+    factory.confirm(USERS[0], 1).failed(Error::ProposalNotFound);
+    factory.revoke(USERS[1], 0).failed(Error::NotConfirmed);
+
+    factory
+        .confirm(USERS[1], 0)
+        .succeed(Event::Queued { id: 0, action, eta });
+    factory.state().proposals().eq(vec![]);
+
+    system.spend_blocks((ETA_DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 0)
+        .succeed(Event::FeeToChanged {
+            fee_to: USERS[2].into(),
+            unnotified_pairs: vec![],
+        });
+    factory.state().fee_to().eq(USERS[2].into());
+
+    // Revoking
+
+    let proposal = Proposal::Queue {
+        action: AdminAction::Threshold(3),
+        eta: system.block_timestamp() + ETA_DELAY,
+    };
+
+    factory
+        .propose(USERS[2], proposal)
+        .succeed(Event::Confirmed {
+            id: 1,
+            confirmations: 1,
+        });
+    factory.revoke(USERS[2], 1).succeed((1, 0));
+    factory.state().proposals().eq(vec![]);
+
+    // Canceling
+
+    let action = AdminAction::RemoveAdmin(USERS[2].into());
+    let eta = system.block_timestamp() + ETA_DELAY;
+
+    factory
+        .propose(USERS[2], Proposal::Queue { action, eta })
+        .succeed(Event::Confirmed {
+            id: 2,
+            confirmations: 1,
+        });
+    factory
+        .confirm(USERS[0], 2)
+        .succeed(Event::Queued { id: 1, action, eta });
+    factory
+        .propose(USERS[0], Proposal::Cancel(1))
+        .succeed(Event::Confirmed {
+            id: 3,
+            confirmations: 1,
+        });
+    factory.confirm(USERS[1], 3).succeed(Event::Canceled(1));
+
+    system.spend_blocks((ETA_DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 1)
+        .failed(Error::OperationNotFound);
+
+    // Membership changes
+
+    let eta = system.block_timestamp() + ETA_DELAY;
+
+    factory
+        .propose(USERS[1], Proposal::Queue { action, eta })
+        .succeed(Event::Confirmed {
+            id: 4,
+            confirmations: 1,
+        });
+    factory
+        .confirm(USERS[2], 4)
+        .succeed(Event::Queued { id: 2, action, eta });
+
+    // This proposal must be discarded after `USERS[2]` removal.
+    factory
+        .propose(USERS[2], proposal)
+        .succeed(Event::Confirmed {
+            id: 5,
+            confirmations: 1,
+        });
+
+    system.spend_blocks((ETA_DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 2)
+        .succeed(Event::AdminRemoved(USERS[2].into()));
+    factory
+        .state()
+        .admins()
+        .eq(vec![USERS[0].into(), USERS[1].into()]);
+    factory.state().proposals().eq(vec![]);
+    factory
+        .propose(USERS[2], proposal)
+        .failed(Error::AccessRestricted);
+
+    // Failed & retried approvals
+
+    let threshold_action = AdminAction::Threshold(1);
+    let eta = system.block_timestamp() + ETA_DELAY;
+
+    factory
+        .propose(
+            USERS[0],
+            Proposal::Queue {
+                action: threshold_action,
+                eta,
+            },
+        )
+        .succeed(Event::Confirmed {
+            id: 6,
+            confirmations: 1,
+        });
+    factory.confirm(USERS[1], 6).succeed(Event::Queued {
+        id: 3,
+        action: threshold_action,
+        eta,
+    });
+
+    let action = AdminAction::FeeTo(USERS[0].into());
+    let eta = system.block_timestamp() + ETA_DELAY * 2;
+    let pending_proposal = Proposal::Queue { action, eta };
+
+    factory
+        .propose(USERS[0], pending_proposal)
+        .succeed(Event::Confirmed {
+            id: 7,
+            confirmations: 1,
+        });
+    factory
+        .propose(
+            USERS[0],
+            Proposal::Queue {
+                action,
+                eta: system.block_timestamp() + 1000,
+            },
+        )
+        .succeed(Event::Confirmed {
+            id: 8,
+            confirmations: 1,
+        });
+
+    system.spend_blocks((ETA_DELAY / 1000) as u32);
+
+    factory
+        .execute(FOREIGN_USER, 3)
+        .succeed(Event::ThresholdSet(1));
+
+    // The failed proposal is removed since it can't be approved later.
+    factory.confirm(USERS[1], 8).failed(Error::EtaTooEarly);
+    factory.state().proposals().eq(vec![(
+        7,
+        PendingProposal {
+            proposal: pending_proposal,
+            confirmations: vec![USERS[0].into()],
+        },
+    )]);
+
+    // The pending proposal reached the lowered threshold, so its approval can
+    // be retried.
+    factory
+        .confirm(USERS[0], 7)
+        .succeed(Event::Queued { id: 4, action, eta });
+    factory.state().proposals().eq(vec![]);
+}
+
+#[test]
+fn fee_tiers() {
+    use dex_factory_io::{AdminAction, Error, Event};
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    Pair::initialize(
+        &system,
+        actor_pair,
+        ActorId::zero(),
+        FEE_DENOMINATOR,
+        ActorId::zero(),
+        3,
+    )
+    .failed(dex_pair_io::Error::InvalidFeeTier);
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 4).succeed();
+
+    factory.state().fee_tiers().eq(FEE_TIERS.into());
+
+    // Creating pairs for the same tokens with different fees
+
+    let pair_actor_30 = factory
+        .create_pair_with_fee_tier(actor_pair, 30)
+        .succeed((actor_pair, 30, 1))
+        .into();
+    let pair_actor_100 = factory
+        .create_pair_with_fee_tier((actor_pair.1, actor_pair.0), 100)
+        .succeed((actor_pair, 100, 2))
+        .into();
+
+    factory
+        .create_pair_with_fee_tier(actor_pair, 100)
+        .failed(Error::PairExist);
+    factory
+        .create_pair_with_fee_tier(actor_pair, 7)
+        .failed(Error::FeeTierNotEnabled);
+
+    factory
+        .state()
+        .pair_with_fee_tier(actor_pair, 100)
+        .eq(pair_actor_100);
+    factory
+        .state()
+        .pairs_for((actor_pair.1, actor_pair.0))
+        .eq(vec![(30, pair_actor_30), (100, pair_actor_100)]);
+
+    let pair = Pair(system.get_program(pair_actor_100));
+
+    pair.state().fee_tier().eq(100);
+    pair.state().token().eq(actor_pair);
+
+    // Enabling & disabling tiers
+
+    factory
+        .queue_and_execute(USERS[0], AdminAction::EnableFeeTier(30))
+        .failed(Error::FeeTierEnabled);
+    factory
+        .queue_and_execute(USERS[0], AdminAction::EnableFeeTier(FEE_DENOMINATOR))
+        .failed(Error::InvalidFeeTier);
+    factory
+        .queue_and_execute(USERS[0], AdminAction::DisableFeeTier(7))
+        .failed(Error::FeeTierNotEnabled);
+
+    factory
+        .queue_and_execute(USERS[0], AdminAction::EnableFeeTier(7))
+        .succeed(Event::FeeTierEnabled(7));
+    factory
+        .create_pair_with_fee_tier(actor_pair, 7)
+        .succeed((actor_pair, 7, 3));
+
+    factory
+        .queue_and_execute(USERS[0], AdminAction::DisableFeeTier(5))
+        .succeed(Event::FeeTierDisabled(5));
+    factory
+        .create_pair_with_fee_tier(actor_pair, 5)
+        .failed(Error::FeeTierNotEnabled);
+    factory.state().fee_tiers().eq(vec![30, 100, 7]);
+    factory.state().all_pairs_length().eq(3);
+}
+
+#[test]
+fn pair_addresses() {
+    use dex_factory_io::pair_actor_id;
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_code = factory.state().pair_code().0;
+
+    for (pair_number, fee_tier) in (1..).zip(FEE_TIERS.iter().copied()) {
+        // The order of tokens mustn't affect the address.
+        let predicted_pair_actor = pair_actor_id(
+            factory.actor_id(),
+            pair_code,
+            (actor_pair.1, actor_pair.0),
+            fee_tier,
+        );
+        let pair_actor: ActorId = factory
+            .create_pair_with_fee_tier(actor_pair, fee_tier)
+            .succeed((actor_pair, fee_tier, pair_number))
+            .into();
+
+        assert_eq!(pair_actor, predicted_pair_actor);
+    }
+}
+
+#[test]
+fn pair_registration() {
+    use dex_factory_io::{AdminAction, Error as FactoryError, Event as FactoryEvent};
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, USERS[2], USERS[0], 3).succeed();
+    let foreign_pair = Pair::initialize(
+        &system,
+        actor_pair,
+        USERS[1],
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        4,
+    )
+    .succeed();
+    let reversed_pair = Pair::initialize(
+        &system,
+        (actor_pair.1, actor_pair.0),
+        factory.actor_id(),
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        5,
+    )
+    .succeed();
+    let pair = Pair::initialize(
+        &system,
+        actor_pair,
+        factory.actor_id(),
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        6,
+    )
+    .succeed();
+    let register_pair = |pair_actor, fee_tier| AdminAction::RegisterPair {
+        pair_actor,
+        token_pair: actor_pair,
+        fee_tier,
+    };
+
+    factory
+        .queue_and_execute(
+            USERS[0],
+            register_pair(foreign_pair.actor_id(), DEFAULT_FEE_TIER),
+        )
+        .failed(FactoryError::PairVerificationFailed);
+    factory
+        .queue_and_execute(USERS[0], register_pair(pair.actor_id(), 100))
+        .failed(FactoryError::PairVerificationFailed);
+    // The A & B tokens of the pair must be in the order of the key.
+    factory
+        .queue_and_execute(
+            USERS[0],
+            register_pair(reversed_pair.actor_id(), DEFAULT_FEE_TIER),
+        )
+        .failed(FactoryError::PairVerificationFailed);
+
+    factory
+        .queue_and_execute(USERS[0], register_pair(pair.actor_id(), DEFAULT_FEE_TIER))
+        .succeed(FactoryEvent::PairRegistered {
+            token_pair: actor_pair,
+            fee_tier: DEFAULT_FEE_TIER,
+            pair_actor: pair.actor_id(),
+            pair_number: 1,
+        });
+    factory.state().pair(actor_pair).eq(pair.actor_id());
+    factory.state().all_pairs_length().eq(1);
+    pair.state().fee_to().eq(USERS[2].into());
+
+    factory
+        .queue_and_execute(USERS[0], register_pair(pair.actor_id(), DEFAULT_FEE_TIER))
+        .failed(FactoryError::PairExist);
+    factory
+        .create_pair(actor_pair)
+        .failed(FactoryError::PairExist);
+}
+
+#[test]
+fn pair_records() {
+    use dex_factory_io::{AdminAction, Event as FactoryEvent, PairRecord};
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_code = factory.state().pair_code().0;
+
+    // Created pair
+
+    let created_from = system.block_timestamp();
+    let created_pair_actor = factory
+        .create_pair(actor_pair)
+        .succeed((actor_pair, 1))
+        .into();
+    let created_to = system.block_timestamp();
+
+    let created_pairs = factory.state().pairs_by_creator(FOREIGN_USER).0;
+    let [(created_key, created_record)] = created_pairs[..] else {
+        panic!("expected 1 created pair, got {created_pairs:?}");
+    };
+
+    assert_eq!(created_key, (actor_pair.0, actor_pair.1, DEFAULT_FEE_TIER));
+    assert_eq!(
+        created_record,
+        PairRecord {
+            pair_actor: created_pair_actor,
+            creator: FOREIGN_USER.into(),
+            code_id: Some(pair_code),
+            ..created_record
+        }
+    );
+
+    // Registered pair
+
+    system.spend_blocks(10);
+
+    let registered_pair = Pair::initialize(
+        &system,
+        actor_pair,
+        factory.actor_id(),
+        100,
+        ActorId::zero(),
+        4,
+    )
+    .succeed();
+    let registered_from = system.block_timestamp();
+
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::RegisterPair {
+                pair_actor: registered_pair.actor_id(),
+                token_pair: actor_pair,
+                fee_tier: 100,
+            },
+        )
+        .succeed(FactoryEvent::PairRegistered {
+            token_pair: actor_pair,
+            fee_tier: 100,
+            pair_actor: registered_pair.actor_id(),
+            pair_number: 2,
+        });
+
+    let registered_to = system.block_timestamp();
+    let registered_pairs = factory.state().pairs_by_creator(USERS[0]).0;
+    let [(registered_key, registered_record)] = registered_pairs[..] else {
+        panic!("expected 1 registered pair, got {registered_pairs:?}");
+    };
+
+    assert_eq!(registered_key, (actor_pair.0, actor_pair.1, 100));
+    assert_eq!(
+        registered_record,
+        PairRecord {
+            pair_actor: registered_pair.actor_id(),
+            creator: USERS[0].into(),
+            code_id: None,
+            ..registered_record
+        }
+    );
+    assert!(registered_record.created_at_block > created_record.created_at_block);
+
+    // Time ranges
+
+    factory
+        .state()
+        .pairs_created_between(created_from, created_to)
+        .eq(vec![(created_key, created_record)]);
+    factory
+        .state()
+        .pairs_created_between(registered_from, registered_to)
+        .eq(vec![(registered_key, registered_record)]);
+    factory
+        .state()
+        .pairs_created_between(created_from, registered_to)
+        .eq(vec![
+            (created_key, created_record),
+            (registered_key, registered_record),
+        ]);
+    factory
+        .state()
+        .pairs_created_between(registered_to + 1, u64::MAX)
+        .eq(vec![]);
+}
+
+#[test]
+fn create_pair_with_liquidity() {
+    use dex_factory_io::{pair_actor_id, Error as FactoryError};
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
+    let factory_actor = factory.actor_id();
+    let pair_code = factory.state().pair_code().0;
+
+    // Creating with the initial liquidity
+
+    let pair_actor = pair_actor_id(factory_actor, pair_code, actor_pair, DEFAULT_FEE_TIER);
+    let liquidity = (U256::from(INIT_LIQ) * U256::from(INIT_LIQ / 2))
+        .integer_sqrt()
+        .low_u128()
+        - MINIMUM_LIQUIDITY as u128;
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair_actor, INIT_LIQ);
+    fungible_token_b.approve(USERS[0], pair_actor, INIT_LIQ / 2);
+
+    // Amounts must follow the order of passed tokens.
+    let created_pair_actor = factory
+        .create_pair_with_liquidity(
+            USERS[0],
+            (actor_pair.1, actor_pair.0),
+            DEFAULT_FEE_TIER,
+            (INIT_LIQ / 2, INIT_LIQ),
+        )
+        .succeed((actor_pair, DEFAULT_FEE_TIER, 1, liquidity));
+    let pair = Pair(system.get_program(created_pair_actor));
+
+    assert_eq!(pair.actor_id(), pair_actor);
+    factory.state().pair(actor_pair).eq(pair_actor);
+    pair.state().balance_of(USERS[0]).eq(liquidity);
+    pair.state().reserve().eq((INIT_LIQ, INIT_LIQ / 2));
+    pair.state().seeder().eq(None);
+
+    factory
+        .create_pair_with_liquidity(USERS[0], actor_pair, DEFAULT_FEE_TIER, (1, 1))
+        .failed(FactoryError::PairExist);
+
+    // Failed seeding
+
+    let pair_actor: [u8; 32] = pair_actor_id(factory_actor, pair_code, actor_pair, 100).into();
+
+    factory
+        .create_pair_with_liquidity(USERS[1], actor_pair, 100, (INIT_LIQ, INIT_LIQ))
+        .failed(FactoryError::SeedingFailed(Error::TransferFailed));
+
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    factory
+        .state()
+        .pair_with_fee_tier(actor_pair, 100)
+        .eq(pair.actor_id());
+
+    // The failed flow can't be resumed, so the pair doesn't await the initial
+    // liquidity from the creator anymore.
+    pair.state().seeder().eq(None);
+
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    // Only the seeder can add the first liquidity.
+
+    let mut pair = Pair::initialize_with_seeder(
+        &system,
+        actor_pair,
+        USERS[2],
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        Some(USERS[1]),
+        4,
+    )
+    .succeed();
+
+    pair.state().seeder().eq(Some(USERS[1].into()));
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .failed(Error::NotSeeded);
+    pair.sync().failed(Error::NotSeeded);
+
+    fungible_token_a.mint(USERS[1], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[1], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[1], pair.actor_id(), INIT_LIQ);
+    fungible_token_b.approve(USERS[1], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[1], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[1])
+        .succeed((USERS[1], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+    pair.state().seeder().eq(None);
+
+    // Only the Factory contract can stop awaiting the initial liquidity.
+
+    let mut pair = Pair::initialize_with_seeder(
+        &system,
+        actor_pair,
+        USERS[2],
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        Some(USERS[1]),
+        5,
+    )
+    .succeed();
+
+    pair.query(USERS[1], InnerAction::ClearSeeder)
+        .failed(Error::AccessRestricted);
+    pair.query(USERS[2], InnerAction::ClearSeeder)
+        .succeed(Event::SeederCleared);
+    pair.state().seeder().eq(None);
+    pair.sync().succeed((0, 0));
+}
+
+#[test]
+fn queries() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    pair.query(
+        USERS[0],
+        InnerAction::GetAmountOut {
+            swap_kind: SwapKind::AForB,
+            amount_in: SWAP_AMOUNT,
+        },
+    )
+    .failed(Error::InsufficientLiquidity);
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ / 2);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ / 2), (0, 0), USERS[0])
+        .succeed((
+            USERS[0],
+            (INIT_LIQ, INIT_LIQ / 2),
+            (U256::from(INIT_LIQ) * U256::from(INIT_LIQ / 2))
+                .integer_sqrt()
+                .low_u128()
+                - MINIMUM_LIQUIDITY as u128,
+        ));
+
+    let pair_reserve = (INIT_LIQ, INIT_LIQ / 2);
+    let last_block_ts = system.block_timestamp();
+
+    pair.query(USERS[1], InnerAction::GetReserves)
+        .succeed(Event::Reserves {
+            reserve_a: pair_reserve.0,
+            reserve_b: pair_reserve.1,
+            last_block_ts,
+        });
+
+    for swap_kind in [SwapKind::AForB, SwapKind::BForA] {
+        let out_amount = pair
+            .state()
+            .calculate_out_amount(swap_kind, SWAP_AMOUNT)
+            .0
+            .unwrap();
+        let in_amount = pair
+            .state()
+            .calculate_in_amount(swap_kind, SWAP_AMOUNT)
+            .0
+            .unwrap();
+
+        pair.query(
+            USERS[1],
+            InnerAction::GetAmountOut {
+                swap_kind,
+                amount_in: SWAP_AMOUNT,
+            },
+        )
+        .succeed(Event::AmountOut(out_amount));
+        pair.query(
+            USERS[1],
+            InnerAction::GetAmountIn {
+                swap_kind,
+                amount_out: SWAP_AMOUNT,
+            },
+        )
+        .succeed(Event::AmountIn(in_amount));
+    }
+
+    pair.query(
+        USERS[1],
+        InnerAction::GetAmountIn {
+            swap_kind: SwapKind::AForB,
+            amount_out: pair_reserve.1,
+        },
+    )
+    .failed(Error::Overflow);
+
+    let balance = pair.state().balance_of(USERS[0]).0;
+
+    pair.query(USERS[1], InnerAction::GetLpBalance(USERS[0].into()))
+        .succeed(Event::LpBalance {
+            actor: USERS[0].into(),
+            balance: balance.into(),
+        });
+    pair.query(USERS[1], InnerAction::GetLpBalance(USERS[1].into()))
+        .succeed(Event::LpBalance {
+            actor: USERS[1].into(),
+            balance: 0.into(),
+        });
+
+    // Cumulative prices are accumulated up to the current block without
+    // updating the state.
+
+    system.spend_blocks(SPENT_BLOCKS);
+    pair.query(USERS[1], InnerAction::GetCumulativePrices)
+        .succeed(Event::CumulativePrices {
+            price_a: utils::calculate_cp(pair_reserve),
+            price_b: utils::calculate_cp((pair_reserve.1, pair_reserve.0)),
+            block_ts: last_block_ts + SPENT_BLOCKS as u64 * 1000,
+        });
+    pair.state().price().eq((U256::zero(), U256::zero()));
+    pair.state().reserve().eq(pair_reserve);
+}
+
+#[test]
+fn pair_lookup() {
+    use dex_factory_io::{sort_pair, Event as FactoryEvent, InnerAction as FactoryInnerAction};
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let fungible_token_c = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+    let actor_pair_c = sort_pair((fungible_token_c.actor_id(), fungible_token_b.actor_id()));
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 4).succeed();
+
+    factory
+        .query(FactoryInnerAction::GetPair(
+            actor_pair.0,
+            actor_pair.1,
+            DEFAULT_FEE_TIER,
+        ))
+        .succeed(FactoryEvent::Pair {
+            token_pair: actor_pair,
+            fee_tier: DEFAULT_FEE_TIER,
+            pair_actor: None,
+        });
+    factory
+        .query(FactoryInnerAction::GetPairCount)
+        .succeed(FactoryEvent::PairCount(0));
+    factory
+        .query(FactoryInnerAction::GetPairs {
+            offset: 0,
+            limit: 10,
+        })
+        .succeed(FactoryEvent::Pairs(vec![]));
+
+    let pairs: Vec<_> = [
+        (actor_pair, DEFAULT_FEE_TIER),
+        (actor_pair_c, DEFAULT_FEE_TIER),
+        (actor_pair, 100),
+    ]
+    .into_iter()
+    .zip(1..)
+    .map(|((token_pair, fee_tier), pair_number)| {
+        let pair_actor: ActorId = factory
+            .create_pair_with_fee_tier(token_pair, fee_tier)
+            .succeed((token_pair, fee_tier, pair_number))
+            .into();
+
+        ((token_pair.0, token_pair.1, fee_tier), pair_actor)
+    })
+    .collect();
+
+    // The order of tokens doesn't matter.
+    factory
+        .query(FactoryInnerAction::GetPair(actor_pair.1, actor_pair.0, 100))
+        .succeed(FactoryEvent::Pair {
+            token_pair: actor_pair,
+            fee_tier: 100,
+            pair_actor: Some(pairs[2].1),
+        });
+    factory
+        .query(FactoryInnerAction::GetPair(actor_pair.0, actor_pair.1, 5))
+        .succeed(FactoryEvent::Pair {
+            token_pair: actor_pair,
+            fee_tier: 5,
+            pair_actor: None,
+        });
+    factory
+        .query(FactoryInnerAction::GetPairCount)
+        .succeed(FactoryEvent::PairCount(3));
+
+    // Pagination
+
+    factory
+        .query(FactoryInnerAction::GetPairs {
+            offset: 0,
+            limit: u32::MAX,
+        })
+        .succeed(FactoryEvent::Pairs(pairs.clone()));
+    factory
+        .query(FactoryInnerAction::GetPairs {
+            offset: 1,
+            limit: 1,
+        })
+        .succeed(FactoryEvent::Pairs(pairs[1..2].to_vec()));
+    factory
+        .query(FactoryInnerAction::GetPairs {
+            offset: 2,
+            limit: 10,
+        })
+        .succeed(FactoryEvent::Pairs(pairs[2..].to_vec()));
+    factory
+        .query(FactoryInnerAction::GetPairs {
+            offset: 3,
+            limit: 10,
+        })
+        .succeed(FactoryEvent::Pairs(vec![]));
+}
+
+#[test]
+fn farm() {
+    use dex_farm_io::Error as FarmError;
+
+    const REWARD_PER_SECOND: u128 = 1000;
+    const STAKE: u128 = 1000;
+    const SPENT_SECONDS: u32 = 10;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let mut reward_token = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 4).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+    let foreign_pair = Pair::initialize(
+        &system,
+        actor_pair,
+        factory.actor_id(),
+        DEFAULT_FEE_TIER,
+        ActorId::zero(),
+        5,
+    )
+    .succeed();
+
+    for user in [USERS[0], USERS[1]] {
+        fungible_token_a.mint(user, INIT_AMOUNT);
+        fungible_token_b.mint(user, INIT_AMOUNT);
+        fungible_token_a.approve(user, pair.actor_id(), INIT_LIQ);
+        fungible_token_b.approve(user, pair.actor_id(), INIT_LIQ);
+    }
+
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+    pair.add_liquidity(USERS[1], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[1])
+        .succeed((USERS[1], (INIT_LIQ, INIT_LIQ), INIT_LIQ));
+
+    let mut farm = Farm::initialize(
+        &system,
+        USERS[2],
+        factory.actor_id(),
+        reward_token.actor_id(),
+        REWARD_PER_SECOND,
+        6,
+    )
+    .succeed();
+
+    reward_token.mint(farm.actor_id(), u64::MAX.into());
+
+    // Pools
+
+    farm.add_pool(USERS[0], pair.actor_id(), 1)
+        .failed(FarmError::AccessRestricted);
+    farm.add_pool(USERS[2], foreign_pair.actor_id(), 1)
+        .failed(FarmError::PairVerificationFailed);
+    farm.add_pool(USERS[2], pair.actor_id(), 1).succeed(0);
+    farm.add_pool(USERS[2], pair.actor_id(), 1)
+        .failed(FarmError::PoolExists);
+
+    // Staking
+
+    farm.deposit(USERS[0], 0, 0)
+        .failed(FarmError::InsufficientAmount);
+    farm.deposit(USERS[0], 1, STAKE)
+        .failed(FarmError::PoolNotFound);
+
+    pair.approve(USERS[0], farm.actor_id(), STAKE).succeed(());
+    farm.deposit(USERS[0], 0, STAKE)
+        .succeed((0, USERS[0], STAKE));
+    pair.state().balance_of(USERS[0]).eq(CLEAN_INIT_LIQ - STAKE);
+    pair.state().balance_of(farm.actor_id()).eq(STAKE);
+    farm.state().stake(0, USERS[0]).eq(STAKE);
+
+    system.spend_blocks(SPENT_SECONDS);
+
+    let reward = REWARD_PER_SECOND * SPENT_SECONDS as u128;
+
+    farm.state()
+        .pending_reward(0, USERS[0], system.block_timestamp())
+        .eq(reward);
+
+    pair.approve(USERS[1], farm.actor_id(), 3 * STAKE)
+        .succeed(());
+    farm.deposit(USERS[1], 0, 3 * STAKE)
+        .succeed((0, USERS[1], 3 * STAKE));
+
+    // Rewards are split in proportion to stakes.
+
+    system.spend_blocks(SPENT_SECONDS);
+    farm.state()
+        .pending_reward(0, USERS[0], system.block_timestamp())
+        .eq(reward + reward / 4);
+    farm.state()
+        .pending_reward(0, USERS[1], system.block_timestamp())
+        .eq(reward * 3 / 4);
+
+    farm.claim(USERS[0], 0)
+        .succeed((0, USERS[0], reward + reward / 4));
+    reward_token.balance(USERS[0]).contains(reward + reward / 4);
+    farm.claim(USERS[0], 0).failed(FarmError::NothingToClaim);
+    farm.claim(USERS[2], 0).failed(FarmError::NothingToClaim);
+
+    // The reward stays after withdrawing.
+
+    farm.withdraw(USERS[1], 0, 3 * STAKE + 1)
+        .failed(FarmError::InsufficientStake);
+    farm.withdraw(USERS[1], 0, 3 * STAKE)
+        .succeed((0, USERS[1], 3 * STAKE));
+    pair.state().balance_of(USERS[1]).eq(INIT_LIQ);
+    farm.state().stake(0, USERS[1]).eq(0);
+    farm.state()
+        .pending_reward(0, USERS[1], system.block_timestamp())
+        .eq(reward * 3 / 4);
+
+    // Allocation points
+
+    let pair_actor_100 = factory
+        .create_pair_with_fee_tier(actor_pair, 100)
+        .succeed((actor_pair, 100, 2))
+        .into();
+
+    farm.add_pool(USERS[2], pair_actor_100, 3).succeed(1);
+    farm.state().total_alloc_point().eq(4);
+
+    system.spend_blocks(SPENT_SECONDS);
+    farm.state()
+        .pending_reward(0, USERS[0], system.block_timestamp())
+        .eq(reward / 4);
+
+    farm.set_pool(USERS[0], 1, 0)
+        .failed(FarmError::AccessRestricted);
+    farm.set_pool(USERS[2], 1, 0).succeed((1, 0));
+    farm.set_reward_per_second(USERS[0], 2 * REWARD_PER_SECOND)
+        .failed(FarmError::AccessRestricted);
+    farm.set_reward_per_second(USERS[2], 2 * REWARD_PER_SECOND)
+        .succeed(2 * REWARD_PER_SECOND);
+
+    system.spend_blocks(SPENT_SECONDS);
+    farm.state()
+        .pending_reward(0, USERS[0], system.block_timestamp())
+        .eq(reward / 4 + 2 * reward);
+
+    farm.claim(USERS[1], 0)
+        .succeed((0, USERS[1], reward * 3 / 4));
+    reward_token.balance(USERS[1]).contains(reward * 3 / 4);
+}
+
+#[test]
+fn lp_positions() {
+    const SWAP_AMOUNT: u128 = 100000;
+    const TRANSFER_AMOUNT: u128 = 1000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ + SWAP_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    pair.state().lp_position(USERS[0]).eq(LpPosition {
+        liquidity: CLEAN_INIT_LIQ.into(),
+        underlying: (CLEAN_INIT_LIQ, CLEAN_INIT_LIQ),
+        fee_earnings: Some((0, 0)),
+    });
+
+    // Fees
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+
+    let pair_reserve = (INIT_LIQ + SWAP_AMOUNT, INIT_LIQ - out_amount);
+    let underlying = (
+        CLEAN_INIT_LIQ * pair_reserve.0 / INIT_LIQ,
+        CLEAN_INIT_LIQ * pair_reserve.1 / INIT_LIQ,
+    );
+    let U256PairTuple(reserve) = pair_reserve.into();
+    let root_k = (reserve.0 * reserve.1).integer_sqrt();
+    // The total supply didn't change, so only `root_k` grew.
+    let fee =
+        |amount: u128| amount - (U256::from(amount) * U256::from(INIT_LIQ) / root_k).low_u128();
+    let fee_earnings = (fee(underlying.0), fee(underlying.1));
+
+    assert!(fee_earnings.0 > 0 && fee_earnings.1 > 0);
+    pair.state().lp_position(USERS[0]).eq(LpPosition {
+        liquidity: CLEAN_INIT_LIQ.into(),
+        underlying,
+        fee_earnings: Some(fee_earnings),
+    });
+
+    // Transferred liquidity tokens
+
+    pair.transfer(USERS[0], TRANSFER_AMOUNT, USERS[1]).succeed((
+        USERS[0],
+        USERS[1],
+        TRANSFER_AMOUNT,
+    ));
+    pair.state().lp_position(USERS[1]).eq(LpPosition {
+        liquidity: TRANSFER_AMOUNT.into(),
+        underlying: (
+            TRANSFER_AMOUNT * pair_reserve.0 / INIT_LIQ,
+            TRANSFER_AMOUNT * pair_reserve.1 / INIT_LIQ,
+        ),
+        fee_earnings: None,
+    });
+    pair.state().lp_position(USERS[2]).eq(LpPosition::default());
+}
+
+#[test]
+fn trading_stats() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ + SWAP_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ + SWAP_AMOUNT);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    pair.state().stats().eq(TradingStats::default());
+
+    // The 1st hour
+
+    let first_swap_ts = system.block_timestamp();
+    let first_out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, first_out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+
+    let fee = SWAP_AMOUNT * DEFAULT_FEE_TIER as u128 / FEE_DENOMINATOR as u128;
+    let first_stats = TradingStats {
+        volume_in: (SWAP_AMOUNT.into(), U256::zero()),
+        volume_out: (U256::zero(), first_out_amount.into()),
+        fees: (fee.into(), U256::zero()),
+        swaps: 1,
+    };
+
+    pair.state().stats().eq(first_stats);
+    pair.state().stats_since(first_swap_ts).eq(first_stats);
+
+    // The 2nd hour
+
+    system.spend_blocks((STATS_BUCKET_DURATION / 1000) as u32);
+
+    let second_swap_ts = system.block_timestamp();
+    let second_out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::BForA, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::BForA)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, second_out_amount),
+            USERS[0],
+            SwapKind::BForA,
+        ));
+
+    let second_stats = TradingStats {
+        volume_in: (U256::zero(), SWAP_AMOUNT.into()),
+        volume_out: (second_out_amount.into(), U256::zero()),
+        fees: (U256::zero(), fee.into()),
+        swaps: 1,
+    };
+    let mut total_stats = first_stats;
+
+    total_stats.add(&second_stats);
+
+    pair.state().stats().eq(total_stats);
+    pair.state().stats_since(first_swap_ts).eq(total_stats);
+    pair.state().stats_since(second_swap_ts).eq(second_stats);
+}
+
+#[test]
+fn price_impact() {
+    const MAX_IMPACT: u16 = 100;
+    const SLIPPAGE: u16 = 50;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[2], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    pair.state()
+        .price_impact(SwapKind::AForB, 1)
+        .eq(Err(Error::InsufficientLiquidity));
+    pair.state()
+        .max_in_amount(SwapKind::AForB, MAX_IMPACT)
+        .eq(Err(Error::InsufficientLiquidity));
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_AMOUNT);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    pair.state()
+        .price_impact(SwapKind::AForB, 0)
+        .eq(Err(Error::InsufficientAmount));
+
+    // 500000 * 10000 * 100 // (9900 * 9970)
+    let max_in_amount = 5065;
+
+    pair.state()
+        .max_in_amount(SwapKind::AForB, MAX_IMPACT)
+        .eq(Ok(max_in_amount));
+    pair.state()
+        .price_impact(SwapKind::AForB, max_in_amount)
+        .eq(Ok(MAX_IMPACT));
+    pair.state()
+        .price_impact(SwapKind::AForB, max_in_amount + 1)
+        .eq(Ok(MAX_IMPACT + 1));
+    pair.state()
+        .max_in_amount(SwapKind::AForB, BPS_DENOMINATOR)
+        .eq(Ok(u128::MAX));
+
+    // Slippage
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, max_in_amount)
+        .0
+        .unwrap();
+    let amount_out_min =
+        out_amount * (BPS_DENOMINATOR - SLIPPAGE) as u128 / BPS_DENOMINATOR as u128;
+
+    pair.state()
+        .amount_out_min(SwapKind::AForB, max_in_amount, SLIPPAGE)
+        .eq(Ok(amount_out_min));
+    pair.swap_exact_tokens_for_tokens(
+        USERS[0],
+        (max_in_amount, amount_out_min),
+        USERS[0],
+        SwapKind::AForB,
+    )
+    .succeed((
+        USERS[0],
+        (max_in_amount, out_amount),
+        USERS[0],
+        SwapKind::AForB,
+    ));
+
+    let in_amount = pair
+        .state()
+        .calculate_in_amount(SwapKind::BForA, out_amount)
+        .0
+        .unwrap();
+    let amount_in_max =
+        (in_amount * (BPS_DENOMINATOR + SLIPPAGE) as u128 + BPS_DENOMINATOR as u128 - 1)
+            / BPS_DENOMINATOR as u128;
+
+    pair.state()
+        .amount_in_max(SwapKind::BForA, out_amount, SLIPPAGE)
+        .eq(Ok(amount_in_max));
+    pair.swap_tokens_for_exact_tokens(
+        USERS[0],
+        (out_amount, amount_in_max),
+        USERS[0],
+        SwapKind::BForA,
+    )
+    .succeed((USERS[0], (in_amount, out_amount), USERS[0], SwapKind::BForA));
+}
+
+#[test]
+fn tx_config() {
+    use dex_factory_io::{AdminAction, Error as FactoryError, Event as FactoryEvent};
+
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    factory.state().pair_tx_config().eq(TxConfig::default());
+    pair.state().tx_config().eq(TxConfig::default());
+
+    // Broadcasting from the factory
+
+    let tx_config = TxConfig {
+        expiry: MIN_TX_EXPIRY,
+        max_cached_txs: 1,
+    };
+
+    pair.update_tx_config(USERS[0], tx_config)
+        .failed(Error::AccessRestricted);
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::PairTxConfig(TxConfig {
+                expiry: MIN_TX_EXPIRY - 1,
+                ..tx_config
+            }),
+        )
+        .failed(FactoryError::InvalidPairTxConfig);
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::PairTxConfig(TxConfig {
+                max_cached_txs: 0,
+                ..tx_config
+            }),
+        )
+        .failed(FactoryError::InvalidPairTxConfig);
+    factory
+        .queue_and_execute(USERS[0], AdminAction::PairTxConfig(tx_config))
+        .succeed(FactoryEvent::PairTxConfigSet {
+            tx_config,
+            unnotified_pairs: vec![],
+        });
+
+    factory.state().pair_tx_config().eq(tx_config);
+    pair.state().tx_config().eq(tx_config);
+
+    // Finished transactions aren't kept in the cache
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ + SWAP_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+
+    pair.state().cached_tx(USERS[0]).eq(None);
+    pair.abandon(USERS[1], USERS[0]).failed(Error::TxNotFound);
+
+    // Only the contract itself can complete transactions
+
+    pair.complete(USERS[1], USERS[0])
+        .failed(Error::AccessRestricted);
+}
+
+#[test]
+fn tx_expiry() {
+    const SWAP_AMOUNT: u128 = 100000;
+    // A block takes 1 second in gtest.
+    const EXPIRY_BLOCKS: u32 = (MIN_TX_EXPIRY / 1000) as u32 + 1;
+
+    let system = utils::initialize_system();
+
+    let mut token_a = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let mut token_b = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let actor_pair = (token_a.actor_id(), token_b.actor_id());
+    let mut pair =
+        Pair::initialize(&system, actor_pair, USERS[1], DEFAULT_FEE_TIER, USERS[2], 3).succeed();
+    let tx_config = TxConfig {
+        expiry: MIN_TX_EXPIRY,
+        max_cached_txs: 1,
+    };
+
+    pair.update_tx_config(USERS[1], tx_config)
+        .succeed(tx_config);
+
+    token_a.mint(USERS[0], INIT_LIQ + 3 * SWAP_AMOUNT);
+    token_a.mint(USERS[1], SWAP_AMOUNT);
+    token_b.mint(USERS[0], INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    let swap = InnerAction::SwapExactTokensForTokens {
+        amount_in: SWAP_AMOUNT,
+        amount_out_min: 0,
+        to: USERS[0].into(),
+        deadline: DEADLINE,
+        swap_kind: SwapKind::AForB,
+    };
+
+    // An expired transaction that hasn't taken tokens from the contract can
+    // only be abandoned
+
+    token_a.respond(Response::Silence);
+    assert!(!pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .result
+        .main_failed());
+    assert!(pair
+        .state()
+        .cached_tx(USERS[0])
+        .0
+        .is_some_and(|cached_tx| !cached_tx.is_settling));
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+
+    // The stale lock is taken over, but the expired transaction still
+    // occupies the cache.
+    pair.swap_exact_tokens_for_tokens(USERS[1], (SWAP_AMOUNT, 0), USERS[1], SwapKind::AForB)
+        .failed(Error::TxCacheFull);
+    pair.retry(USERS[0], swap).failed(Error::TxExpired);
+
+    token_a.respond(Response::Execute);
+    pair.abandon(USERS[1], USERS[0]).succeed(Event::Abandoned {
+        actor: USERS[0].into(),
+        depositor: USERS[0].into(),
+        refund: (SWAP_AMOUNT, 0),
+    });
+    pair.state().cached_tx(USERS[0]).eq(None);
+    pair.state().reserve().eq((INIT_LIQ, INIT_LIQ));
+    assert_eq!(token_a.balance(USERS[0]), 3 * SWAP_AMOUNT);
+
+    // An expired transaction that has taken tokens from the contract is
+    // resumed by abandoning it...
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    token_b.respond(Response::Silence);
+    assert!(!pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .result
+        .main_failed());
+    assert!(pair
+        .state()
+        .cached_tx(USERS[0])
+        .0
+        .is_some_and(|cached_tx| cached_tx.is_settling));
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_b.respond(Response::Execute);
+
+    pair.abandon(USERS[1], USERS[0]).succeed(Event::Swap {
+        sender: USERS[0].into(),
+        in_amount: SWAP_AMOUNT,
+        out_amount,
+        to: USERS[0].into(),
+        kind: SwapKind::AForB,
+    });
+    pair.state().cached_tx(USERS[0]).eq(None);
+
+    let reserve = (INIT_LIQ + SWAP_AMOUNT, INIT_LIQ - out_amount);
+
+    pair.state().reserve().eq(reserve);
+    assert_eq!(token_b.balance(USERS[0]), out_amount);
+
+    // ...or retrying it.
+
+    let out_amount_2 = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    token_b.respond(Response::Silence);
+    assert!(!pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .result
+        .main_failed());
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_b.respond(Response::Execute);
+
+    pair.retry(USERS[0], swap).succeed(Event::Swap {
+        sender: USERS[0].into(),
+        in_amount: SWAP_AMOUNT,
+        out_amount: out_amount_2,
+        to: USERS[0].into(),
+        kind: SwapKind::AForB,
+    });
+    pair.state().cached_tx(USERS[0]).eq(None);
+    pair.state()
+        .reserve()
+        .eq((reserve.0 + SWAP_AMOUNT, reserve.1 - out_amount_2));
+    assert_eq!(token_a.balance(USERS[0]), SWAP_AMOUNT);
+    assert_eq!(token_b.balance(USERS[0]), out_amount + out_amount_2);
+}
+
+#[test]
+fn owed_tokens() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    pair.state().owed(USERS[0], actor_pair.0).eq(0);
+    pair.claim(USERS[0], actor_pair.0)
+        .failed(Error::NothingToClaim);
+    pair.state().cached_tx(USERS[0]).eq(None);
+
+    // Refunds that a token rejects or panics on are owed
+
+    let mut token_a = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let mut token_b = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let actor_pair = (token_a.actor_id(), token_b.actor_id());
+    let mut pair = Pair::initialize(
+        &system,
+        actor_pair,
+        USERS[1],
+        DEFAULT_FEE_TIER,
+        USERS[2],
+        10,
+    )
+    .succeed();
+
+    token_a.mint(USERS[0], INIT_LIQ + 2 * SWAP_AMOUNT);
+    token_b.mint(USERS[0], INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    token_b.respond(Response::Reject);
+
+    for response in [Response::Reject, Response::Panic] {
+        // The input transfer succeeds, but the output one & the refund fail.
+        token_a.respond(response);
+        token_a.queue(&[Response::Execute]);
+
+        pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+            .failed(Error::TransferFailed);
+    }
+
+    pair.state()
+        .owed(USERS[0], actor_pair.0)
+        .eq(2 * SWAP_AMOUNT);
+    pair.state().reserve().eq((INIT_LIQ, INIT_LIQ));
+    assert_eq!(token_a.balance(USERS[0]), 0);
+
+    // Owed tokens are excluded from the reserve & the excess
+
+    token_a.respond(Response::Execute);
+    token_b.respond(Response::Execute);
+
+    pair.sync().succeed((INIT_LIQ, INIT_LIQ));
+    pair.query(USERS[1], InnerAction::Skim(USERS[1].into()))
+        .succeed(Event::Skim {
+            amount_a: 0,
+            amount_b: 0,
+            to: USERS[1].into(),
+        });
+    assert_eq!(token_a.balance(USERS[1]), 0);
+
+    pair.claim(USERS[0], actor_pair.0)
+        .succeed((USERS[0], actor_pair.0, 2 * SWAP_AMOUNT));
+    pair.state().owed(USERS[0], actor_pair.0).eq(0);
+    assert_eq!(token_a.balance(USERS[0]), 2 * SWAP_AMOUNT);
+}
+
+#[test]
+fn lock() {
+    let system = utils::initialize_system();
+
+    let fungible_token_b = FungibleToken::initialize(&system);
+    let silent_token = SilentProgram::initialize(&system);
+    let actor_pair = (silent_token.actor_id(), fungible_token_b.actor_id());
+    let mut pair =
+        Pair::initialize(&system, actor_pair, USERS[1], DEFAULT_FEE_TIER, USERS[2], 3).succeed();
+
+    pair.state().lock().eq(None);
+
+    // The token contract doesn't reply, so the addition stays in progress &
+    // holds the lock
+
+    assert!(!pair
+        .add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .result
+        .main_failed());
+    assert_eq!(
+        pair.state().lock().0.map(|lock| lock.actor),
+        Some(USERS[0].into())
+    );
+
+    pair.swap_exact_tokens_for_tokens(USERS[1], (INIT_LIQ, 0), USERS[1], SwapKind::AForB)
+        .failed(Error::Busy);
+    pair.add_liquidity(USERS[1], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[1])
+        .failed(Error::Busy);
+    pair.sync().failed(Error::Busy);
+
+    // Actions that don't change the reserve aren't blocked
+
+    pair.query(USERS[1], InnerAction::GetReserves)
+        .succeed(Event::Reserves {
+            reserve_a: 0,
+            reserve_b: 0,
+            last_block_ts: 0,
+        });
+}
+
+#[test]
+fn commit_reveal_swap() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    fungible_token_a.mint(USERS[1], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[1], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[1], pair.actor_id(), INIT_AMOUNT);
+    fungible_token_b.approve(USERS[1], pair.actor_id(), INIT_AMOUNT);
+
+    // Committing
+
+    let reveal = SwapReveal {
+        swap_kind: SwapKind::AForB,
+        amount_in: SWAP_AMOUNT,
+        amount_out_min: 0,
+        to: USERS[2].into(),
+        salt: [1; 32],
+    };
+    let hash = reveal.hash();
+    let deposit = (SWAP_AMOUNT * 2, SWAP_AMOUNT);
+
+    pair.commit_swap(USERS[1], hash, (0, 0))
+        .failed(Error::InsufficientAmount);
+    pair.commit_swap(USERS[1], hash, deposit)
+        .succeed(Event::SwapCommitted {
+            actor: USERS[1].into(),
+            hash,
+            deposit,
+        });
+    pair.commit_swap(USERS[1], hash, deposit)
+        .failed(Error::CommitmentExists);
+
+    assert_eq!(
+        pair.state()
+            .commitment(USERS[1])
+            .0
+            .map(|commitment| (commitment.hash, commitment.deposit)),
+        Some((hash, deposit))
+    );
+
+    // The escrow isn't a part of the reserve
+
+    pair.sync().succeed((INIT_LIQ, INIT_LIQ));
+
+    // Revealing
+
+    pair.reveal_swap(USERS[1], reveal)
+        .failed(Error::RevealTooEarly);
+
+    system.spend_blocks(SPENT_BLOCKS);
+
+    pair.reveal_swap(
+        USERS[1],
+        SwapReveal {
+            salt: [2; 32],
+            ..reveal
+        },
+    )
+    .failed(Error::InvalidReveal);
+    pair.reveal_swap(USERS[2], reveal)
+        .failed(Error::CommitmentNotFound);
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.reveal_swap(USERS[1], reveal).succeed(Event::Swap {
+        sender: USERS[1].into(),
+        in_amount: SWAP_AMOUNT,
+        out_amount,
+        to: USERS[2].into(),
+        kind: SwapKind::AForB,
+    });
+
+    pair.state().commitment(USERS[1]).eq(None);
+    pair.state()
+        .reserve()
+        .eq((INIT_LIQ + SWAP_AMOUNT, INIT_LIQ - out_amount));
+    fungible_token_a
+        .balance(USERS[1])
+        .contains(INIT_AMOUNT - SWAP_AMOUNT);
+    fungible_token_b.balance(USERS[1]).contains(INIT_AMOUNT);
+    fungible_token_b.balance(USERS[2]).contains(out_amount);
+
+    // Refunding
+
+    pair.commit_swap(USERS[1], hash, deposit)
+        .succeed(Event::SwapCommitted {
+            actor: USERS[1].into(),
+            hash,
+            deposit,
+        });
+    pair.refund_swap(USERS[2], USERS[1])
+        .failed(Error::RevealWindowOpen);
+
+    system.spend_blocks(REVEAL_WINDOW + 1);
+
+    pair.reveal_swap(USERS[1], reveal)
+        .failed(Error::RevealWindowClosed);
+    pair.refund_swap(USERS[2], USERS[1])
+        .succeed(Event::SwapRefunded {
+            actor: USERS[1].into(),
+            refund: deposit,
+        });
+
+    pair.state().commitment(USERS[1]).eq(None);
+    fungible_token_a
+        .balance(USERS[1])
+        .contains(INIT_AMOUNT - SWAP_AMOUNT);
+    fungible_token_b.balance(USERS[1]).contains(INIT_AMOUNT);
+}
+
+#[test]
+fn batch_auction() {
+    const SWAP_AMOUNT: u128 = 100000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    fungible_token_a.mint(USERS[1], SWAP_AMOUNT);
+    fungible_token_a.approve(USERS[1], pair.actor_id(), SWAP_AMOUNT);
+    fungible_token_b.mint(USERS[2], SWAP_AMOUNT);
+    fungible_token_b.approve(USERS[2], pair.actor_id(), SWAP_AMOUNT);
+
+    // Submitting
+
+    let order_a = Order {
+        swap_kind: SwapKind::AForB,
+        amount_in: SWAP_AMOUNT,
+        amount_out_min: 0,
+        to: USERS[1].into(),
+    };
+    let order_b = Order {
+        swap_kind: SwapKind::BForA,
+        to: USERS[2].into(),
+        ..order_a
+    };
+
+    pair.submit_order(
+        USERS[1],
+        Order {
+            amount_in: 0,
+            ..order_a
+        },
+    )
+    .failed(Error::InsufficientAmount);
+    pair.submit_order(USERS[1], order_a)
+        .succeed((USERS[1], 0, order_a));
+    pair.submit_order(USERS[1], order_a)
+        .failed(Error::OrderExists);
+    pair.submit_order(USERS[2], order_b)
+        .succeed((USERS[2], 0, order_b));
+
+    pair.settle_batch(USERS[0]).failed(Error::BatchNotEnded);
+
+    // The escrow isn't a part of the reserve
+
+    pair.sync().succeed((INIT_LIQ, INIT_LIQ));
+
+    // Settling with delayed `InnerAction::SettleBatch`
+
+    system.spend_blocks(BATCH_EPOCH);
+
+    // Opposite orders of the same value at the current price are netted
+    // without touching the reserve.
+    pair.state().batch().eq(None);
+    pair.state().reserve().eq((INIT_LIQ, INIT_LIQ));
+    fungible_token_a.balance(USERS[1]).contains(0);
+    fungible_token_b.balance(USERS[1]).contains(SWAP_AMOUNT);
+    fungible_token_a.balance(USERS[2]).contains(SWAP_AMOUNT);
+    fungible_token_b.balance(USERS[2]).contains(0);
+
+    pair.settle_batch(USERS[0]).failed(Error::BatchNotFound);
+}
+
+#[test]
+fn batch_settlement_chunks() {
+    const SWAP_AMOUNT: u128 = 10000;
+    // A block takes 1 second in gtest.
+    const EXPIRY_BLOCKS: u32 = (MIN_TX_EXPIRY / 1000) as u32 + 1;
+
+    let system = utils::initialize_system();
+
+    let mut token_a = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let mut token_b = MockToken::initialize(&system, TokenStandard::ShardedFt);
+    let actor_pair = (token_a.actor_id(), token_b.actor_id());
+    let mut pair =
+        Pair::initialize(&system, actor_pair, USERS[1], DEFAULT_FEE_TIER, USERS[2], 3).succeed();
+    let tx_config = TxConfig {
+        expiry: MIN_TX_EXPIRY,
+        ..Default::default()
+    };
+
+    pair.update_tx_config(USERS[1], tx_config)
+        .succeed(tx_config);
+
+    token_a.mint(USERS[0], INIT_LIQ);
+    token_b.mint(USERS[0], INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    // More orders than one settlement message can transfer
+    let traders: Vec<u64> = (0..=u64::from(MAX_SETTLED_ORDERS))
+        .map(|i| 100 + i)
+        .collect();
+    let orders: Vec<_> = traders
+        .iter()
+        .map(|trader| Order {
+            swap_kind: SwapKind::AForB,
+            amount_in: SWAP_AMOUNT,
+            amount_out_min: 0,
+            to: (*trader).into(),
+        })
+        .collect();
+
+    for (trader, order) in traders.iter().zip(&orders) {
+        token_a.mint(*trader, SWAP_AMOUNT);
+        pair.submit_order(*trader, *order)
+            .succeed((*trader, 0, *order));
+    }
+
+    let Clearing { payouts, .. } =
+        hidden::clear_batch(&orders, (INIT_LIQ, INIT_LIQ), DEFAULT_FEE_TIER);
+
+    // The delayed settlement is interrupted on its first payout.
+
+    token_b.respond(Response::Silence);
+    system.spend_blocks(BATCH_EPOCH);
+
+    assert!(pair
+        .state()
+        .batch()
+        .0
+        .and_then(|batch| batch.clearing)
+        .is_some_and(|clearing| clearing.paid == 0));
+    assert!(pair
+        .state()
+        .cached_tx(pair.actor_id())
+        .0
+        .is_some_and(|cached_tx| cached_tx.is_settling));
+
+    // Only the interrupted message can continue from its current order.
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+
+    pair.settle_batch(USERS[0]).failed(Error::BatchSettling);
+
+    // Resuming it settles the first chunk & sends the continuation that
+    // settles the rest.
+
+    token_b.respond(Response::Execute);
+    pair.abandon(USERS[0], pair.actor_id())
+        .succeed(Event::BatchPartiallySettled {
+            id: 0,
+            paid: MAX_SETTLED_ORDERS,
+        });
+
+    pair.state().batch().eq(None);
+    pair.state().cached_tx(pair.actor_id()).eq(None);
+    assert_eq!(token_b.transfers(), traders.len() as u32 + 1);
+
+    for (trader, payout) in traders.iter().zip(payouts) {
+        assert_eq!(token_b.balance(*trader), payout);
+    }
+
+    // An interrupted continuation can only be resumed as well, so it doesn't
+    // resend its current payout.
+
+    for (trader, order) in traders.iter().zip(&orders) {
+        token_a.mint(*trader, SWAP_AMOUNT);
+        pair.submit_order(*trader, *order)
+            .succeed((*trader, 1, *order));
+    }
+
+    let Clearing {
+        payouts,
+        swap_kind,
+        swap,
+        ..
+    } = hidden::clear_batch(&orders, pair.state().reserve().0, DEFAULT_FEE_TIER);
+    let balances: Vec<_> = traders
+        .iter()
+        .map(|trader| token_b.balance(*trader))
+        .collect();
+    let transfers = token_b.transfers();
+
+    token_b.queue(&[Response::Execute; MAX_SETTLED_ORDERS as usize]);
+    token_b.respond(Response::Silence);
+    system.spend_blocks(BATCH_EPOCH);
+
+    assert!(pair
+        .state()
+        .batch()
+        .0
+        .and_then(|batch| batch.clearing)
+        .is_some_and(|clearing| clearing.paid == MAX_SETTLED_ORDERS));
+    assert!(pair
+        .state()
+        .cached_tx(pair.actor_id())
+        .0
+        .is_some_and(|cached_tx| cached_tx.is_settling));
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+
+    pair.settle_batch(USERS[0]).failed(Error::BatchSettling);
+
+    token_b.respond(Response::Execute);
+    pair.abandon(USERS[0], pair.actor_id())
+        .succeed(Event::BatchSettled {
+            id: 1,
+            filled: payouts.iter().filter(|payout| **payout != 0).count() as u32,
+            swap_kind,
+            swap,
+        });
+
+    pair.state().batch().eq(None);
+    pair.state().cached_tx(pair.actor_id()).eq(None);
+    assert_eq!(token_b.transfers(), transfers + traders.len() as u32);
+
+    for ((trader, payout), balance) in traders.iter().zip(payouts).zip(balances) {
+        assert_eq!(token_b.balance(*trader), balance + payout);
+    }
+}
+
+#[test]
+fn circuit_breaker() {
+    use dex_factory_io::{AdminAction, Error as FactoryError, Event as FactoryEvent};
+
+    const SWAP_AMOUNT: u128 = 20000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let pair_actor = factory.create_pair(actor_pair).succeed((actor_pair, 1));
+    let mut pair = Pair(system.get_program(pair_actor));
+
+    factory
+        .state()
+        .pair_circuit_breaker()
+        .eq(CircuitBreaker::default());
+    pair.state().circuit_breaker().eq(CircuitBreaker::default());
+
+    // Broadcasting from the factory
+
+    let circuit_breaker = CircuitBreaker {
+        max_deviation: 1000,
+        twap_window: 60000,
+        max_trade_size: 500,
+    };
+
+    pair.update_circuit_breaker(USERS[0], circuit_breaker)
+        .failed(Error::AccessRestricted);
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::PairCircuitBreaker(CircuitBreaker {
+                twap_window: 0,
+                ..circuit_breaker
+            }),
+        )
+        .failed(FactoryError::InvalidPairCircuitBreaker);
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::PairCircuitBreaker(CircuitBreaker {
+                max_trade_size: BPS_DENOMINATOR + 1,
+                ..circuit_breaker
+            }),
+        )
+        .failed(FactoryError::InvalidPairCircuitBreaker);
+    factory
+        .queue_and_execute(USERS[0], AdminAction::PairCircuitBreaker(circuit_breaker))
+        .succeed(FactoryEvent::PairCircuitBreakerSet {
+            circuit_breaker,
+            unnotified_pairs: vec![],
+        });
+
+    factory.state().pair_circuit_breaker().eq(circuit_breaker);
+    pair.state().circuit_breaker().eq(circuit_breaker);
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_b.mint(USERS[0], INIT_AMOUNT);
+    fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_AMOUNT);
+    fungible_token_b.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    system.spend_blocks(10);
+
+    // The max trade size
+
+    pair.swap_exact_tokens_for_tokens(
+        USERS[0],
+        (INIT_LIQ * 5 / 100 + 1, 0),
+        USERS[0],
+        SwapKind::AForB,
+    )
+    .failed(Error::TradeTooLarge);
+
+    // The max deviation from the TWAP
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .failed(Error::PriceDeviationExceeded);
+
+    fungible_token_a
+        .balance(USERS[0])
+        .contains(INIT_AMOUNT - INIT_LIQ - SWAP_AMOUNT);
+
+    // Disabled limits
+
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::PairCircuitBreaker(CircuitBreaker::default()),
+        )
+        .succeed(FactoryEvent::PairCircuitBreakerSet {
+            circuit_breaker: CircuitBreaker::default(),
+            unnotified_pairs: vec![],
+        });
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    pair.swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+}
+
+#[test]
+fn token_standards() {
+    use dex_factory_io::{sort_pair, AdminAction, Error as FactoryError, Event as FactoryEvent};
+
+    const LIQ: u128 = 100000;
+    const SWAP_AMOUNT: u128 = 10000;
+
+    let system = utils::initialize_system();
+
+    let mut fungible_token_c = FungibleToken::initialize(&system);
+    let mut fungible_token_b = FungibleToken::initialize(&system);
+    let mut fungible_token_a = FungibleToken::initialize(&system);
+    let actor_pair_ab = (fungible_token_a.actor_id(), fungible_token_b.actor_id());
+    let actor_pair_ac = (fungible_token_a.actor_id(), fungible_token_c.actor_id());
+
+    let mut factory = Factory::initialize(&system, 0, USERS[0], 3).succeed();
+    let mut pair_ab = Pair(
+        system.get_program(
+            factory
+                .create_pair(actor_pair_ab)
+                .succeed((actor_pair_ab, 1)),
+        ),
+    );
+    let mut pair_ac = Pair(
+        system.get_program(
+            factory
+                .create_pair(actor_pair_ac)
+                .succeed((actor_pair_ac, 2)),
+        ),
+    );
+
+    // Liquidity tokens of Pair contracts are gear-lib fungible tokens
+
+    fungible_token_a.mint(USERS[0], INIT_AMOUNT);
+
+    for (fungible_token, pair) in [
+        (&mut fungible_token_b, &mut pair_ab),
+        (&mut fungible_token_c, &mut pair_ac),
+    ] {
+        fungible_token.mint(USERS[0], INIT_LIQ);
+        fungible_token.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+        fungible_token_a.approve(USERS[0], pair.actor_id(), INIT_LIQ);
+        pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+            .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+    }
+
+    factory
+        .queue_and_execute(
+            USERS[0],
+            AdminAction::TokenStandard {
+                token: ActorId::zero(),
+                standard: TokenStandard::GearLibFt,
+            },
+        )
+        .failed(FactoryError::ZeroActorId);
+
+    for pair in [&pair_ab, &pair_ac] {
+        factory
+            .queue_and_execute(
+                USERS[0],
+                AdminAction::TokenStandard {
+                    token: pair.actor_id(),
+                    standard: TokenStandard::GearLibFt,
+                },
+            )
+            .succeed(FactoryEvent::TokenStandardSet {
+                token: pair.actor_id(),
+                standard: TokenStandard::GearLibFt,
+            });
+        factory
+            .state()
+            .token_standard(pair.actor_id())
+            .eq(TokenStandard::GearLibFt);
+    }
+
+    factory
+        .state()
+        .token_standard(fungible_token_a.actor_id())
+        .eq(TokenStandard::ShardedFt);
+
+    // A pair of liquidity tokens
+
+    let lp_token = sort_pair((pair_ab.actor_id(), pair_ac.actor_id()));
+    let mut lp_pair =
+        Pair(system.get_program(factory.create_pair(lp_token).succeed((lp_token, 3))));
+
+    lp_pair
+        .state()
+        .token_standard()
+        .eq((TokenStandard::GearLibFt, TokenStandard::GearLibFt));
+
+    for pair in [&mut pair_ab, &mut pair_ac] {
+        pair.approve(USERS[0], lp_pair.actor_id(), LIQ + SWAP_AMOUNT)
+            .succeed(());
+    }
+
+    lp_pair
+        .add_liquidity(USERS[0], (LIQ, LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (LIQ, LIQ), LIQ - MINIMUM_LIQUIDITY as u128));
+
+    pair_ab.state().balance_of(lp_pair.actor_id()).eq(LIQ);
+    pair_ac.state().balance_of(lp_pair.actor_id()).eq(LIQ);
+
+    let out_amount = lp_pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    lp_pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .succeed((
+            USERS[0],
+            (SWAP_AMOUNT, out_amount),
+            USERS[0],
+            SwapKind::AForB,
+        ));
+
+    // The contract transfers its own liquidity tokens without an approval,
+    // & balances are queried with the standard of each token.
+
+    lp_pair
+        .sync()
+        .succeed((LIQ + SWAP_AMOUNT, LIQ - out_amount));
+}
+
+#[test]
+fn gear_lib_ft_replays() {
+    const SWAP_AMOUNT: u128 = 10000;
+    // A block takes 1 second in gtest.
+    const EXPIRY_BLOCKS: u32 = (MIN_TX_EXPIRY / 1000) as u32 + 1;
+
+    let system = utils::initialize_system();
+
+    let mut token_a = MockToken::initialize(&system, TokenStandard::GearLibFt);
+    let mut token_b = MockToken::initialize(&system, TokenStandard::GearLibFt);
+    let actor_pair = (token_a.actor_id(), token_b.actor_id());
+    let mut pair = Pair::initialize_with_token_standard(
+        &system,
+        actor_pair,
+        (TokenStandard::GearLibFt, TokenStandard::GearLibFt),
+        USERS[1],
+        3,
+    )
+    .succeed();
+    let tx_config = TxConfig {
+        expiry: MIN_TX_EXPIRY,
+        ..Default::default()
+    };
+
+    pair.update_tx_config(USERS[1], tx_config)
+        .succeed(tx_config);
+    token_a.set_holder(pair.actor_id());
+    token_b.set_holder(pair.actor_id());
+
+    token_a.mint(USERS[0], 2 * INIT_LIQ + 2 * SWAP_AMOUNT);
+    token_b.mint(USERS[0], 2 * INIT_LIQ);
+    pair.add_liquidity(USERS[0], (INIT_LIQ, INIT_LIQ), (0, 0), USERS[0])
+        .succeed((USERS[0], (INIT_LIQ, INIT_LIQ), CLEAN_INIT_LIQ));
+
+    let swap = InnerAction::SwapExactTokensForTokens {
+        amount_in: SWAP_AMOUNT,
+        amount_out_min: 0,
+        to: USERS[0].into(),
+        deadline: DEADLINE,
+        swap_kind: SwapKind::AForB,
+    };
+
+    // A resumed transaction doesn't pull a deposit that has got a reply
+    // again.
+
+    let out_amount = pair
+        .state()
+        .calculate_out_amount(SwapKind::AForB, SWAP_AMOUNT)
+        .0
+        .unwrap();
+
+    token_b.respond(Response::Silence);
+    assert!(!pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .result
+        .main_failed());
+    assert_eq!(token_a.transfers(), 2);
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_b.respond(Response::Execute);
+
+    pair.retry(USERS[0], swap).succeed(Event::Swap {
+        sender: USERS[0].into(),
+        in_amount: SWAP_AMOUNT,
+        out_amount,
+        to: USERS[0].into(),
+        kind: SwapKind::AForB,
+    });
+    assert_eq!(token_a.transfers(), 2);
+    assert_eq!(token_a.balance(USERS[0]), INIT_LIQ + SWAP_AMOUNT);
+    assert_eq!(token_b.balance(USERS[0]), INIT_LIQ + out_amount);
+
+    let reserve = (INIT_LIQ + SWAP_AMOUNT, INIT_LIQ - out_amount);
+
+    pair.state().reserve().eq(reserve);
+
+    // An abandoned transaction doesn't pull a deposit that has got a reply
+    // again before refunding it, & doesn't repeat one without a reply...
+
+    let (amount_a, amount_b) = (reserve.0, reserve.1);
+
+    token_b.respond(Response::Silence);
+    assert!(!pair
+        .add_liquidity(USERS[0], (amount_a, amount_b), (0, 0), USERS[0])
+        .result
+        .main_failed());
+    assert_eq!(token_a.transfers(), 3);
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_b.respond(Response::Execute);
+
+    pair.abandon(USERS[1], USERS[0]).succeed(Event::Abandoned {
+        actor: USERS[0].into(),
+        depositor: USERS[0].into(),
+        refund: (amount_a, 0),
+    });
+    assert_eq!(token_a.transfers(), 4);
+    assert_eq!(token_b.transfers(), 2);
+    assert_eq!(token_a.balance(USERS[0]), INIT_LIQ + SWAP_AMOUNT);
+    assert_eq!(token_b.balance(USERS[0]), INIT_LIQ + out_amount);
+    pair.state().reserve().eq(reserve);
+
+    // ...but refunds the latter if the contract's balance shows it was
+    // executed.
+
+    token_b.respond(Response::ExecuteSilently);
+    assert!(!pair
+        .add_liquidity(USERS[0], (amount_a, amount_b), (0, 0), USERS[0])
+        .result
+        .main_failed());
+    assert_eq!(token_b.transfers(), 3);
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_b.respond(Response::Execute);
+
+    pair.abandon(USERS[1], USERS[0]).succeed(Event::Abandoned {
+        actor: USERS[0].into(),
+        depositor: USERS[0].into(),
+        refund: (amount_a, amount_b),
+    });
+    assert_eq!(token_a.transfers(), 6);
+    assert_eq!(token_b.transfers(), 4);
+    assert_eq!(token_a.balance(USERS[0]), INIT_LIQ + SWAP_AMOUNT);
+    assert_eq!(token_b.balance(USERS[0]), INIT_LIQ + out_amount);
+    pair.state().reserve().eq(reserve);
+
+    // A failed transfer fails again instead of being sent when its
+    // transaction is resumed.
+
+    token_a.queue(&[Response::Execute]);
+    token_a.respond(Response::Silence);
+    token_b.respond(Response::Reject);
+    assert!(!pair
+        .swap_exact_tokens_for_tokens(USERS[0], (SWAP_AMOUNT, 0), USERS[0], SwapKind::AForB)
+        .result
+        .main_failed());
+
+    system.spend_blocks(EXPIRY_BLOCKS);
+    token_a.respond(Response::Execute);
+    token_b.respond(Response::Execute);
+
+    pair.retry(USERS[0], swap).failed(Error::TransferFailed);
+    pair.state().cached_tx(USERS[0]).eq(None);
+    assert_eq!(token_a.transfers(), 8);
+    assert_eq!(token_b.transfers(), 4);
+    assert_eq!(token_a.balance(USERS[0]), INIT_LIQ + SWAP_AMOUNT);
+    assert_eq!(token_b.balance(USERS[0]), INIT_LIQ + out_amount);
+    pair.state().reserve().eq(reserve);
+}
@@ -1,5 +1,5 @@
 use gstd::{fmt::Debug, marker::PhantomData, prelude::*, ActorId};
-use gtest::{Log, Program as InnerProgram, RunResult as InnerRunResult, System};
+use gtest::{Log, Program as InnerProgram, RunResult as InnerRunResult, System, WasmProgram};
 use pretty_assertions::assert_eq;
 
 pub fn initialize_system() -> System {
@@ -20,6 +20,51 @@ pub trait Program {
     }
 }
 
+/// A program that never replies, so actions awaiting its replies stay in
+/// progress.
+pub struct SilentProgram<'a>(pub InnerProgram<'a>);
+
+impl Program for SilentProgram<'_> {
+    fn inner_program(&self) -> &InnerProgram {
+        &self.0
+    }
+}
+
+impl<'a> SilentProgram<'a> {
+    pub fn initialize(system: &'a System) -> Self {
+        let program = InnerProgram::mock(system, Silent);
+
+        assert!(!program.send(super::FOREIGN_USER, 0u8).main_failed());
+
+        Self(program)
+    }
+}
+
+#[derive(Debug)]
+struct Silent;
+
+impl WasmProgram for Silent {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Err("the program doesn't have a state")
+    }
+}
+
 pub trait TransactionalProgram {
     fn previous_mut_transaction_id(&mut self) -> &mut u64;
 
@@ -108,6 +153,12 @@ impl<T, E: Encode> InitResult<T, E> {
 
         self.contract_instance
     }
+
+    #[track_caller]
+    pub fn failed(self, error: E) {
+        assert!(!self.is_active);
+        self.assert_contains(Err::<(), _>(error));
+    }
 }
 
 #[track_caller]
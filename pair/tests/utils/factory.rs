@@ -1,13 +1,17 @@
-use super::{common::StateReply, InitResult, Program, RunResult, FOREIGN_USER};
+use super::{common::StateReply, InitResult, Program, RunResult, DEADLINE, FOREIGN_USER};
 use dex_factory::WASM_BINARY_OPT;
 use dex_factory_io::*;
 use dex_factory_state::{WASM_BINARY, WASM_EXPORTS};
-use gstd::{prelude::*, ActorId};
-use gtest::{Program as InnerProgram, System};
+use dex_pair_io::DEFAULT_FEE_TIER;
+use gstd::{prelude::*, ActorId, CodeId};
+use gtest::{Program as InnerProgram, RunResult as InnerRunResult, System};
 
 type FactoryRunResult<T, R> = RunResult<T, R, Event, Error>;
 
-pub struct Factory<'a>(InnerProgram<'a>);
+/// The 0.05%, 0.3%, & 1% fee tiers.
+pub const FEE_TIERS: &[FeeTier] = &[5, DEFAULT_FEE_TIER, 100];
+
+pub struct Factory<'a>(InnerProgram<'a>, &'a System);
 
 impl Program for Factory<'_> {
     fn inner_program(&self) -> &InnerProgram {
@@ -19,7 +23,28 @@ impl<'a> Factory<'a> {
     pub fn initialize(
         system: &'a System,
         fee_to: u64,
-        fee_to_setter: u64,
+        admin: u64,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        Self::initialize_with_timelock_delay(system, fee_to, admin, 0, id)
+    }
+
+    pub fn initialize_with_timelock_delay(
+        system: &'a System,
+        fee_to: u64,
+        admin: u64,
+        timelock_delay: u64,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        Self::initialize_with_multisig(system, fee_to, &[admin], 1, timelock_delay, id)
+    }
+
+    pub fn initialize_with_multisig(
+        system: &'a System,
+        fee_to: u64,
+        admins: &[u64],
+        threshold: u32,
+        timelock_delay: u64,
         id: u64,
     ) -> InitResult<Self, Error> {
         let program =
@@ -32,13 +57,16 @@ impl<'a> Factory<'a> {
             FOREIGN_USER,
             Initialize {
                 fee_to: fee_to.into(),
-                fee_to_setter: fee_to_setter.into(),
+                admins: admins.iter().copied().map(Into::into).collect(),
+                threshold,
                 pair: pair_code_id.into(),
+                fee_tiers: FEE_TIERS.into(),
+                timelock_delay,
             },
         );
         let is_active = system.is_active_program(program.id());
 
-        InitResult::new(Self(program), result, is_active)
+        InitResult::new(Self(program, system), result, is_active)
     }
 
     pub fn create_pair(
@@ -46,17 +74,62 @@ impl<'a> Factory<'a> {
         pair: (ActorId, ActorId),
     ) -> FactoryRunResult<((ActorId, ActorId), u32), [u8; 32]> {
         RunResult::new(
-            self.0
-                .send(FOREIGN_USER, Action::CreatePair(pair.0, pair.1)),
+            self.send(
+                FOREIGN_USER,
+                InnerAction::CreatePair(pair.0, pair.1, DEFAULT_FEE_TIER),
+            ),
             |event, (token_pair, pair_number)| {
-                if let Event::PairCreated {
+                check_pair_created(event, (token_pair, DEFAULT_FEE_TIER, pair_number))
+            },
+        )
+    }
+
+    pub fn create_pair_with_fee_tier(
+        &mut self,
+        pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    ) -> FactoryRunResult<((ActorId, ActorId), FeeTier, u32), [u8; 32]> {
+        RunResult::new(
+            self.send(
+                FOREIGN_USER,
+                InnerAction::CreatePair(pair.0, pair.1, fee_tier),
+            ),
+            check_pair_created,
+        )
+    }
+
+    pub fn create_pair_with_liquidity(
+        &mut self,
+        from: u64,
+        pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+        amount: (u128, u128),
+    ) -> FactoryRunResult<((ActorId, ActorId), FeeTier, u32, u128), [u8; 32]> {
+        RunResult::new(
+            self.send(
+                from,
+                InnerAction::CreatePairWithLiquidity {
+                    token_a: pair.0,
+                    token_b: pair.1,
+                    fee_tier,
+                    amount_a: amount.0,
+                    amount_b: amount.1,
+                    deadline: DEADLINE,
+                },
+            ),
+            |event, (token_pair, fee_tier, pair_number, liquidity)| {
+                if let Event::PairCreatedWithLiquidity {
                     token_pair: true_token_pair,
+                    fee_tier: true_fee_tier,
                     pair_actor,
                     pair_number: true_pair_number,
+                    liquidity: true_liquidity,
                 } = event
                 {
                     assert_eq!(token_pair, true_token_pair);
+                    assert_eq!(fee_tier, true_fee_tier);
                     assert_eq!(pair_number, true_pair_number);
+                    assert_eq!(liquidity.into(), true_liquidity);
 
                     pair_actor.into()
                 } else {
@@ -66,29 +139,134 @@ impl<'a> Factory<'a> {
         )
     }
 
-    pub fn fee_to(&mut self, from: u64, to: impl Into<ActorId>) -> FactoryRunResult<ActorId, ()> {
+    pub fn propose(&mut self, from: u64, proposal: Proposal) -> FactoryRunResult<Event, ()> {
         RunResult::new(
-            self.0.send(from, Action::FeeTo(to.into())),
-            |event, fee_to| {
-                assert_eq!(event, Event::FeeToSet(fee_to));
-            },
+            self.send(from, InnerAction::Propose(proposal)),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn confirm(&mut self, from: u64, id: u64) -> FactoryRunResult<Event, ()> {
+        RunResult::new(
+            self.send(from, InnerAction::Confirm(id)),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn revoke(&mut self, from: u64, id: u64) -> FactoryRunResult<(u64, u32), ()> {
+        RunResult::new(
+            self.send(from, InnerAction::Revoke(id)),
+            |event, (id, confirmations)| assert_eq!(event, Event::Revoked { id, confirmations }),
         )
     }
 
-    pub fn fee_to_setter(
+    pub fn queue(
         &mut self,
         from: u64,
-        to: impl Into<ActorId>,
-    ) -> FactoryRunResult<u64, ()> {
+        action: AdminAction,
+        eta: u64,
+    ) -> FactoryRunResult<(u64, AdminAction, u64), ()> {
         RunResult::new(
-            self.0.send(from, Action::FeeToSetter(to.into())),
-            |event, fee_to_setter| assert!(event == Event::FeeToSetterSet(fee_to_setter.into())),
+            self.send(from, InnerAction::Propose(Proposal::Queue { action, eta })),
+            |event, (id, action, eta)| assert_eq!(event, Event::Queued { id, action, eta }),
         )
     }
 
+    pub fn execute(&mut self, from: u64, id: u64) -> FactoryRunResult<Event, ()> {
+        RunResult::new(
+            self.send(from, InnerAction::Execute(id)),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn cancel(&mut self, from: u64, id: u64) -> FactoryRunResult<u64, ()> {
+        RunResult::new(
+            self.send(from, InnerAction::Propose(Proposal::Cancel(id))),
+            |event, id| assert_eq!(event, Event::Canceled(id)),
+        )
+    }
+
+    /// Proposes to queue an action with the earliest possible ETA &, if the
+    /// action is queued and the timelock delay is 0, immediately executes it.
+    fn queue_and_execute_with_check<T>(
+        &mut self,
+        from: u64,
+        action: AdminAction,
+        check: fn(Event, T),
+    ) -> FactoryRunResult<T, ()> {
+        let result = self.send(
+            from,
+            InnerAction::Propose(Proposal::Queue {
+                action,
+                eta: self.1.block_timestamp(),
+            }),
+        );
+
+        if let Ok(Ok(Event::Queued { id, .. })) =
+            Result::<Event, Error>::decode(&mut result.log()[0].payload())
+        {
+            RunResult::new(self.send(from, InnerAction::Execute(id)), check)
+        } else {
+            RunResult::new(result, check)
+        }
+    }
+
+    pub fn queue_and_execute(
+        &mut self,
+        from: u64,
+        action: AdminAction,
+    ) -> FactoryRunResult<Event, ()> {
+        self.queue_and_execute_with_check(from, action, |event, true_event| {
+            assert_eq!(event, true_event)
+        })
+    }
+
+    pub fn fee_to(&mut self, from: u64, to: impl Into<ActorId>) -> FactoryRunResult<ActorId, ()> {
+        self.queue_and_execute_with_check(from, AdminAction::FeeTo(to.into()), |event, fee_to| {
+            assert_eq!(
+                event,
+                Event::FeeToChanged {
+                    fee_to,
+                    unnotified_pairs: vec![]
+                }
+            )
+        })
+    }
+
+    pub fn query(&mut self, action: InnerAction) -> FactoryRunResult<Event, ()> {
+        RunResult::new(self.send(FOREIGN_USER, action), |event, true_event| {
+            assert_eq!(event, true_event)
+        })
+    }
+
     pub fn state(&self) -> FactoryState {
         FactoryState(&self.0)
     }
+
+    fn send(&self, from: u64, action: InnerAction) -> InnerRunResult {
+        self.0.send(from, Action::new(action))
+    }
+}
+
+fn check_pair_created(
+    event: Event,
+    (token_pair, fee_tier, pair_number): ((ActorId, ActorId), FeeTier, u32),
+) -> [u8; 32] {
+    if let Event::PairCreated {
+        token_pair: true_token_pair,
+        fee_tier: true_fee_tier,
+        pair_actor,
+        pair_number: true_pair_number,
+    } = event
+    {
+        assert_eq!(token_pair, true_token_pair);
+        assert_eq!(fee_tier, true_fee_tier);
+        assert_eq!(pair_number, true_pair_number);
+
+        pair_actor.into()
+    } else {
+        unreachable!()
+    }
 }
 
 pub struct FactoryState<'a>(&'a InnerProgram<'a>);
@@ -122,19 +300,82 @@ impl FactoryState<'_> {
         self.query_state(1)
     }
 
-    pub fn fee_to_setter(self) -> StateReply<ActorId> {
+    pub fn admins(self) -> StateReply<Vec<ActorId>> {
         self.query_state(2)
     }
 
     pub fn pair(self, pair: (ActorId, ActorId)) -> StateReply<ActorId> {
-        self.query_state_with_argument(3, pair)
+        self.pair_with_fee_tier(pair, DEFAULT_FEE_TIER)
+    }
+
+    pub fn pair_with_fee_tier(
+        self,
+        pair: (ActorId, ActorId),
+        fee_tier: FeeTier,
+    ) -> StateReply<ActorId> {
+        self.query_state_with_argument(3, (pair, fee_tier))
     }
 
     pub fn all_pairs_length(self) -> StateReply<u32> {
         self.query_state(4)
     }
 
-    pub fn all_pairs(self) -> StateReply<Vec<((ActorId, ActorId), ActorId)>> {
+    pub fn all_pairs(self) -> StateReply<Vec<((ActorId, ActorId, FeeTier), ActorId)>> {
         self.query_state(5)
     }
+
+    pub fn timelock_delay(self) -> StateReply<u64> {
+        self.query_state(6)
+    }
+
+    pub fn queued_operations(self) -> StateReply<Vec<(u64, QueuedOperation)>> {
+        self.query_state(7)
+    }
+
+    pub fn threshold(self) -> StateReply<u32> {
+        self.query_state(9)
+    }
+
+    pub fn proposals(self) -> StateReply<Vec<(u64, PendingProposal)>> {
+        self.query_state(10)
+    }
+
+    pub fn fee_tiers(self) -> StateReply<Vec<FeeTier>> {
+        self.query_state(11)
+    }
+
+    pub fn pairs_for(self, pair: (ActorId, ActorId)) -> StateReply<Vec<(FeeTier, ActorId)>> {
+        self.query_state_with_argument(12, pair)
+    }
+
+    pub fn pair_code(self) -> StateReply<CodeId> {
+        self.query_state(13)
+    }
+
+    pub fn pairs_by_creator(
+        self,
+        creator: impl Into<ActorId>,
+    ) -> StateReply<Vec<((ActorId, ActorId, FeeTier), PairRecord)>> {
+        self.query_state_with_argument(15, creator.into())
+    }
+
+    pub fn pairs_created_between(
+        self,
+        from: u64,
+        to: u64,
+    ) -> StateReply<Vec<((ActorId, ActorId, FeeTier), PairRecord)>> {
+        self.query_state_with_argument(16, (from, to))
+    }
+
+    pub fn pair_tx_config(self) -> StateReply<TxConfig> {
+        self.query_state(17)
+    }
+
+    pub fn pair_circuit_breaker(self) -> StateReply<CircuitBreaker> {
+        self.query_state(18)
+    }
+
+    pub fn token_standard(self, token: impl Into<ActorId>) -> StateReply<TokenStandard> {
+        self.query_state_with_argument(19, token.into())
+    }
 }
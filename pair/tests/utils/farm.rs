@@ -0,0 +1,237 @@
+use super::{common::StateReply, InitResult, Program, RunResult, FOREIGN_USER};
+use dex_farm::WASM_BINARY_OPT;
+use dex_farm_io::*;
+use dex_farm_state::{WASM_BINARY, WASM_EXPORTS};
+use gstd::{prelude::*, ActorId};
+use gtest::{Program as InnerProgram, RunResult as InnerRunResult, System};
+use primitive_types::U256;
+
+type FarmRunResult<T, R = ()> = RunResult<T, R, Event, Error>;
+
+pub struct Farm<'a>(InnerProgram<'a>);
+
+impl Program for Farm<'_> {
+    fn inner_program(&self) -> &InnerProgram {
+        &self.0
+    }
+}
+
+impl<'a> Farm<'a> {
+    pub fn initialize(
+        system: &'a System,
+        admin: u64,
+        factory: ActorId,
+        reward_token: ActorId,
+        reward_per_second: u128,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        let program =
+            InnerProgram::from_opt_and_meta_code_with_id(system, id, WASM_BINARY_OPT.into(), None);
+
+        let result = program.send(
+            FOREIGN_USER,
+            Initialize {
+                admin: admin.into(),
+                factory,
+                reward_token,
+                reward_per_second,
+            },
+        );
+        let is_active = system.is_active_program(program.id());
+
+        InitResult::new(Self(program), result, is_active)
+    }
+
+    pub fn add_pool(
+        &mut self,
+        from: u64,
+        pair: ActorId,
+        alloc_point: u32,
+    ) -> FarmRunResult<PoolId> {
+        RunResult::new(
+            self.send(from, InnerAction::AddPool { pair, alloc_point }),
+            |event, pool_id| {
+                if let Event::PoolAdded {
+                    pool_id: true_pool_id,
+                    ..
+                } = event
+                {
+                    assert_eq!(pool_id, true_pool_id)
+                } else {
+                    unreachable!()
+                }
+            },
+        )
+    }
+
+    pub fn set_pool(
+        &mut self,
+        from: u64,
+        pool_id: PoolId,
+        alloc_point: u32,
+    ) -> FarmRunResult<(PoolId, u32)> {
+        RunResult::new(
+            self.send(
+                from,
+                InnerAction::SetPool {
+                    pool_id,
+                    alloc_point,
+                },
+            ),
+            |event, (pool_id, alloc_point)| {
+                assert_eq!(
+                    event,
+                    Event::PoolSet {
+                        pool_id,
+                        alloc_point
+                    }
+                )
+            },
+        )
+    }
+
+    pub fn set_reward_per_second(
+        &mut self,
+        from: u64,
+        reward_per_second: u128,
+    ) -> FarmRunResult<u128> {
+        RunResult::new(
+            self.send(from, InnerAction::SetRewardPerSecond(reward_per_second)),
+            |event, reward_per_second| {
+                assert_eq!(event, Event::RewardPerSecondSet(reward_per_second))
+            },
+        )
+    }
+
+    pub fn deposit(
+        &mut self,
+        from: u64,
+        pool_id: PoolId,
+        amount: u128,
+    ) -> FarmRunResult<(PoolId, u64, u128)> {
+        RunResult::new(
+            self.send(
+                from,
+                InnerAction::Deposit {
+                    pool_id,
+                    amount: amount.into(),
+                },
+            ),
+            |event, (pool_id, user, amount)| {
+                assert_eq!(
+                    event,
+                    Event::Deposited {
+                        pool_id,
+                        user: user.into(),
+                        amount: amount.into(),
+                    }
+                )
+            },
+        )
+    }
+
+    pub fn withdraw(
+        &mut self,
+        from: u64,
+        pool_id: PoolId,
+        amount: u128,
+    ) -> FarmRunResult<(PoolId, u64, u128)> {
+        RunResult::new(
+            self.send(
+                from,
+                InnerAction::Withdraw {
+                    pool_id,
+                    amount: amount.into(),
+                },
+            ),
+            |event, (pool_id, user, amount)| {
+                assert_eq!(
+                    event,
+                    Event::Withdrawn {
+                        pool_id,
+                        user: user.into(),
+                        amount: amount.into(),
+                    }
+                )
+            },
+        )
+    }
+
+    pub fn claim(&mut self, from: u64, pool_id: PoolId) -> FarmRunResult<(PoolId, u64, u128)> {
+        RunResult::new(
+            self.send(from, InnerAction::Claim(pool_id)),
+            |event, (pool_id, user, reward)| {
+                assert_eq!(
+                    event,
+                    Event::Claimed {
+                        pool_id,
+                        user: user.into(),
+                        reward,
+                    }
+                )
+            },
+        )
+    }
+
+    pub fn state(&self) -> FarmState {
+        FarmState(&self.0)
+    }
+
+    fn send(&self, from: u64, action: InnerAction) -> InnerRunResult {
+        self.0.send(from, Action::new(action))
+    }
+}
+
+pub struct FarmState<'a>(&'a InnerProgram<'a>);
+
+impl FarmState<'_> {
+    fn query_state_common<A: Encode, T: Decode>(
+        self,
+        fn_index: usize,
+        argument: Option<A>,
+    ) -> StateReply<T> {
+        StateReply(
+            self.0
+                .read_state_using_wasm(WASM_EXPORTS[fn_index], WASM_BINARY.into(), argument)
+                .unwrap(),
+        )
+    }
+
+    fn query_state_with_argument<A: Encode, T: Decode>(
+        self,
+        fn_index: usize,
+        argument: A,
+    ) -> StateReply<T> {
+        self.query_state_common(fn_index, Some(argument))
+    }
+
+    fn query_state<T: Decode>(self, fn_index: usize) -> StateReply<T> {
+        self.query_state_common::<(), _>(fn_index, None)
+    }
+
+    pub fn stake(self, pool_id: PoolId, user: impl Into<ActorId>) -> StateReply<u128> {
+        StateReply(
+            self.query_state_with_argument::<_, Stake>(3, (pool_id, user.into()))
+                .0
+                .amount
+                .low_u128(),
+        )
+    }
+
+    pub fn pending_reward(
+        self,
+        pool_id: PoolId,
+        user: impl Into<ActorId>,
+        block_ts: u64,
+    ) -> StateReply<u128> {
+        StateReply(
+            self.query_state_with_argument::<_, U256>(4, (pool_id, user.into(), block_ts))
+                .0
+                .low_u128(),
+        )
+    }
+
+    pub fn total_alloc_point(self) -> StateReply<u64> {
+        self.query_state(6)
+    }
+}
@@ -0,0 +1,237 @@
+use super::{common::Program, FOREIGN_USER};
+use dex_pair_io::{Action, Error, Event, InnerAction, TokenStandard};
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gear_lib::tokens::fungible::FTTransfer;
+use gstd::{prelude::*, ActorId};
+use gtest::{Program as InnerProgram, System, WasmProgram};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
+
+/// A response of [`MockToken`] to transfers.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    /// Executes transfers.
+    #[default]
+    Execute,
+    /// Replies to transfers with an error.
+    Reject,
+    /// Panics on transfers.
+    Panic,
+    /// Doesn't reply to transfers, so actions awaiting them stay in progress.
+    Silence,
+    /// Executes transfers without replying to them.
+    ExecuteSilently,
+}
+
+#[derive(Default, Debug)]
+struct MockState {
+    standard: TokenStandard,
+    response: Response,
+    queued_responses: VecDeque<Response>,
+    balances: HashMap<ActorId, u128>,
+    /// Executed transaction IDs of sharded FT transfers.
+    transaction_ids: HashSet<u64>,
+    /// The sender of gear-lib FT `InnerAction::Transfer`s since a mock can't
+    /// get the source of a message.
+    holder: ActorId,
+    transfers: u32,
+}
+
+/// A fungible token of a given [`TokenStandard`] whose responses to transfers
+/// are set by a test.
+pub struct MockToken<'a>(InnerProgram<'a>, Rc<RefCell<MockState>>);
+
+impl Program for MockToken<'_> {
+    fn inner_program(&self) -> &InnerProgram {
+        &self.0
+    }
+}
+
+impl<'a> MockToken<'a> {
+    pub fn initialize(system: &'a System, standard: TokenStandard) -> Self {
+        let state = Rc::new(RefCell::new(MockState {
+            standard,
+            ..Default::default()
+        }));
+        let program = InnerProgram::mock(system, Mock(state.clone()));
+
+        assert!(!program.send(FOREIGN_USER, 0u8).main_failed());
+
+        Self(program, state)
+    }
+
+    pub fn mint(&mut self, actor: impl Into<ActorId>, amount: u128) {
+        *self
+            .1
+            .borrow_mut()
+            .balances
+            .entry(actor.into())
+            .or_default() += amount;
+    }
+
+    pub fn respond(&mut self, response: Response) {
+        self.1.borrow_mut().response = response;
+    }
+
+    /// Sets responses to the next transfers, after which transfers get the
+    /// [`MockToken::respond()`] one.
+    pub fn queue(&mut self, responses: &[Response]) {
+        self.1.borrow_mut().queued_responses = responses.iter().copied().collect();
+    }
+
+    /// Sets the sender of gear-lib FT `InnerAction::Transfer`s (the Pair
+    /// contract).
+    pub fn set_holder(&mut self, holder: ActorId) {
+        self.1.borrow_mut().holder = holder;
+    }
+
+    pub fn balance(&self, actor: impl Into<ActorId>) -> u128 {
+        self.1
+            .borrow()
+            .balances
+            .get(&actor.into())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of executed transfers.
+    pub fn transfers(&self) -> u32 {
+        self.1.borrow().transfers
+    }
+}
+
+#[derive(Debug)]
+struct Mock(Rc<RefCell<MockState>>);
+
+impl Mock {
+    fn transfer(
+        &mut self,
+        (sender, recipient, amount): (ActorId, ActorId, u128),
+        transaction_id: Option<u64>,
+        reply: impl FnOnce(bool) -> Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, &'static str> {
+        let mut state = self.0.borrow_mut();
+        let response = state.queued_responses.pop_front().unwrap_or(state.response);
+
+        match response {
+            Response::Execute | Response::ExecuteSilently => (),
+            Response::Reject => return Ok(Some(reply(false))),
+            Response::Panic => return Err("the token panicked on a transfer"),
+            Response::Silence => return Ok(None),
+        }
+
+        // Sharded FT transfers with executed transaction IDs are skipped.
+        if transaction_id.is_some_and(|id| state.transaction_ids.contains(&id)) {
+            return Ok(Some(reply(true)));
+        }
+
+        let sender_balance = state.balances.entry(sender).or_default();
+
+        let Some(balance) = sender_balance.checked_sub(amount) else {
+            return Ok(Some(reply(false)));
+        };
+
+        *sender_balance = balance;
+        *state.balances.entry(recipient).or_default() += amount;
+        state.transaction_ids.extend(transaction_id);
+        state.transfers += 1;
+
+        Ok((response == Response::Execute).then(|| reply(true)))
+    }
+
+    fn balance(&self, actor: ActorId) -> u128 {
+        self.0
+            .borrow()
+            .balances
+            .get(&actor)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn handle_sharded_ft(&mut self, action: FTokenAction) -> Result<Option<Vec<u8>>, &'static str> {
+        match action {
+            FTokenAction::Message {
+                transaction_id,
+                payload:
+                    LogicAction::Transfer {
+                        sender,
+                        recipient,
+                        amount,
+                    },
+            } => self.transfer((sender, recipient, amount), Some(transaction_id), |is_ok| {
+                if is_ok {
+                    FTokenEvent::Ok
+                } else {
+                    FTokenEvent::Err
+                }
+                .encode()
+            }),
+            FTokenAction::GetBalance(actor) => {
+                Ok(Some(FTokenEvent::Balance(self.balance(actor)).encode()))
+            }
+            _ => Err("unexpected sharded FT action"),
+        }
+    }
+
+    fn handle_gear_lib_ft(&mut self, action: InnerAction) -> Result<Option<Vec<u8>>, &'static str> {
+        let (from, to, amount) = match action {
+            InnerAction::Transfer { to, amount } => (self.0.borrow().holder, to, amount),
+            InnerAction::TransferFrom { from, to, amount } => (from, to, amount),
+            InnerAction::GetLpBalance(actor) => {
+                return Ok(Some(
+                    Ok::<_, Error>(Event::LpBalance {
+                        actor,
+                        balance: self.balance(actor).into(),
+                    })
+                    .encode(),
+                ))
+            }
+            _ => return Err("unexpected gear-lib FT action"),
+        };
+
+        self.transfer((from, to, amount.low_u128()), None, |is_ok| {
+            if is_ok {
+                Ok(Event::Transfer(FTTransfer { from, to, amount }))
+            } else {
+                Err(Error::InsufficientAmount)
+            }
+            .encode()
+        })
+    }
+}
+
+impl WasmProgram for Mock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let standard = self.0.borrow().standard;
+
+        match standard {
+            TokenStandard::ShardedFt => self.handle_sharded_ft(
+                FTokenAction::decode(&mut &payload[..]).map_err(|_| "failed to decode")?,
+            ),
+            TokenStandard::GearLibFt => self.handle_gear_lib_ft(
+                Action::decode(&mut &payload[..])
+                    .map_err(|_| "failed to decode")?
+                    .action,
+            ),
+        }
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Err("the program doesn't have a state")
+    }
+}
@@ -1,19 +1,23 @@
 use common::{InitResult, Program, RunResult, StateReply, TransactionalProgram};
+use dex_pair::WASM_BINARY_OPT;
 use dex_pair_io::{hidden::U256PairTuple, *};
 use dex_pair_state::{WASM_BINARY, WASM_EXPORTS};
 use gear_lib::tokens::fungible::FTTransfer;
 use gstd::{prelude::*, ActorId};
-use gtest::Program as InnerProgram;
+use gtest::{Program as InnerProgram, System};
 use primitive_types::U256;
 
 mod common;
 mod factory;
+mod farm;
 mod fungible_token;
+mod mock_token;
 
 pub mod prelude;
 
-pub use common::initialize_system;
+pub use common::{initialize_system, SilentProgram};
 pub use fungible_token::FungibleToken;
+pub use mock_token::{MockToken, Response};
 
 pub const FOREIGN_USER: u64 = 1029384756123;
 pub const FT_MAIN: &str = "../target/wasm32-unknown-unknown/debug/ft_main.opt.wasm";
@@ -21,7 +25,7 @@ pub const FT_STORAGE: &str = "../target/wasm32-unknown-unknown/debug/ft_storage.
 pub const FT_LOGIC: &str = "../target/wasm32-unknown-unknown/debug/ft_logic.opt.wasm";
 pub const SPENT_BLOCKS: u32 = 1;
 
-const DEADLINE: u64 = 99999999999999999;
+pub const DEADLINE: u64 = 99999999999999999;
 
 type PairRunResult<T, C = ()> = RunResult<T, C, Event, Error>;
 
@@ -34,6 +38,79 @@ impl Program for Pair<'_> {
 }
 
 impl<'a> Pair<'a> {
+    pub fn initialize(
+        system: &'a System,
+        token: (ActorId, ActorId),
+        factory: impl Into<ActorId>,
+        fee_tier: FeeTier,
+        fee_to: impl Into<ActorId>,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        Self::initialize_with_seeder(system, token, factory, fee_tier, fee_to, None, id)
+    }
+
+    pub fn initialize_with_seeder(
+        system: &'a System,
+        token: (ActorId, ActorId),
+        factory: impl Into<ActorId>,
+        fee_tier: FeeTier,
+        fee_to: impl Into<ActorId>,
+        seeder: Option<u64>,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        Self::initialize_with_config(
+            system,
+            Initialize {
+                pair: token,
+                factory: factory.into(),
+                fee_tier,
+                fee_to: fee_to.into(),
+                seeder: seeder.map(Into::into),
+                tx_config: Default::default(),
+                circuit_breaker: Default::default(),
+                token_standard: Default::default(),
+            },
+            id,
+        )
+    }
+
+    pub fn initialize_with_token_standard(
+        system: &'a System,
+        token: (ActorId, ActorId),
+        token_standard: (TokenStandard, TokenStandard),
+        factory: impl Into<ActorId>,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        Self::initialize_with_config(
+            system,
+            Initialize {
+                pair: token,
+                factory: factory.into(),
+                fee_tier: DEFAULT_FEE_TIER,
+                fee_to: ActorId::zero(),
+                seeder: None,
+                tx_config: Default::default(),
+                circuit_breaker: Default::default(),
+                token_standard,
+            },
+            id,
+        )
+    }
+
+    fn initialize_with_config(
+        system: &'a System,
+        config: Initialize,
+        id: u64,
+    ) -> InitResult<Self, Error> {
+        let program =
+            InnerProgram::from_opt_and_meta_code_with_id(system, id, WASM_BINARY_OPT.into(), None);
+
+        let result = program.send(FOREIGN_USER, config);
+        let is_active = system.is_active_program(program.id());
+
+        InitResult::new(Self(program), result, is_active)
+    }
+
     pub fn add_liquidity(
         &mut self,
         from: u64,
@@ -245,6 +322,164 @@ impl<'a> Pair<'a> {
         )
     }
 
+    pub fn approve(
+        &mut self,
+        from: u64,
+        spender: impl Into<ActorId>,
+        amount: u128,
+    ) -> PairRunResult<()> {
+        RunResult::new(
+            self.0.send(
+                from,
+                Action::new(InnerAction::Approve {
+                    spender: spender.into(),
+                    amount: amount.into(),
+                }),
+            ),
+            |event, _| assert!(matches!(event, Event::Approval(_))),
+        )
+    }
+
+    pub fn update_fee_to(&mut self, from: u64, fee_to: impl Into<ActorId>) -> PairRunResult<u64> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::UpdateFeeTo(fee_to.into()))),
+            |event, fee_to| assert_eq!(event, Event::FeeToUpdated(fee_to.into())),
+        )
+    }
+
+    pub fn refresh_fee_to(&mut self) -> PairRunResult<u64> {
+        RunResult::new(
+            self.0
+                .send(FOREIGN_USER, Action::new(InnerAction::RefreshFeeTo)),
+            |event, fee_to| assert_eq!(event, Event::FeeToUpdated(fee_to.into())),
+        )
+    }
+
+    pub fn update_tx_config(&mut self, from: u64, tx_config: TxConfig) -> PairRunResult<TxConfig> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::UpdateTxConfig(tx_config))),
+            |event, tx_config| assert_eq!(event, Event::TxConfigUpdated(tx_config)),
+        )
+    }
+
+    pub fn update_circuit_breaker(
+        &mut self,
+        from: u64,
+        circuit_breaker: CircuitBreaker,
+    ) -> PairRunResult<CircuitBreaker> {
+        RunResult::new(
+            self.0.send(
+                from,
+                Action::new(InnerAction::UpdateCircuitBreaker(circuit_breaker)),
+            ),
+            |event, circuit_breaker| {
+                assert_eq!(event, Event::CircuitBreakerUpdated(circuit_breaker))
+            },
+        )
+    }
+
+    pub fn abandon(&mut self, from: u64, actor: impl Into<ActorId>) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::Abandon(actor.into()))),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn complete(&mut self, from: u64, actor: impl Into<ActorId>) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::Complete(actor.into()))),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn commit_swap(
+        &mut self,
+        from: u64,
+        hash: [u8; 32],
+        deposit: (u128, u128),
+    ) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::CommitSwap { hash, deposit })),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn reveal_swap(&mut self, from: u64, reveal: SwapReveal) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::RevealSwap(reveal))),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn refund_swap(&mut self, from: u64, actor: impl Into<ActorId>) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::RefundSwap(actor.into()))),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn submit_order(&mut self, from: u64, order: Order) -> PairRunResult<(u64, u64, Order)> {
+        RunResult::new(
+            self.0
+                .send(from, Action::new(InnerAction::SubmitOrder(order))),
+            |event, (actor, batch, order)| {
+                assert_eq!(
+                    event,
+                    Event::OrderSubmitted {
+                        actor: actor.into(),
+                        batch,
+                        order,
+                    }
+                )
+            },
+        )
+    }
+
+    pub fn settle_batch(&mut self, from: u64) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0.send(from, Action::new(InnerAction::SettleBatch)),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn claim(&mut self, from: u64, token: ActorId) -> PairRunResult<(u64, ActorId, u128)> {
+        RunResult::new(
+            self.0.send(from, Action::new(InnerAction::Claim(token))),
+            |event, (actor, token, amount)| {
+                assert_eq!(
+                    event,
+                    Event::Claimed {
+                        actor: actor.into(),
+                        token,
+                        amount
+                    }
+                )
+            },
+        )
+    }
+
+    /// Resumes a cached transaction of `from` with `action`.
+    pub fn retry(&mut self, from: u64, action: InnerAction) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0.send(from, Action::new(action).to_retry()),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
+    pub fn query(&mut self, from: u64, action: InnerAction) -> PairRunResult<Event> {
+        RunResult::new(
+            self.0.send(from, Action::new(action)),
+            |event, true_event| assert_eq!(event, true_event),
+        )
+    }
+
     pub fn state(&self) -> PairState {
         PairState(&self.0)
     }
@@ -317,6 +552,96 @@ impl PairState<'_> {
     ) -> StateReply<Result<u128, Error>> {
         self.query_state_with_argument(10, (swap_kind, out_amount))
     }
+
+    pub fn fee_to(self) -> StateReply<ActorId> {
+        self.query_state(11)
+    }
+
+    pub fn fee_tier(self) -> StateReply<FeeTier> {
+        self.query_state(12)
+    }
+
+    pub fn seeder(self) -> StateReply<Option<ActorId>> {
+        self.query_state(13)
+    }
+
+    pub fn lp_position(self, actor: impl Into<ActorId>) -> StateReply<LpPosition> {
+        self.query_state_with_argument(14, actor.into())
+    }
+
+    pub fn stats(self) -> StateReply<TradingStats> {
+        self.query_state(15)
+    }
+
+    pub fn stats_since(self, since: u64) -> StateReply<TradingStats> {
+        self.query_state_with_argument(16, since)
+    }
+
+    pub fn price_impact(
+        self,
+        swap_kind: SwapKind,
+        in_amount: u128,
+    ) -> StateReply<Result<u16, Error>> {
+        self.query_state_with_argument(17, (swap_kind, in_amount))
+    }
+
+    pub fn max_in_amount(
+        self,
+        swap_kind: SwapKind,
+        max_impact: u16,
+    ) -> StateReply<Result<u128, Error>> {
+        self.query_state_with_argument(18, (swap_kind, max_impact))
+    }
+
+    pub fn amount_out_min(
+        self,
+        swap_kind: SwapKind,
+        in_amount: u128,
+        slippage: u16,
+    ) -> StateReply<Result<u128, Error>> {
+        self.query_state_with_argument(19, (swap_kind, in_amount, slippage))
+    }
+
+    pub fn amount_in_max(
+        self,
+        swap_kind: SwapKind,
+        out_amount: u128,
+        slippage: u16,
+    ) -> StateReply<Result<u128, Error>> {
+        self.query_state_with_argument(20, (swap_kind, out_amount, slippage))
+    }
+
+    pub fn tx_config(self) -> StateReply<TxConfig> {
+        self.query_state(21)
+    }
+
+    pub fn cached_tx(self, actor: impl Into<ActorId>) -> StateReply<Option<CachedTx>> {
+        self.query_state_with_argument(22, actor.into())
+    }
+
+    pub fn owed(self, actor: impl Into<ActorId>, token: ActorId) -> StateReply<u128> {
+        self.query_state_with_argument(23, (actor.into(), token))
+    }
+
+    pub fn lock(self) -> StateReply<Option<Lock>> {
+        self.query_state(24)
+    }
+
+    pub fn commitment(self, actor: impl Into<ActorId>) -> StateReply<Option<Commitment>> {
+        self.query_state_with_argument(25, actor.into())
+    }
+
+    pub fn batch(self) -> StateReply<Option<Batch>> {
+        self.query_state(26)
+    }
+
+    pub fn circuit_breaker(self) -> StateReply<CircuitBreaker> {
+        self.query_state(27)
+    }
+
+    pub fn token_standard(self) -> StateReply<(TokenStandard, TokenStandard)> {
+        self.query_state(28)
+    }
 }
 
 pub fn calculate_cp(reserve: (u128, u128)) -> U256 {
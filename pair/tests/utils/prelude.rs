@@ -1,4 +1,9 @@
-pub use super::{common::Program, factory::Factory, Pair, FOREIGN_USER, SPENT_BLOCKS};
+pub use super::{
+    common::Program,
+    factory::{Factory, FEE_TIERS},
+    farm::Farm,
+    Pair, DEADLINE, FOREIGN_USER, SPENT_BLOCKS,
+};
 pub use dex_pair_io::{hidden::U256PairTuple, *};
 pub use gstd::{prelude::*, ActorId};
 pub use primitive_types::U256;